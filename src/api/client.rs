@@ -1,12 +1,27 @@
-use std::path::PathBuf;
+use std::fs::{self, File, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
 
 use crate::api::models::{
-    AudioTrack, Chapter, LibrariesResponse, Library, LibraryItem, LibraryItemsResponse,
-    MediaProgress, PersonalizedShelf,
+    AudioTrack, AuthorsResponse, Bookmark, Chapter, Collection, CollectionsResponse, CurrentUser,
+    LibrariesResponse, Library, LibraryAuthor, LibraryFilterData, LibraryItem,
+    LibraryItemsResponse, LibrarySearchResponse, MediaProgress, Narrator, NarratorsResponse,
+    PersonalizedShelf, PlaybackSession, Playlist, PlaylistsResponse, Series, SeriesResponse,
+    SessionSync,
 };
+use crate::api::query::{LibraryQuery, group_filter};
 use crate::config::Config;
+use crate::player::commands::AudioSource;
+use reqwest::StatusCode;
 use reqwest::blocking::Client;
+use reqwest::header::RANGE;
 
+const DOWNLOAD_CHUNK: usize = 64 * 1024;
+
+/// Library items fetched per request.
+const ITEMS_PAGE_SIZE: usize = 100;
+
+#[derive(Clone)]
 pub struct ApiClient {
     client: Client,
     base_url: String,
@@ -34,7 +49,13 @@ impl ApiClient {
         Ok(wrapper.libraries)
     }
 
-    pub fn get_library_items(&self, library_id: &str) -> Result<Vec<LibraryItem>, ApiError> {
+    /// Fetches one page of a library's items. Pages count from 0.
+    pub fn get_library_items(
+        &self,
+        library_id: &str,
+        query: &LibraryQuery,
+        page: usize,
+    ) -> Result<LibraryItemsResponse, ApiError> {
         let resp = self
             .client
             .get(format!(
@@ -42,11 +63,129 @@ impl ApiClient {
                 self.base_url, library_id
             ))
             .header("Authorization", format!("Bearer {}", self.api_key))
+            .query(&query.params())
+            .query(&[("limit", ITEMS_PAGE_SIZE), ("page", page)])
             .send()?
             .error_for_status()?;
 
-        let wrapper: LibraryItemsResponse = resp.json()?;
-        Ok(wrapper.results)
+        Ok(resp.json()?)
+    }
+
+    /// Series in a library, each with its books.
+    pub fn get_series(&self, library_id: &str) -> Result<Vec<Series>, ApiError> {
+        let url = format!("{}/api/libraries/{}/series", self.base_url, library_id);
+        let resp = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.api_key)
+            .query(&[("sort", "name")])
+            .send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let series: SeriesResponse = resp.json()?;
+        Ok(series.results)
+    }
+
+    pub fn get_authors(&self, library_id: &str) -> Result<Vec<LibraryAuthor>, ApiError> {
+        let url = format!("{}/api/libraries/{}/authors", self.base_url, library_id);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let authors: AuthorsResponse = resp.json()?;
+        Ok(authors.authors)
+    }
+
+    pub fn get_narrators(&self, library_id: &str) -> Result<Vec<Narrator>, ApiError> {
+        let url = format!("{}/api/libraries/{}/narrators", self.base_url, library_id);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let narrators: NarratorsResponse = resp.json()?;
+        Ok(narrators.narrators)
+    }
+
+    /// All items in a library whose `group` includes `value`, see
+    /// [`group_filter`].
+    pub fn get_group_items(
+        &self,
+        library_id: &str,
+        group: &str,
+        value: &str,
+    ) -> Result<Vec<LibraryItem>, ApiError> {
+        let url = format!("{}/api/libraries/{}/items", self.base_url, library_id);
+        let resp = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.api_key)
+            .query(&[("filter", group_filter(group, value))])
+            .send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let items: LibraryItemsResponse = resp.json()?;
+        Ok(items.results)
+    }
+
+    /// The user's listening progress on every item.
+    pub fn get_all_progress(&self) -> Result<Vec<MediaProgress>, ApiError> {
+        let url = format!("{}/api/me", self.base_url);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let user: CurrentUser = resp.json()?;
+        Ok(user.media_progress)
+    }
+
+    pub fn get_filter_data(&self, library_id: &str) -> Result<LibraryFilterData, ApiError> {
+        let url = format!("{}/api/libraries/{}/filterdata", self.base_url, library_id);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        Ok(resp.json()?)
+    }
+
+    /// Items matching `query` in any field the server indexes.
+    pub fn search_library(
+        &self,
+        library_id: &str,
+        query: &str,
+    ) -> Result<Vec<LibraryItem>, ApiError> {
+        let url = format!("{}/api/libraries/{}/search", self.base_url, library_id);
+        let resp = self
+            .client
+            .get(&url)
+            .bearer_auth(&self.api_key)
+            .query(&[("q", query), ("limit", "100")])
+            .send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let results: LibrarySearchResponse = resp.json()?;
+        Ok(results
+            .book
+            .into_iter()
+            .chain(results.podcast)
+            .map(|r| r.library_item)
+            .collect())
     }
 
     pub fn get_library_item(&self, item_id: &str) -> Result<LibraryItem, ApiError> {
@@ -77,12 +216,32 @@ impl ApiClient {
             .unwrap_or_default())
     }
 
-    pub fn download_audio(&self, item_id: &str) -> Result<PathBuf, ApiError> {
-        let temp_path = PathBuf::from(format!("/tmp/decibelle_{}.audio", item_id));
-        if temp_path.exists() {
-            return Ok(temp_path);
+    pub fn track_source(&self, track: &AudioTrack) -> AudioSource {
+        AudioSource::Stream {
+            url: format!("{}{}", self.base_url, track.content_url),
+            api_key: self.api_key.clone(),
+            extension: track
+                .metadata
+                .as_ref()
+                .map(|m| m.ext.trim_start_matches('.').to_string()),
         }
+    }
+
+    /// Source for a track whose download to `part_path` has just started.
+    pub fn downloading_source(&self, track: &AudioTrack, part_path: PathBuf) -> AudioSource {
+        let metadata = track.metadata.as_ref();
+        AudioSource::Downloading {
+            path: part_path,
+            len: metadata.map_or(0, |m| m.size as u64),
+            url: format!("{}{}", self.base_url, track.content_url),
+            api_key: self.api_key.clone(),
+            extension: metadata.map(|m| m.ext.trim_start_matches('.').to_string()),
+        }
+    }
 
+    /// Opens a playback session for an item. The server records listening
+    /// time and progress against the session until it is closed.
+    pub fn start_session(&self, item_id: &str) -> Result<PlaybackSession, ApiError> {
         let url = format!("{}/api/items/{}/play", self.base_url, item_id);
 
         let response = self.client
@@ -100,55 +259,109 @@ impl ApiClient {
         .send()?
         .error_for_status()?;
 
-        let session: serde_json::Value = response.json()?;
-        let content_url = session["audioTracks"]
-            .as_array()
-            .and_then(|tracks| tracks.first())
-            .and_then(|track| track["contentUrl"].as_str())
-            .ok_or_else(|| "No audio tracks in playback session");
-
-        let url = match content_url {
-            Ok(url) => url,
-            Err(_e) => return Err(ApiError::NotFound),
-        };
+        Ok(response.json()?)
+    }
 
-        let audio_url = format!("{}{}", self.base_url, url);
-        let audio_response = self
+    /// Reports the position and the seconds listened since the last sync.
+    pub fn sync_session(&self, session_id: &str, sync: &SessionSync) -> Result<(), ApiError> {
+        let url = format!("{}/api/session/{}/sync", self.base_url, session_id);
+        let resp = self
             .client
-            .get(&audio_url)
-            .header("Authorization", format!("Bearer {}", self.api_key))
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(sync)
             .send()?;
 
-        let bytes = audio_response.bytes()?;
-        let _ = std::fs::write(&temp_path, &bytes);
+        status_result(resp.status())
+    }
 
-        Ok(temp_path)
+    /// Closes a session, syncing it one last time if `sync` is given.
+    pub fn close_session(
+        &self,
+        session_id: &str,
+        sync: Option<&SessionSync>,
+    ) -> Result<(), ApiError> {
+        let url = format!("{}/api/session/{}/close", self.base_url, session_id);
+        let mut request = self.client.post(&url).bearer_auth(&self.api_key);
+        if let Some(sync) = sync {
+            request = request.json(sync);
+        }
+
+        status_result(request.send()?.status())
     }
 
+    /// Downloads `track` into `dest`, resuming from whatever is already
+    /// there. `on_progress` is called with (bytes done, bytes total) after
+    /// every chunk.
     pub fn download_track(
         &self,
-        item_id: &str,
         track: &AudioTrack,
-    ) -> Result<std::path::PathBuf, ApiError> {
+        dest: &Path,
+        mut on_progress: impl FnMut(u64, u64),
+    ) -> Result<u64, ApiError> {
         let url = format!("{}{}", self.base_url, track.content_url);
+        let expected = track.metadata.as_ref().map(|m| m.size as u64);
+        let mut offset = fs::metadata(dest).map(|m| m.len()).unwrap_or(0);
 
-        let path =
-            std::path::PathBuf::from(format!("/tmp/decibelle_{}_{}.audio", item_id, track.index));
+        if offset > 0 && Some(offset) == expected {
+            return Ok(offset);
+        }
+        if expected.is_some_and(|size| offset > size) {
+            fs::remove_file(dest)?;
+            offset = 0;
+        }
 
-        if path.exists() {
-            return Ok(path);
+        let mut request = self.client.get(&url).bearer_auth(&self.api_key);
+        if offset > 0 {
+            request = request.header(RANGE, format!("bytes={}-", offset));
         }
+        let mut response = request.send()?;
 
-        let response = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+        let (mut file, mut done) = match response.status() {
+            StatusCode::PARTIAL_CONTENT => (OpenOptions::new().append(true).open(dest)?, offset),
+            status if status.is_success() => (File::create(dest)?, 0),
+            status => return Err(ApiError::Http(status.as_u16())),
+        };
 
-        if !response.status().is_success() {
-            return Err(ApiError::Http(response.status().as_u16()));
+        let total = expected
+            .or_else(|| response.content_length().map(|len| len + done))
+            .unwrap_or(0);
+
+        let mut chunk = vec![0u8; DOWNLOAD_CHUNK];
+        loop {
+            let n = match response.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            file.write_all(&chunk[..n])?;
+            done += n as u64;
+            on_progress(done, total);
+        }
+
+        file.sync_all()?;
+        // The connection can close cleanly before the body is complete
+        if done < total {
+            return Err(ApiError::Io(std::io::Error::new(
+                std::io::ErrorKind::UnexpectedEof,
+                format!("Download ended after {} of {} bytes", done, total),
+            )));
         }
+        Ok(done)
+    }
+
+    pub fn get_cover(&self, item_id: &str) -> Result<Vec<u8>, ApiError> {
+        let url = format!("{}/api/items/{}/cover", self.base_url, item_id);
 
-        let bytes = response.bytes()?;
-        let _ = std::fs::write(&path, &bytes);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
 
-        Ok(path)
+        match resp.status().as_u16() {
+            200 => Ok(resp.bytes()?.to_vec()),
+            404 => Err(ApiError::NotFound),
+            code => Err(ApiError::Http(code)),
+        }
     }
 
     pub fn get_personalized(&self, library_id: &str) -> Result<Vec<PersonalizedShelf>, ApiError> {
@@ -214,6 +427,132 @@ impl ApiClient {
         Ok(())
     }
 
+    /// Bookmarks in an item, oldest position first.
+    pub fn get_bookmarks(&self, item_id: &str) -> Result<Vec<Bookmark>, ApiError> {
+        let url = format!("{}/api/me", self.base_url);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let user: CurrentUser = resp.json()?;
+        let mut bookmarks: Vec<Bookmark> = user
+            .bookmarks
+            .into_iter()
+            .filter(|b| b.library_item_id == item_id)
+            .collect();
+        bookmarks.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(bookmarks)
+    }
+
+    pub fn create_bookmark(&self, item_id: &str, time: f64, title: &str) -> Result<(), ApiError> {
+        let url = format!("{}/api/me/item/{}/bookmark", self.base_url, item_id);
+        let resp = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({ "time": time, "title": title }))
+            .send()?;
+
+        status_result(resp.status())
+    }
+
+    /// Renames the bookmark at `time`. The server identifies bookmarks by
+    /// item and time.
+    pub fn update_bookmark(&self, item_id: &str, time: f64, title: &str) -> Result<(), ApiError> {
+        let url = format!("{}/api/me/item/{}/bookmark", self.base_url, item_id);
+        let resp = self
+            .client
+            .patch(&url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({ "time": time, "title": title }))
+            .send()?;
+
+        status_result(resp.status())
+    }
+
+    pub fn delete_bookmark(&self, item_id: &str, time: f64) -> Result<(), ApiError> {
+        let url = format!(
+            "{}/api/me/item/{}/bookmark/{}",
+            self.base_url, item_id, time
+        );
+        let resp = self.client.delete(&url).bearer_auth(&self.api_key).send()?;
+
+        status_result(resp.status())
+    }
+
+    /// The user's collections across all libraries, with their books.
+    pub fn get_collections(&self) -> Result<Vec<Collection>, ApiError> {
+        let url = format!("{}/api/collections", self.base_url);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let collections: CollectionsResponse = resp.json()?;
+        Ok(collections.collections)
+    }
+
+    /// The user's playlists across all libraries, with their items.
+    pub fn get_playlists(&self) -> Result<Vec<Playlist>, ApiError> {
+        let url = format!("{}/api/playlists", self.base_url);
+        let resp = self.client.get(&url).bearer_auth(&self.api_key).send()?;
+
+        if !resp.status().is_success() {
+            return Err(ApiError::Http(resp.status().as_u16()));
+        }
+
+        let playlists: PlaylistsResponse = resp.json()?;
+        Ok(playlists.playlists)
+    }
+
+    /// Creates a playlist holding just the given item.
+    pub fn create_playlist(
+        &self,
+        library_id: &str,
+        name: &str,
+        item_id: &str,
+    ) -> Result<(), ApiError> {
+        let url = format!("{}/api/playlists", self.base_url);
+        let resp = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({
+                "libraryId": library_id,
+                "name": name,
+                "items": [{ "libraryItemId": item_id }],
+            }))
+            .send()?;
+
+        status_result(resp.status())
+    }
+
+    pub fn add_to_playlist(&self, playlist_id: &str, item_id: &str) -> Result<(), ApiError> {
+        let url = format!("{}/api/playlists/{}/item", self.base_url, playlist_id);
+        let resp = self
+            .client
+            .post(&url)
+            .bearer_auth(&self.api_key)
+            .json(&serde_json::json!({ "libraryItemId": item_id }))
+            .send()?;
+
+        status_result(resp.status())
+    }
+
+    /// Removes the item. The server deletes a playlist left empty.
+    pub fn remove_from_playlist(&self, playlist_id: &str, item_id: &str) -> Result<(), ApiError> {
+        let url = format!(
+            "{}/api/playlists/{}/item/{}",
+            self.base_url, playlist_id, item_id
+        );
+        let resp = self.client.delete(&url).bearer_auth(&self.api_key).send()?;
+
+        status_result(resp.status())
+    }
+
     pub fn get_continue_listening(
         &self,
         library_id: &str,
@@ -238,6 +577,15 @@ impl ApiClient {
     }
 }
 
+/// Maps the status of a request whose response body isn't needed.
+fn status_result(status: StatusCode) -> Result<(), ApiError> {
+    match status.as_u16() {
+        200..=299 => Ok(()),
+        404 => Err(ApiError::NotFound),
+        code => Err(ApiError::Http(code)),
+    }
+}
+
 // Error type
 #[derive(Debug)]
 #[allow(dead_code)]
@@ -246,6 +594,24 @@ pub enum ApiError {
     NotFound,
     Unauthorized,
     Http(u16),
+    Io(std::io::Error),
+}
+
+impl ApiError {
+    /// True when the server couldn't be reached at all, as opposed to
+    /// answering with an error.
+    pub fn is_unreachable(&self) -> bool {
+        match self {
+            ApiError::Network(e) => e.is_connect() || e.is_timeout() || e.is_request(),
+            _ => false,
+        }
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        ApiError::Io(e)
+    }
 }
 
 impl From<reqwest::Error> for ApiError {
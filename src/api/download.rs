@@ -0,0 +1,220 @@
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, mpsc};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::api::client::{ApiClient, ApiError};
+use crate::api::models::{AudioTrack, LibraryItem};
+use crate::cache::AudioCache;
+use crate::events::types::{AppEvent, DownloadProgress};
+use crate::offline::OfflineStore;
+
+const MAX_ATTEMPTS: u32 = 5;
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Downloads a track into the cache on its own thread, reporting progress
+/// and resuming from the `.part` file after a dropped connection.
+pub fn spawn(
+    client: ApiClient,
+    cache: Arc<Mutex<AudioCache>>,
+    item_id: String,
+    track: AudioTrack,
+    part_path: PathBuf,
+    event_tx: mpsc::Sender<AppEvent>,
+) -> JoinHandle<()> {
+    std::thread::spawn(move || {
+        let mut progress = DownloadProgress {
+            item_id: item_id.clone(),
+            track_index: Some(track.index),
+            bytes_done: 0,
+            bytes_total: 0,
+            bytes_per_sec: 0.0,
+        };
+
+        let mut attempt = 0;
+        let result = loop {
+            attempt += 1;
+
+            let mut last_report = Instant::now();
+            let mut last_bytes = None;
+
+            let result = client.download_track(&track, &part_path, |done, total| {
+                let elapsed = last_report.elapsed();
+                if elapsed < PROGRESS_INTERVAL {
+                    return;
+                }
+
+                if let Some(last) = last_bytes {
+                    let rate = (done - last) as f64 / elapsed.as_secs_f64();
+                    progress.bytes_per_sec = if progress.bytes_per_sec > 0.0 {
+                        progress.bytes_per_sec * 0.7 + rate * 0.3
+                    } else {
+                        rate
+                    };
+                }
+
+                progress.bytes_done = done;
+                progress.bytes_total = total;
+                last_bytes = Some(done);
+                last_report = Instant::now();
+
+                let _ = event_tx.send(AppEvent::DownloadProgress(progress.clone()));
+            });
+
+            match result {
+                Ok(done) => {
+                    progress.bytes_done = done;
+                    break Ok(());
+                }
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    eprintln!("Download interrupted ({:?}), retrying", e);
+                    std::thread::sleep(Duration::from_secs(1 << attempt));
+                }
+                Err(e) => break Err(format!("{:?}", e)),
+            }
+        };
+
+        let result = result.and_then(|_| {
+            cache
+                .lock()
+                .unwrap()
+                .finish_download(&item_id, &track, &part_path)
+                .map_err(|e| format!("{:?}", e))
+        });
+
+        match result {
+            Ok(()) => {
+                let _ = event_tx.send(AppEvent::DownloadFinished(progress));
+            }
+            Err(error) => {
+                cache.lock().unwrap().abort_download(&item_id, &track);
+                let _ = event_tx.send(AppEvent::DownloadFailed(progress, error));
+            }
+        }
+    })
+}
+
+/// Downloads every track, the chapters and the cover of a book into the
+/// offline store. Progress is reported across the whole book.
+pub fn spawn_offline(
+    client: ApiClient,
+    store: OfflineStore,
+    item_id: String,
+    event_tx: mpsc::Sender<AppEvent>,
+) -> JoinHandle<()> {
+    std::thread::spawn(
+        move || match download_offline(&client, &store, &item_id, &event_tx) {
+            Ok(()) => {
+                let _ = event_tx.send(AppEvent::OfflineDownloadFinished(item_id));
+                let _ = event_tx.send(AppEvent::OfflineItemsChanged(store.list_item_ids()));
+            }
+            Err(e) => {
+                let _ = event_tx.send(AppEvent::OfflineDownloadFailed(item_id, format!("{:?}", e)));
+            }
+        },
+    )
+}
+
+fn download_offline(
+    client: &ApiClient,
+    store: &OfflineStore,
+    item_id: &str,
+    event_tx: &mpsc::Sender<AppEvent>,
+) -> Result<(), ApiError> {
+    let mut item: LibraryItem = client.get_library_item(item_id)?;
+
+    let has_tracks = item
+        .media
+        .as_ref()
+        .and_then(|m| m.tracks.as_ref())
+        .is_some_and(|t| !t.is_empty());
+    if !has_tracks {
+        let session = client.start_session(item_id)?;
+        let _ = client.close_session(&session.id, None);
+        if let Some(media) = item.media.as_mut() {
+            media.tracks = Some(session.audio_tracks);
+        }
+    }
+
+    let tracks = item
+        .media
+        .as_ref()
+        .and_then(|m| m.tracks.clone())
+        .unwrap_or_default();
+
+    let mut progress = DownloadProgress {
+        item_id: item_id.to_string(),
+        track_index: None,
+        bytes_done: 0,
+        bytes_total: tracks
+            .iter()
+            .filter_map(|t| t.metadata.as_ref())
+            .map(|m| m.size as u64)
+            .sum(),
+        bytes_per_sec: 0.0,
+    };
+
+    std::fs::create_dir_all(store.item_dir(item_id))?;
+
+    for track in &tracks {
+        let dest = store.track_file(item_id, track);
+        if store.track_path(item_id, track).is_some() {
+            progress.bytes_done += std::fs::metadata(&dest)?.len();
+            continue;
+        }
+
+        let part_path = dest.with_extension("part");
+        let book_done = progress.bytes_done;
+        let mut last_report = Instant::now();
+
+        let mut attempt = 0;
+        let track_bytes = loop {
+            attempt += 1;
+            // Only what this attempt fetches counts towards the speed, not
+            // what a resumed `.part` file already held
+            let resumed_from = std::fs::metadata(&part_path).map_or(0, |m| m.len());
+            let started = Instant::now();
+            let result = client.download_track(track, &part_path, |done, _| {
+                if last_report.elapsed() < PROGRESS_INTERVAL {
+                    return;
+                }
+                progress.bytes_done = book_done + done;
+                progress.bytes_per_sec =
+                    done.saturating_sub(resumed_from) as f64 / started.elapsed().as_secs_f64();
+                last_report = Instant::now();
+                let _ = event_tx.send(AppEvent::DownloadProgress(progress.clone()));
+            });
+
+            match result {
+                Ok(done) => break done,
+                Err(e) if attempt < MAX_ATTEMPTS => {
+                    eprintln!("Download interrupted ({:?}), retrying", e);
+                    std::thread::sleep(Duration::from_secs(1 << attempt));
+                }
+                Err(e) => return Err(e),
+            }
+        };
+
+        std::fs::rename(&part_path, &dest)?;
+        if store.track_path(item_id, track).is_none() {
+            let _ = std::fs::remove_file(&dest);
+            return Err(ApiError::Io(std::io::Error::other(format!(
+                "Incomplete download for track {}",
+                track.index
+            ))));
+        }
+        progress.bytes_done = book_done + track_bytes;
+    }
+
+    match client.get_cover(item_id) {
+        Ok(data) => store.save_cover(item_id, &data).map_err(store_error)?,
+        Err(ApiError::NotFound) => {}
+        Err(e) => return Err(e),
+    }
+
+    store.save_item(&item).map_err(store_error)
+}
+
+fn store_error(e: anyhow::Error) -> ApiError {
+    ApiError::Io(std::io::Error::other(format!("{:#}", e)))
+}
@@ -1,3 +1,6 @@
 pub mod client;
+pub mod download;
 pub mod models;
+pub mod query;
+pub mod stream;
 pub mod thread;
@@ -1,4 +1,4 @@
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
 
 #[derive(Debug, Clone, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -92,6 +92,7 @@ pub struct MediaMetadata {
     pub subtitle: Option<String>,
     pub authors: Option<Vec<Author>>,
     pub narrators: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "one_or_many")]
     pub series: Option<Vec<SeriesSequence>>,
     pub genres: Option<Vec<String>>,
     pub published_year: Option<String>,
@@ -273,3 +274,185 @@ pub struct MediaProgress {
     pub started_at: i64,
     pub finished_at: Option<i64>,
 }
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[allow(dead_code)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackSession {
+    pub id: String,
+    pub library_item_id: String,
+    pub display_title: Option<String>,
+    pub duration: Option<f64>,
+    pub current_time: Option<f64>,
+    pub time_listening: Option<f64>,
+    pub started_at: Option<i64>,
+    #[serde(default)]
+    pub audio_tracks: Vec<AudioTrack>,
+}
+
+/// Body of a session sync or close request.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSync {
+    pub current_time: f64,
+    pub duration: f64,
+    /// Seconds listened since the previous sync
+    pub time_listened: f64,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+#[allow(dead_code)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    pub library_item_id: String,
+    pub title: String,
+    pub time: f64,
+    pub created_at: Option<i64>,
+}
+
+/// The parts of `/api/me` the app uses.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrentUser {
+    #[serde(default)]
+    pub bookmarks: Vec<Bookmark>,
+    #[serde(default)]
+    pub media_progress: Vec<MediaProgress>,
+}
+
+/// Response of `/api/libraries/{id}/search`, keeping only the items.
+#[derive(Debug, Deserialize)]
+pub struct LibrarySearchResponse {
+    #[serde(default)]
+    pub book: Vec<SearchResult>,
+    #[serde(default)]
+    pub podcast: Vec<SearchResult>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    pub library_item: LibraryItem,
+}
+
+/// Values a library's items can be filtered by, from
+/// `/api/libraries/{id}/filterdata`.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct LibraryFilterData {
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub narrators: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Series {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub books: Vec<LibraryItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SeriesResponse {
+    pub results: Vec<Series>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub books: Vec<LibraryItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CollectionsResponse {
+    pub collections: Vec<Collection>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Playlist {
+    pub id: String,
+    pub library_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub items: Vec<PlaylistItem>,
+}
+
+impl Playlist {
+    pub fn contains(&self, item_id: &str) -> bool {
+        self.items
+            .iter()
+            .any(|item| item.library_item_id == item_id)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaylistItem {
+    pub library_item_id: String,
+    pub episode_id: Option<String>,
+    /// Included when playlists are listed expanded
+    pub library_item: Option<LibraryItem>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct PlaylistsResponse {
+    pub playlists: Vec<Playlist>,
+}
+
+/// An author with the number of their books in a library.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryAuthor {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub num_books: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuthorsResponse {
+    pub authors: Vec<LibraryAuthor>,
+}
+
+/// A narrator with the number of books they read in a library.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Narrator {
+    pub name: String,
+    #[serde(default)]
+    pub num_books: usize,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct NarratorsResponse {
+    pub narrators: Vec<Narrator>,
+}
+
+/// Accepts a single object where a list is expected. Items listed by
+/// series can carry `series` as just the one being listed.
+fn one_or_many<'de, D, T>(deserializer: D) -> Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany<T> {
+        Many(Vec<T>),
+        One(T),
+    }
+
+    Ok(
+        Option::<OneOrMany<T>>::deserialize(deserializer)?.map(|value| match value {
+            OneOrMany::Many(values) => values,
+            OneOrMany::One(value) => vec![value],
+        }),
+    )
+}
@@ -0,0 +1,146 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use serde::{Deserialize, Serialize};
+
+/// Orders the server can list a library's items in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LibrarySort {
+    /// Follows the server's "ignore prefixes when sorting" setting, so
+    /// "The Hobbit" sorts under H when it's on
+    #[default]
+    Title,
+    /// By author, last name first
+    Author,
+    AddedAt,
+    Duration,
+    Progress,
+}
+
+impl LibrarySort {
+    pub const ALL: [LibrarySort; 5] = [
+        LibrarySort::Title,
+        LibrarySort::Author,
+        LibrarySort::AddedAt,
+        LibrarySort::Duration,
+        LibrarySort::Progress,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            LibrarySort::Title => "Title",
+            LibrarySort::Author => "Author",
+            LibrarySort::AddedAt => "Date added",
+            LibrarySort::Duration => "Duration",
+            LibrarySort::Progress => "Progress",
+        }
+    }
+
+    /// Whether picking this sort starts out descending, e.g. newest first.
+    pub fn descending_by_default(self) -> bool {
+        matches!(self, LibrarySort::AddedAt | LibrarySort::Progress)
+    }
+
+    fn param(self) -> &'static str {
+        match self {
+            LibrarySort::Title => "media.metadata.title",
+            LibrarySort::Author => "media.metadata.authorNameLF",
+            LibrarySort::AddedAt => "addedAt",
+            LibrarySort::Duration => "media.duration",
+            LibrarySort::Progress => "progress",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProgressFilter {
+    Finished,
+    InProgress,
+    NotStarted,
+}
+
+impl ProgressFilter {
+    pub const ALL: [ProgressFilter; 3] = [
+        ProgressFilter::Finished,
+        ProgressFilter::InProgress,
+        ProgressFilter::NotStarted,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ProgressFilter::Finished => "Finished",
+            ProgressFilter::InProgress => "In progress",
+            ProgressFilter::NotStarted => "Not started",
+        }
+    }
+
+    fn param(self) -> &'static str {
+        match self {
+            ProgressFilter::Finished => "finished",
+            ProgressFilter::InProgress => "in-progress",
+            ProgressFilter::NotStarted => "not-started",
+        }
+    }
+}
+
+/// Narrows a library down to the items matching one value.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case", tag = "by", content = "value")]
+pub enum LibraryFilter {
+    Genre(String),
+    Tag(String),
+    Narrator(String),
+    Progress(ProgressFilter),
+}
+
+impl LibraryFilter {
+    pub fn label(&self) -> String {
+        match self {
+            LibraryFilter::Genre(genre) => format!("Genre: {}", genre),
+            LibraryFilter::Tag(tag) => format!("Tag: {}", tag),
+            LibraryFilter::Narrator(narrator) => format!("Narrator: {}", narrator),
+            LibraryFilter::Progress(progress) => progress.label().to_string(),
+        }
+    }
+
+    fn param(&self) -> String {
+        match self {
+            LibraryFilter::Genre(genre) => group_filter("genres", genre),
+            LibraryFilter::Tag(tag) => group_filter("tags", tag),
+            LibraryFilter::Narrator(narrator) => group_filter("narrators", narrator),
+            LibraryFilter::Progress(progress) => group_filter("progress", progress.param()),
+        }
+    }
+}
+
+/// Value of the `filter` parameter selecting items whose `group`, e.g.
+/// "series", includes `value`.
+pub fn group_filter(group: &str, value: &str) -> String {
+    format!("{}.{}", group, STANDARD.encode(value))
+}
+
+/// How a library's items are listed. Remembered for each library.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LibraryQuery {
+    #[serde(default)]
+    pub sort: LibrarySort,
+    #[serde(default)]
+    pub descending: bool,
+    #[serde(default)]
+    pub filter: Option<LibraryFilter>,
+}
+
+impl LibraryQuery {
+    /// Query parameters for `/api/libraries/{id}/items`.
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+        let mut params = vec![
+            ("sort", self.sort.param().to_string()),
+            ("desc", u8::from(self.descending).to_string()),
+        ];
+        if let Some(filter) = &self.filter {
+            params.push(("filter", filter.param()));
+        }
+        params
+    }
+}
@@ -0,0 +1,621 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::time::{Duration, Instant};
+
+use reqwest::StatusCode;
+use reqwest::blocking::{Client, Response};
+use reqwest::header::{CONTENT_LENGTH, CONTENT_RANGE, RANGE};
+use symphonia::core::io::MediaSource;
+
+/// Most bytes fetched from the network per refill.
+const READ_AHEAD: usize = 512 * 1024;
+/// Already-read bytes kept around for short backward seeks.
+const KEEP_BEHIND: usize = 256 * 1024;
+/// Forward seeks shorter than this read through instead of reconnecting.
+const SKIP_THRESHOLD: u64 = 1024 * 1024;
+/// Reads this far past the end of a download wait for it to catch up
+/// instead of streaming.
+const WAIT_AHEAD: u64 = 4 * 1024 * 1024;
+/// A download that hasn't grown for this long is given up on.
+const STALL_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Seekable audio source backed by HTTP range requests.
+///
+/// Reads are served from a read-ahead buffer filled from a single open
+/// response. Seeking outside the buffer opens a new `Range` request, so
+/// only the bytes that are actually played get downloaded.
+pub struct HttpMediaSource {
+    client: Client,
+    url: String,
+    api_key: String,
+    len: Option<u64>,
+    pos: u64,
+    buffer: Vec<u8>,
+    buffer_start: u64,
+    response: Option<Response>,
+}
+
+impl HttpMediaSource {
+    pub fn open(client: Client, url: String, api_key: String) -> io::Result<Self> {
+        let mut source = Self {
+            client,
+            url,
+            api_key,
+            len: None,
+            pos: 0,
+            buffer: Vec::new(),
+            buffer_start: 0,
+            response: None,
+        };
+
+        source.connect(0)?;
+        Ok(source)
+    }
+
+    fn buffer_end(&self) -> u64 {
+        self.buffer_start + self.buffer.len() as u64
+    }
+
+    fn connect(&mut self, offset: u64) -> io::Result<()> {
+        let response = self
+            .client
+            .get(&self.url)
+            .bearer_auth(&self.api_key)
+            .header(RANGE, format!("bytes={}-", offset))
+            .send()
+            .map_err(io::Error::other)?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(io::Error::other(format!("HTTP {}", status.as_u16())));
+        }
+
+        let mut response = response;
+        if status == StatusCode::PARTIAL_CONTENT {
+            if self.len.is_none() {
+                self.len = content_range_total(&response);
+            }
+        } else {
+            // Server ignored the range, skip up to the requested offset
+            if self.len.is_none() {
+                self.len = content_length(&response);
+            }
+            io::copy(&mut (&mut response).take(offset), &mut io::sink())?;
+        }
+
+        self.buffer.clear();
+        self.buffer_start = offset;
+        self.response = Some(response);
+        Ok(())
+    }
+
+    fn fill(&mut self) -> io::Result<usize> {
+        let Some(response) = self.response.as_mut() else {
+            return Ok(0);
+        };
+
+        // Trimmed in large steps, as refills can be small
+        if self.buffer.len() > KEEP_BEHIND + READ_AHEAD {
+            let behind = (self.pos - self.buffer_start) as usize;
+            let drop = behind.saturating_sub(KEEP_BEHIND).min(self.buffer.len());
+            self.buffer.drain(..drop);
+            self.buffer_start += drop as u64;
+        }
+
+        let old_len = self.buffer.len();
+        self.buffer.resize(old_len + READ_AHEAD, 0);
+
+        // Whatever the first read returns, so a slow link doesn't hold up
+        // the player until a full read-ahead has arrived
+        let filled = loop {
+            match response.read(&mut self.buffer[old_len..]) {
+                Ok(n) => break n,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => {
+                    self.buffer.truncate(old_len);
+                    return Err(e);
+                }
+            }
+        };
+
+        self.buffer.truncate(old_len + filled);
+        if filled == 0 {
+            self.response = None;
+        }
+        Ok(filled)
+    }
+}
+
+impl Read for HttpMediaSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.len.is_some_and(|len| self.pos >= len) {
+            return Ok(0);
+        }
+
+        let in_buffer = self.pos >= self.buffer_start && self.pos < self.buffer_end();
+        if !in_buffer {
+            let end = self.buffer_end();
+            let can_read_through =
+                self.response.is_some() && self.pos >= end && self.pos - end < SKIP_THRESHOLD;
+
+            if !can_read_through {
+                self.connect(self.pos)?;
+            }
+
+            let mut reconnected = false;
+            while self.pos >= self.buffer_end() {
+                let filled = match self.fill() {
+                    Ok(n) => n,
+                    Err(e) if reconnected => return Err(e),
+                    Err(_) => 0,
+                };
+                if filled > 0 {
+                    continue;
+                }
+
+                // The server closed the connection early, pick up where it left off
+                let truncated = self.len.is_some_and(|len| self.pos < len);
+                if !truncated || reconnected {
+                    return Ok(0);
+                }
+                self.connect(self.pos)?;
+                reconnected = true;
+            }
+        }
+
+        let offset = (self.pos - self.buffer_start) as usize;
+        let available = &self.buffer[offset..];
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for HttpMediaSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(delta) => self.pos as i64 + delta,
+            SeekFrom::End(delta) => {
+                let len = self
+                    .len
+                    .ok_or_else(|| io::Error::other("Stream length unknown"))?;
+                len as i64 + delta
+            }
+        };
+
+        if target < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek before start of stream",
+            ));
+        }
+
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
+
+impl MediaSource for HttpMediaSource {
+    fn is_seekable(&self) -> bool {
+        self.len.is_some()
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        self.len
+    }
+}
+
+/// Audio source for a track that is being downloaded into the cache.
+///
+/// Whatever the download has written is read from the partial file, so a
+/// first play doesn't fetch the track a second time. Reads far ahead of the
+/// download, or past one that has stalled, are streamed with range requests
+/// until the download catches up.
+pub struct DownloadingMediaSource {
+    file: Option<File>,
+    len: u64,
+    pos: u64,
+    downloaded: u64,
+    last_growth: Instant,
+    client: Client,
+    url: String,
+    api_key: String,
+    stream: Option<HttpMediaSource>,
+}
+
+impl DownloadingMediaSource {
+    /// `file` is the partial download, `len` the full size of the track.
+    pub fn new(file: Option<File>, len: u64, client: Client, url: String, api_key: String) -> Self {
+        Self {
+            file,
+            len,
+            pos: 0,
+            downloaded: 0,
+            last_growth: Instant::now(),
+            client,
+            url,
+            api_key,
+            stream: None,
+        }
+    }
+
+    /// Bytes written so far. The handle stays valid after the finished
+    /// download is moved into the cache.
+    fn poll_downloaded(&mut self) -> u64 {
+        let len = self
+            .file
+            .as_ref()
+            .and_then(|f| f.metadata().ok())
+            .map_or(0, |m| m.len());
+        if len != self.downloaded {
+            self.downloaded = len;
+            self.last_growth = Instant::now();
+        }
+        len
+    }
+
+    fn read_file(&mut self, buf: &mut [u8], available: u64) -> io::Result<usize> {
+        let Some(file) = self.file.as_mut() else {
+            return Ok(0);
+        };
+        let len = (buf.len() as u64).min(available) as usize;
+        file.seek(SeekFrom::Start(self.pos))?;
+        let n = file.read(&mut buf[..len])?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn read_stream(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = match &mut self.stream {
+            Some(stream) => stream,
+            None => self.stream.insert(HttpMediaSource::open(
+                self.client.clone(),
+                self.url.clone(),
+                self.api_key.clone(),
+            )?),
+        };
+        stream.seek(SeekFrom::Start(self.pos))?;
+        let n = stream.read(buf)?;
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl Read for DownloadingMediaSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.len {
+            return Ok(0);
+        }
+
+        loop {
+            let downloaded = self.poll_downloaded();
+            if self.pos < downloaded {
+                // Caught up, the stream's connection is no longer needed
+                self.stream = None;
+                return self.read_file(buf, downloaded - self.pos);
+            }
+
+            let close_behind = self.pos - downloaded < WAIT_AHEAD;
+            let growing = self.last_growth.elapsed() < STALL_TIMEOUT;
+            if self.file.is_none() || !close_behind || !growing {
+                return self.read_stream(buf);
+            }
+            std::thread::sleep(Duration::from_millis(20));
+        }
+    }
+}
+
+impl Seek for DownloadingMediaSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let target = match pos {
+            SeekFrom::Start(offset) => offset as i64,
+            SeekFrom::Current(delta) => self.pos as i64 + delta,
+            SeekFrom::End(delta) => self.len as i64 + delta,
+        };
+
+        if target < 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Seek before start of stream",
+            ));
+        }
+
+        self.pos = target as u64;
+        Ok(self.pos)
+    }
+}
+
+impl MediaSource for DownloadingMediaSource {
+    fn is_seekable(&self) -> bool {
+        true
+    }
+
+    fn byte_len(&self) -> Option<u64> {
+        Some(self.len)
+    }
+}
+
+fn content_length(response: &Response) -> Option<u64> {
+    response
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+fn content_range_total(response: &Response) -> Option<u64> {
+    // Content-Range: bytes 0-1023/4096
+    response
+        .headers()
+        .get(CONTENT_RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.rsplit('/').next())
+        .and_then(|v| v.parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    use super::*;
+
+    const BODY_LEN: usize = 6 * 1024 * 1024;
+
+    #[derive(Clone, Copy)]
+    enum Behaviour {
+        /// Answers `Range` requests with 206 Partial Content
+        Ranged,
+        /// Always sends the whole file with 200 OK
+        IgnoresRange,
+        /// Like `Ranged`, but the first response closes after this many
+        /// body bytes
+        ClosesEarly(usize),
+    }
+
+    /// Local stand-in for the Audiobookshelf file endpoint. Records the
+    /// `Range` header of every request.
+    struct TestServer {
+        url: String,
+        ranges: Arc<Mutex<Vec<Option<String>>>>,
+    }
+
+    impl TestServer {
+        fn start(body: Arc<Vec<u8>>, behaviour: Behaviour) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let url = format!("http://{}/audio.mp3", listener.local_addr().unwrap());
+            let ranges = Arc::new(Mutex::new(Vec::new()));
+
+            let recorded = ranges.clone();
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let Ok(stream) = stream else { break };
+                    let request_index = {
+                        let mut ranges = recorded.lock().unwrap();
+                        ranges.push(None);
+                        ranges.len() - 1
+                    };
+                    let body = body.clone();
+                    let recorded = recorded.clone();
+                    // A dropped response leaves its connection writing, so
+                    // each one gets a thread
+                    std::thread::spawn(move || {
+                        let range = read_range_header(&stream);
+                        recorded.lock().unwrap()[request_index] = range.clone();
+                        respond(stream, &body, range, behaviour, request_index);
+                    });
+                }
+            });
+
+            Self { url, ranges }
+        }
+
+        fn open(&self) -> HttpMediaSource {
+            HttpMediaSource::open(Client::new(), self.url.clone(), "key".to_string()).unwrap()
+        }
+
+        fn ranges(&self) -> Vec<Option<String>> {
+            self.ranges.lock().unwrap().clone()
+        }
+    }
+
+    fn read_range_header(stream: &TcpStream) -> Option<String> {
+        let mut reader = BufReader::new(stream);
+        let mut range = None;
+        loop {
+            let mut line = String::new();
+            if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                return range;
+            }
+            if let Some((name, value)) = line.split_once(':')
+                && name.eq_ignore_ascii_case("range")
+            {
+                range = Some(value.trim().to_string());
+            }
+        }
+    }
+
+    fn respond(
+        mut stream: TcpStream,
+        body: &[u8],
+        range: Option<String>,
+        behaviour: Behaviour,
+        request_index: usize,
+    ) {
+        let start = range
+            .as_deref()
+            .and_then(|r| r.strip_prefix("bytes="))
+            .and_then(|r| r.trim_end_matches('-').parse::<usize>().ok())
+            .unwrap_or(0);
+
+        let (head, sent) = match behaviour {
+            Behaviour::IgnoresRange => (
+                format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n", body.len()),
+                body,
+            ),
+            Behaviour::Ranged | Behaviour::ClosesEarly(_) => {
+                let head = format!(
+                    "HTTP/1.1 206 Partial Content\r\nContent-Range: bytes {}-{}/{}\r\nContent-Length: {}\r\n",
+                    start,
+                    body.len() - 1,
+                    body.len(),
+                    body.len() - start
+                );
+                let sent = match behaviour {
+                    Behaviour::ClosesEarly(cut) if request_index == 0 => &body[start..cut],
+                    _ => &body[start..],
+                };
+                (head, sent)
+            }
+        };
+
+        let _ = stream.write_all(format!("{}Connection: close\r\n\r\n", head).as_bytes());
+        let _ = stream.write_all(sent);
+        let _ = stream.shutdown(std::net::Shutdown::Both);
+    }
+
+    fn test_body() -> Arc<Vec<u8>> {
+        Arc::new((0..BODY_LEN).map(|i| (i % 251) as u8).collect())
+    }
+
+    fn read_at(source: &mut HttpMediaSource, offset: u64, len: usize) -> Vec<u8> {
+        source.seek(SeekFrom::Start(offset)).unwrap();
+        let mut buf = vec![0; len];
+        source.read_exact(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn seeks_far_forward_and_back_with_range_requests() {
+        let body = test_body();
+        let server = TestServer::start(body.clone(), Behaviour::Ranged);
+        let mut source = server.open();
+        assert_eq!(source.byte_len(), Some(BODY_LEN as u64));
+
+        assert_eq!(read_at(&mut source, 0, 1000), body[..1000]);
+
+        let forward = 3 * SKIP_THRESHOLD;
+        let bytes = read_at(&mut source, forward, 1000);
+        assert_eq!(bytes, body[forward as usize..forward as usize + 1000]);
+
+        let back = 100;
+        let bytes = read_at(&mut source, back, 1000);
+        assert_eq!(bytes, body[back as usize..back as usize + 1000]);
+
+        assert_eq!(
+            server.ranges(),
+            vec![
+                Some("bytes=0-".to_string()),
+                Some(format!("bytes={}-", forward)),
+                Some(format!("bytes={}-", back)),
+            ]
+        );
+        assert_eq!(source.byte_len(), Some(BODY_LEN as u64));
+    }
+
+    #[test]
+    fn skips_ahead_when_server_ignores_range() {
+        let body = test_body();
+        let server = TestServer::start(body.clone(), Behaviour::IgnoresRange);
+        let mut source = server.open();
+        assert_eq!(source.byte_len(), Some(BODY_LEN as u64));
+
+        let offset = 2 * SKIP_THRESHOLD + 17;
+        let bytes = read_at(&mut source, offset, 4096);
+        assert_eq!(bytes, body[offset as usize..offset as usize + 4096]);
+
+        let mut rest = Vec::new();
+        source.read_to_end(&mut rest).unwrap();
+        assert_eq!(rest, body[offset as usize + 4096..]);
+        assert_eq!(source.byte_len(), Some(BODY_LEN as u64));
+    }
+
+    #[test]
+    fn resumes_after_connection_closes_mid_body() {
+        let body = test_body();
+        let cut = SKIP_THRESHOLD as usize + 12345;
+        let server = TestServer::start(body.clone(), Behaviour::ClosesEarly(cut));
+        let mut source = server.open();
+
+        let mut read = Vec::new();
+        source.read_to_end(&mut read).unwrap();
+        assert_eq!(read.len(), BODY_LEN);
+        assert_eq!(read, *body);
+        assert_eq!(source.byte_len(), Some(BODY_LEN as u64));
+
+        let ranges = server.ranges();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[1], Some(format!("bytes={}-", cut)));
+    }
+
+    fn part_file(name: &str) -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("decibelle-{}-{}.part", name, std::process::id()))
+    }
+
+    #[test]
+    fn reads_download_as_it_grows() {
+        let body = test_body();
+        let server = TestServer::start(body.clone(), Behaviour::Ranged);
+        let path = part_file("growing");
+        let mut writer = File::create(&path).unwrap();
+
+        let mut source = DownloadingMediaSource::new(
+            File::open(&path).ok(),
+            BODY_LEN as u64,
+            Client::new(),
+            server.url.clone(),
+            "key".to_string(),
+        );
+        let written = body.clone();
+        let download = std::thread::spawn(move || {
+            for chunk in written.chunks(256 * 1024) {
+                writer.write_all(chunk).unwrap();
+                std::thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let mut read = Vec::new();
+        source.read_to_end(&mut read).unwrap();
+        download.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(read, *body);
+        assert_eq!(source.byte_len(), Some(BODY_LEN as u64));
+        assert!(server.ranges().is_empty());
+    }
+
+    #[test]
+    fn streams_far_ahead_of_download() {
+        let body = test_body();
+        let server = TestServer::start(body.clone(), Behaviour::Ranged);
+        let path = part_file("ahead");
+        std::fs::write(&path, &body[..1000]).unwrap();
+
+        let mut source = DownloadingMediaSource::new(
+            File::open(&path).ok(),
+            BODY_LEN as u64,
+            Client::new(),
+            server.url.clone(),
+            "key".to_string(),
+        );
+        let mut start = vec![0; 1000];
+        source.read_exact(&mut start).unwrap();
+        assert_eq!(start, body[..1000]);
+        assert!(server.ranges().is_empty());
+
+        let offset = WAIT_AHEAD as usize + 4096;
+        source.seek(SeekFrom::Start(offset as u64)).unwrap();
+        let mut ahead = vec![0; 1000];
+        source.read_exact(&mut ahead).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(ahead, body[offset..offset + 1000]);
+        assert_eq!(
+            server.ranges().last(),
+            Some(&Some(format!("bytes={}-", offset)))
+        );
+    }
+}
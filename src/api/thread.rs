@@ -1,20 +1,82 @@
-use crate::api::client::ApiClient;
-use crate::api::models::find_track_for_position;
-use crate::events::types::{AppEvent, TrackInfo};
-use std::sync::mpsc;
+use crate::api::client::{ApiClient, ApiError};
+use crate::api::download;
+use crate::api::models::{
+    AudioTrack, LibraryItem, PlaybackSession, SessionSync, find_track_for_position,
+};
+use crate::api::query::LibraryQuery;
+use crate::cache::AudioCache;
+use crate::events::types::{AppEvent, ItemsPage, TrackInfo};
+use crate::offline::{
+    JournalEntry, OFFLINE_LIBRARY_ID, OfflineStore, ProgressJournal, SyncOutcome,
+};
+use crate::player::commands::AudioSource;
+use std::fs::OpenOptions;
+use std::sync::{Arc, Mutex, mpsc};
+use std::time::Duration;
+
+type SharedCache = Option<Arc<Mutex<AudioCache>>>;
+
+/// How often to check whether the server is back while offline.
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(30);
 
 pub enum ApiCommand {
     FetchLibraries,
-    FetchLibraryItems(String),
+    /// Library id, how to list it and the page to fetch
+    FetchLibraryItems(String, LibraryQuery, usize),
+    FetchFilterData(String),
+    FetchSeries(String),
+    FetchAuthors(String),
+    FetchNarrators(String),
+    /// Library id, filter group and value, e.g. "series" and a series id
+    FetchGroupBooks(String, String, String),
+    FetchCollections,
+    FetchPlaylists,
+    /// Library id, name and the item to start it with
+    CreatePlaylist(String, String, String),
+    /// Playlist id and item id
+    AddToPlaylist(String, String),
+    /// Playlist id and item id
+    RemoveFromPlaylist(String, String),
+    FetchProgress,
+    /// Library id and query
+    SearchLibrary(String, String),
     FetchItemChapters(String),
     DownloadForPlayback(String, f64),
+    /// Resolves the track after the given index so it can be queued.
+    PrepareNextTrack(String, i32),
+    DownloadForOffline(String),
+    RemoveOffline(String),
     FetchContinueListening(String),
-    UpdateProgress {
-        item_id: String,
-        current_time: f64,
-        duration: f64,
-        is_finished: bool,
-    },
+    /// Personalized shelves of the given library
+    FetchShelves(String),
+    SyncSession(ProgressUpdate),
+    CloseSession(ProgressUpdate),
+    FetchBookmarks(String),
+    /// Item id, position and title
+    CreateBookmark(String, f64, String),
+    /// Renames the bookmark at the given position
+    UpdateBookmark(String, f64, String),
+    DeleteBookmark(String, f64),
+}
+
+/// Playback progress reported by the app.
+pub struct ProgressUpdate {
+    pub item_id: String,
+    pub current_time: f64,
+    pub duration: f64,
+    /// Seconds listened since the previous update
+    pub time_listened: f64,
+    pub is_finished: bool,
+}
+
+impl ProgressUpdate {
+    fn session_sync(&self) -> SessionSync {
+        SessionSync {
+            current_time: self.current_time,
+            duration: self.duration,
+            time_listened: self.time_listened,
+        }
+    }
 }
 
 pub fn spawn(
@@ -24,21 +86,193 @@ pub fn spawn(
 ) -> std::thread::JoinHandle<()> {
     std::thread::spawn(move || {
         let client = ApiClient::new(&config);
+        let cache: SharedCache = match AudioCache::open(config.cache_size_mb * 1024 * 1024) {
+            Ok(cache) => Some(Arc::new(Mutex::new(cache))),
+            Err(e) => {
+                eprintln!("Audio cache disabled: {:?}", e);
+                None
+            }
+        };
+        let store = OfflineStore::open()
+            .map_err(|e| eprintln!("Offline store disabled: {:?}", e))
+            .ok();
+        let mut journal = ProgressJournal::open()
+            .map_err(|e| eprintln!("Progress journal disabled: {:?}", e))
+            .ok();
+
+        let mut online = true;
+        let mut session: Option<PlaybackSession> = None;
+
+        loop {
+            let timeout = journal
+                .as_ref()
+                .and_then(|j| j.time_until_due())
+                .map_or(RECONNECT_INTERVAL, |due| due.min(RECONNECT_INTERVAL));
+
+            let cmd = match cmd_rx.recv_timeout(timeout) {
+                Ok(cmd) => cmd,
+                Err(mpsc::RecvTimeoutError::Timeout) => {
+                    let due = journal.as_ref().is_some_and(|j| j.is_due());
+                    if online && !due {
+                        continue;
+                    }
+
+                    let reachable = match journal.as_mut() {
+                        Some(journal) if !journal.is_empty() => flush_journal(&client, journal),
+                        _ => client.get_libraries().is_ok(),
+                    };
+                    if reachable && !online {
+                        let _ = event_tx.send(AppEvent::ServerReachable);
+                    }
+                    online = reachable;
+                    continue;
+                }
+                Err(mpsc::RecvTimeoutError::Disconnected) => break,
+            };
 
-        while let Ok(cmd) = cmd_rx.recv() {
             match cmd {
                 ApiCommand::FetchLibraries => match client.get_libraries() {
                     Ok(libs) => {
+                        online = true;
                         let _ = event_tx.send(AppEvent::LibrariesLoaded(libs));
+                        if let Some(ref store) = store {
+                            let _ =
+                                event_tx.send(AppEvent::OfflineItemsChanged(store.list_item_ids()));
+                        }
+                        if let Some(ref mut journal) = journal {
+                            flush_journal(&client, journal);
+                        }
+                    }
+                    Err(e) if e.is_unreachable() && store.is_some() => {
+                        online = false;
+                        let items = store.as_ref().map(|s| s.list_items()).unwrap_or_default();
+                        let _ = event_tx.send(AppEvent::OfflineModeEntered(items));
                     }
                     Err(e) => {
                         let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
                     }
                 },
-                ApiCommand::FetchLibraryItems(library_id) => {
-                    match client.get_library_items(&library_id) {
+                ApiCommand::FetchLibraryItems(library_id, query, page) => {
+                    if library_id == OFFLINE_LIBRARY_ID {
+                        // Downloaded books all come as one page
+                        let items = store.as_ref().map(|s| s.list_items()).unwrap_or_default();
+                        let _ = event_tx.send(AppEvent::ItemsLoaded(ItemsPage {
+                            library_id,
+                            query,
+                            page: 0,
+                            total: items.len(),
+                            items,
+                        }));
+                        continue;
+                    }
+
+                    match client.get_library_items(&library_id, &query, page) {
+                        Ok(response) => {
+                            let total = response
+                                .total
+                                .map_or(response.results.len(), |total| total.max(0) as usize);
+                            let _ = event_tx.send(AppEvent::ItemsLoaded(ItemsPage {
+                                library_id,
+                                query,
+                                page,
+                                total,
+                                items: response.results,
+                            }));
+                        }
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::FetchSeries(library_id) => match client.get_series(&library_id) {
+                    Ok(series) => {
+                        let _ = event_tx.send(AppEvent::SeriesLoaded(library_id, series));
+                    }
+                    Err(e) => {
+                        let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                    }
+                },
+                ApiCommand::FetchAuthors(library_id) => match client.get_authors(&library_id) {
+                    Ok(authors) => {
+                        let _ = event_tx.send(AppEvent::AuthorsLoaded(library_id, authors));
+                    }
+                    Err(e) => {
+                        let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                    }
+                },
+                ApiCommand::FetchNarrators(library_id) => match client.get_narrators(&library_id) {
+                    Ok(narrators) => {
+                        let _ = event_tx.send(AppEvent::NarratorsLoaded(library_id, narrators));
+                    }
+                    Err(e) => {
+                        let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                    }
+                },
+                ApiCommand::FetchGroupBooks(library_id, group, value) => {
+                    match client.get_group_items(&library_id, &group, &value) {
+                        Ok(books) => {
+                            let _ = event_tx.send(AppEvent::GroupBooksLoaded(group, value, books));
+                        }
+                        Err(e) => {
+                            eprintln!("Failed to fetch {} books: {:?}", group, e);
+                        }
+                    }
+                }
+                ApiCommand::FetchCollections => match client.get_collections() {
+                    Ok(collections) => {
+                        let _ = event_tx.send(AppEvent::CollectionsLoaded(collections));
+                    }
+                    Err(e) => {
+                        let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                    }
+                },
+                ApiCommand::FetchPlaylists => send_playlists(&client, &event_tx),
+                ApiCommand::CreatePlaylist(library_id, name, item_id) => {
+                    match client.create_playlist(&library_id, &name, &item_id) {
+                        Ok(()) => send_playlists(&client, &event_tx),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::AddToPlaylist(playlist_id, item_id) => {
+                    match client.add_to_playlist(&playlist_id, &item_id) {
+                        Ok(()) => send_playlists(&client, &event_tx),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::RemoveFromPlaylist(playlist_id, item_id) => {
+                    match client.remove_from_playlist(&playlist_id, &item_id) {
+                        Ok(()) => send_playlists(&client, &event_tx),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::FetchProgress => match client.get_all_progress() {
+                    Ok(progress) => {
+                        let _ = event_tx.send(AppEvent::ProgressLoaded(progress));
+                    }
+                    Err(e) => {
+                        eprintln!("Failed to fetch progress: {:?}", e);
+                    }
+                },
+                ApiCommand::FetchFilterData(library_id) => {
+                    match client.get_filter_data(&library_id) {
+                        Ok(data) => {
+                            let _ = event_tx.send(AppEvent::FilterDataLoaded(library_id, data));
+                        }
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::SearchLibrary(library_id, query) => {
+                    match client.search_library(&library_id, &query) {
                         Ok(items) => {
-                            let _ = event_tx.send(AppEvent::ItemsLoaded(items));
+                            let _ = event_tx.send(AppEvent::SearchResults(query, items));
                         }
                         Err(e) => {
                             let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
@@ -46,10 +280,16 @@ pub fn spawn(
                     }
                 }
                 ApiCommand::FetchItemChapters(item_id) => {
+                    let local = || store.as_ref().and_then(|s| s.load_chapters(&item_id));
+
                     match client.get_item_chapters(&item_id) {
                         Ok(chapters) => {
                             let _ = event_tx.send(AppEvent::ChaptersLoaded(chapters));
                         }
+                        Err(e) if e.is_unreachable() && local().is_some() => {
+                            let chapters = local().unwrap_or_default();
+                            let _ = event_tx.send(AppEvent::ChaptersLoaded(chapters));
+                        }
                         Err(e) => {
                             let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
                         }
@@ -57,7 +297,23 @@ pub fn spawn(
                 }
 
                 ApiCommand::DownloadForPlayback(item_id, position) => {
-                    match client.get_library_item(&item_id) {
+                    let item = fetch_item(&client, &store, &item_id);
+
+                    if online
+                        && session
+                            .as_ref()
+                            .is_none_or(|s| s.library_item_id != item_id)
+                    {
+                        if let Some(old) = session.take() {
+                            let _ = client.close_session(&old.id, None);
+                        }
+                        session = client
+                            .start_session(&item_id)
+                            .map_err(|e| eprintln!("Failed to open playback session: {:?}", e))
+                            .ok();
+                    }
+
+                    match item {
                         Ok(item) => {
                             let tracks = item.media.as_ref().and_then(|m| m.tracks.as_ref());
 
@@ -70,36 +326,29 @@ pub fn spawn(
                                         let track_local_position =
                                             (position - track.start_offset).max(0.0);
 
-                                        match client.download_track(&item_id, track) {
-                                            Ok(path) => {
-                                                let _ = event_tx.send(AppEvent::DownloadFinished(
-                                                    path,
-                                                    track_local_position,
-                                                    TrackInfo {
-                                                        index: track.index,
-                                                        start_offset: track.start_offset,
-                                                        duration: track.duration,
-                                                    },
-                                                ));
-                                            }
-                                            Err(e) => {
-                                                let _ = event_tx
-                                                    .send(AppEvent::ApiError(format!("{:?}", e)));
-                                            }
-                                        }
+                                        let _ = event_tx.send(AppEvent::PlaybackReady(
+                                            resolve_source(
+                                                &client, &cache, &store, &item_id, track, &event_tx,
+                                            ),
+                                            track_local_position,
+                                            track_info(track),
+                                        ));
                                     }
                                 }
-                                _ => match client.download_audio(&item_id) {
-                                    Ok(path) => {
-                                        let _ = event_tx.send(AppEvent::DownloadFinished(
-                                            path,
+                                _ => match session.as_ref().and_then(|s| s.audio_tracks.first()) {
+                                    Some(track) => {
+                                        let _ = event_tx.send(AppEvent::PlaybackReady(
+                                            resolve_source(
+                                                &client, &cache, &store, &item_id, track, &event_tx,
+                                            ),
                                             position,
                                             TrackInfo::single_file(),
                                         ));
                                     }
-                                    Err(e) => {
-                                        let _ =
-                                            event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                                    None => {
+                                        let _ = event_tx.send(AppEvent::ApiError(
+                                            "No audio tracks found".to_string(),
+                                        ));
                                     }
                                 },
                             }
@@ -110,12 +359,77 @@ pub fn spawn(
                     }
                 }
 
+                ApiCommand::PrepareNextTrack(item_id, index) => {
+                    let next = fetch_item(&client, &store, &item_id).map(|item| {
+                        item.media
+                            .and_then(|m| m.tracks)
+                            .and_then(|tracks| tracks.into_iter().find(|t| t.index == index + 1))
+                    });
+
+                    match next {
+                        Ok(Some(track)) => {
+                            let source = resolve_source(
+                                &client, &cache, &store, &item_id, &track, &event_tx,
+                            );
+                            let _ = event_tx.send(AppEvent::NextTrackReady(
+                                item_id,
+                                source,
+                                track_info(&track),
+                            ));
+                        }
+                        Ok(None) => {}
+                        Err(e) => {
+                            eprintln!("Failed to prepare next track: {:?}", e);
+                        }
+                    }
+                }
+
+                ApiCommand::DownloadForOffline(item_id) => match store {
+                    Some(ref store) => {
+                        download::spawn_offline(
+                            client.clone(),
+                            store.clone(),
+                            item_id,
+                            event_tx.clone(),
+                        );
+                    }
+                    None => {
+                        let _ = event_tx.send(AppEvent::OfflineDownloadFailed(
+                            item_id,
+                            "Offline store unavailable".to_string(),
+                        ));
+                    }
+                },
+
+                ApiCommand::RemoveOffline(item_id) => {
+                    if let Some(ref store) = store {
+                        if let Err(e) = store.remove_item(&item_id) {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                        let _ = event_tx.send(AppEvent::OfflineItemsChanged(store.list_item_ids()));
+                    }
+                }
+
                 ApiCommand::FetchContinueListening(library_id) => {
-                    match client.get_continue_listening(&library_id) {
+                    let result = if library_id == OFFLINE_LIBRARY_ID {
+                        Ok(None)
+                    } else {
+                        client.get_continue_listening(&library_id)
+                    };
+
+                    match result {
                         Ok(Some((item, position))) => {
                             let _ =
                                 event_tx.send(AppEvent::ContinueListeningLoaded(item, position));
                         }
+                        Ok(None) if library_id == OFFLINE_LIBRARY_ID => {
+                            if let Some((item, position)) =
+                                offline_continue_listening(&store, &journal)
+                            {
+                                let _ = event_tx
+                                    .send(AppEvent::ContinueListeningLoaded(item, position));
+                            }
+                        }
                         Ok(None) => {}
                         Err(e) => {
                             eprintln!("Continue listening error: {:?}", e);
@@ -123,19 +437,279 @@ pub fn spawn(
                     }
                 }
 
-                ApiCommand::UpdateProgress {
-                    item_id,
-                    current_time,
-                    duration,
-                    is_finished,
-                } => {
-                    if let Err(e) =
-                        client.update_media_progress(&item_id, current_time, duration, is_finished)
-                    {
-                        eprintln!("Failed to update progress: {:?}", e);
+                ApiCommand::FetchShelves(library_id) => {
+                    match client.get_personalized(&library_id) {
+                        Ok(shelves) => {
+                            let _ = event_tx.send(AppEvent::ShelvesLoaded(library_id, shelves));
+                        }
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+
+                ApiCommand::SyncSession(update) => {
+                    let result = match session {
+                        Some(ref s) if online && s.library_item_id == update.item_id => {
+                            Some(client.sync_session(&s.id, &update.session_sync()))
+                        }
+                        _ => None,
+                    };
+                    if let Some(Err(ApiError::NotFound)) = result {
+                        session = None;
+                    }
+                    record_progress(&client, &mut journal, &mut online, &update, result);
+                }
+
+                ApiCommand::CloseSession(update) => {
+                    let result = match session.take() {
+                        Some(s) if online && s.library_item_id == update.item_id => {
+                            Some(client.close_session(&s.id, Some(&update.session_sync())))
+                        }
+                        Some(s) if online => {
+                            let _ = client.close_session(&s.id, None);
+                            None
+                        }
+                        _ => None,
+                    };
+                    record_progress(&client, &mut journal, &mut online, &update, result);
+                }
+
+                ApiCommand::FetchBookmarks(item_id) => {
+                    send_bookmarks(&client, &event_tx, &item_id);
+                }
+                ApiCommand::CreateBookmark(item_id, time, title) => {
+                    match client.create_bookmark(&item_id, time, &title) {
+                        Ok(()) => send_bookmarks(&client, &event_tx, &item_id),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::UpdateBookmark(item_id, time, title) => {
+                    match client.update_bookmark(&item_id, time, &title) {
+                        Ok(()) => send_bookmarks(&client, &event_tx, &item_id),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
+                    }
+                }
+                ApiCommand::DeleteBookmark(item_id, time) => {
+                    match client.delete_bookmark(&item_id, time) {
+                        Ok(()) => send_bookmarks(&client, &event_tx, &item_id),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+                        }
                     }
                 }
             }
         }
     })
 }
+
+/// Sends the item's bookmarks as the server has them now. Offline there
+/// are none to show, which isn't worth an error.
+fn send_bookmarks(client: &ApiClient, event_tx: &mpsc::Sender<AppEvent>, item_id: &str) {
+    match client.get_bookmarks(item_id) {
+        Ok(bookmarks) => {
+            let _ = event_tx.send(AppEvent::BookmarksLoaded(item_id.to_string(), bookmarks));
+        }
+        Err(e) if e.is_unreachable() => {
+            let _ = event_tx.send(AppEvent::BookmarksLoaded(item_id.to_string(), Vec::new()));
+        }
+        Err(e) => {
+            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+        }
+    }
+}
+
+/// Sends the user's playlists as the server has them now.
+fn send_playlists(client: &ApiClient, event_tx: &mpsc::Sender<AppEvent>) {
+    match client.get_playlists() {
+        Ok(playlists) => {
+            let _ = event_tx.send(AppEvent::PlaylistsLoaded(playlists));
+        }
+        Err(e) => {
+            let _ = event_tx.send(AppEvent::ApiError(format!("{:?}", e)));
+        }
+    }
+}
+
+/// Fetches an item, falling back to the offline copy when the server is
+/// unreachable.
+fn fetch_item(
+    client: &ApiClient,
+    store: &Option<OfflineStore>,
+    item_id: &str,
+) -> Result<LibraryItem, ApiError> {
+    match client.get_library_item(item_id) {
+        Ok(item) => Ok(item),
+        Err(e) if e.is_unreachable() => store.as_ref().and_then(|s| s.load_item(item_id)).ok_or(e),
+        Err(e) => Err(e),
+    }
+}
+
+fn track_info(track: &AudioTrack) -> TrackInfo {
+    TrackInfo {
+        index: track.index,
+        start_offset: track.start_offset,
+        duration: track.duration,
+    }
+}
+
+/// Falls back to the progress journal when `update` couldn't be sent
+/// through a session. A session doesn't mark a book as finished, so that's
+/// sent separately, and journaled if it fails.
+fn record_progress(
+    client: &ApiClient,
+    journal: &mut Option<ProgressJournal>,
+    online: &mut bool,
+    update: &ProgressUpdate,
+    result: Option<Result<(), ApiError>>,
+) {
+    match result {
+        Some(Ok(())) if !update.is_finished => return,
+        // Sent straight away: the session just moved the server's last
+        // update past anything journaled, so the journal would drop it
+        Some(Ok(())) => match client.update_media_progress(
+            &update.item_id,
+            update.current_time,
+            update.duration,
+            true,
+        ) {
+            Ok(()) => return,
+            Err(e) => {
+                if e.is_unreachable() {
+                    *online = false;
+                }
+                eprintln!("Failed to mark book as finished: {:?}", e);
+            }
+        },
+        Some(Err(e)) => {
+            if e.is_unreachable() {
+                *online = false;
+            }
+            eprintln!("Failed to sync session: {:?}", e);
+        }
+        _ => {}
+    }
+
+    match journal {
+        Some(journal) => {
+            journal.record(
+                &update.item_id,
+                update.current_time,
+                update.duration,
+                update.is_finished,
+            );
+            if *online {
+                *online = flush_journal(client, journal);
+            }
+        }
+        None => {
+            if let Err(e) = client.update_media_progress(
+                &update.item_id,
+                update.current_time,
+                update.duration,
+                update.is_finished,
+            ) {
+                eprintln!("Failed to update progress: {:?}", e);
+            }
+        }
+    }
+}
+
+/// Pushes journaled progress to the server. Returns false if the server
+/// couldn't be reached.
+fn flush_journal(client: &ApiClient, journal: &mut ProgressJournal) -> bool {
+    let mut unreachable = false;
+    journal.flush(|entry| push_progress(client, entry, &mut unreachable));
+    !unreachable
+}
+
+/// Sends one journal entry unless the server already has newer progress
+/// for the item, e.g. from listening on another device.
+fn push_progress(client: &ApiClient, entry: &JournalEntry, unreachable: &mut bool) -> SyncOutcome {
+    let classify = |e: ApiError, unreachable: &mut bool| {
+        if e.is_unreachable() {
+            *unreachable = true;
+            SyncOutcome::Retry
+        } else {
+            match e {
+                ApiError::Http(code) if code < 500 => SyncOutcome::Rejected,
+                _ => SyncOutcome::Retry,
+            }
+        }
+    };
+
+    match client.get_media_progress(&entry.item_id) {
+        Ok(remote) if remote.last_update > entry.recorded_at => return SyncOutcome::Superseded,
+        Ok(_) | Err(ApiError::NotFound) => {}
+        Err(e) => return classify(e, unreachable),
+    }
+
+    match client.update_media_progress(
+        &entry.item_id,
+        entry.current_time,
+        entry.duration,
+        entry.is_finished,
+    ) {
+        Ok(()) => SyncOutcome::Sent,
+        Err(e) => classify(e, unreachable),
+    }
+}
+
+/// Picks the downloaded book with the most recent local progress.
+fn offline_continue_listening(
+    store: &Option<OfflineStore>,
+    journal: &Option<ProgressJournal>,
+) -> Option<(LibraryItem, f64)> {
+    let store = store.as_ref()?;
+    let entry = journal.as_ref()?.latest()?;
+    let item = store.load_item(&entry.item_id)?;
+    Some((item, entry.current_time))
+}
+
+/// Plays from the offline store or the cache when possible. Otherwise
+/// downloads the track into the cache in the background and plays from the
+/// download as it arrives.
+fn resolve_source(
+    client: &ApiClient,
+    cache: &SharedCache,
+    store: &Option<OfflineStore>,
+    item_id: &str,
+    track: &AudioTrack,
+    event_tx: &mpsc::Sender<AppEvent>,
+) -> AudioSource {
+    if let Some(path) = store.as_ref().and_then(|s| s.track_path(item_id, track)) {
+        return AudioSource::File(path);
+    }
+
+    let Some(cache) = cache else {
+        return client.track_source(track);
+    };
+
+    if let Some(path) = cache.lock().unwrap().lookup(item_id, track) {
+        return AudioSource::File(path);
+    }
+
+    let Some(part_path) = cache.lock().unwrap().begin_download(item_id, track) else {
+        return client.track_source(track);
+    };
+
+    // Created up front so the player can open it before the first bytes land
+    let _ = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&part_path);
+    let source = client.downloading_source(track, part_path.clone());
+    download::spawn(
+        client.clone(),
+        cache.clone(),
+        item_id.to_string(),
+        track.clone(),
+        part_path,
+        event_tx.clone(),
+    );
+    source
+}
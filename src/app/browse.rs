@@ -0,0 +1,231 @@
+use crate::api::models::{Library, LibraryItem};
+use crate::app::{decrement, increment};
+
+pub const COLLECTIONS_LIBRARY_ID: &str = "decibelle-collections";
+pub const PLAYLISTS_LIBRARY_ID: &str = "decibelle-playlists";
+
+/// What the library panel lists.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum BrowseMode {
+    Books,
+    Series,
+    Authors,
+    Narrators,
+    /// Only shown by the collections pseudo-library
+    Collections,
+    /// Only shown by the playlists pseudo-library
+    Playlists,
+}
+
+impl BrowseMode {
+    pub fn label(self) -> &'static str {
+        match self {
+            BrowseMode::Books => "Books",
+            BrowseMode::Series => "Series",
+            BrowseMode::Authors => "Authors",
+            BrowseMode::Narrators => "Narrators",
+            BrowseMode::Collections => "Collections",
+            BrowseMode::Playlists => "Playlists",
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            BrowseMode::Books => BrowseMode::Series,
+            BrowseMode::Series => BrowseMode::Authors,
+            BrowseMode::Authors => BrowseMode::Narrators,
+            BrowseMode::Narrators => BrowseMode::Books,
+            BrowseMode::Collections | BrowseMode::Playlists => self,
+        }
+    }
+
+    pub fn is_pseudo_library(self) -> bool {
+        matches!(self, BrowseMode::Collections | BrowseMode::Playlists)
+    }
+
+    /// Filter group selecting the books of one of this mode's groups.
+    pub fn filter_group(self) -> Option<&'static str> {
+        match self {
+            BrowseMode::Books | BrowseMode::Collections | BrowseMode::Playlists => None,
+            BrowseMode::Series => Some("series"),
+            BrowseMode::Authors => Some("authors"),
+            BrowseMode::Narrators => Some("narrators"),
+        }
+    }
+}
+
+/// Pseudo-libraries listing the user's collections and playlists, which
+/// span the real libraries.
+pub fn pseudo_libraries() -> Vec<Library> {
+    [
+        (COLLECTIONS_LIBRARY_ID, "Collections"),
+        (PLAYLISTS_LIBRARY_ID, "Playlists"),
+    ]
+    .into_iter()
+    .map(|(id, name)| Library {
+        id: id.to_string(),
+        name: name.to_string(),
+        media_type: "book".to_string(),
+        display_order: None,
+        icon: None,
+        provider: None,
+        folders: None,
+        settings: None,
+        created_at: None,
+        last_update: None,
+    })
+    .collect()
+}
+
+/// The mode a pseudo-library is always browsed in.
+pub fn pseudo_library_mode(library_id: &str) -> Option<BrowseMode> {
+    match library_id {
+        COLLECTIONS_LIBRARY_ID => Some(BrowseMode::Collections),
+        PLAYLISTS_LIBRARY_ID => Some(BrowseMode::Playlists),
+        _ => None,
+    }
+}
+
+/// A named group of books, such as a series, a playlist or everything by
+/// an author.
+#[derive(Debug, Clone)]
+pub struct BrowseGroup {
+    /// Value of the group's filter: the series or author id, or the
+    /// narrator's name
+    pub id: String,
+    pub name: String,
+    /// Number of books, known before the books themselves are loaded
+    pub count: usize,
+    pub books: Vec<LibraryItem>,
+}
+
+/// State of the library panel when it lists groups instead of books.
+/// Opening a group lists its books in place of the groups.
+pub struct Browser {
+    pub mode: BrowseMode,
+    pub groups: Vec<BrowseGroup>,
+    pub selected_group: usize,
+    /// Index into `groups` of the group whose books are listed
+    pub open_group: Option<usize>,
+    pub selected_book: usize,
+    pub loading: bool,
+    /// Id of a group to open as soon as the groups arrive
+    pub reveal: Option<String>,
+}
+
+impl Browser {
+    pub fn new() -> Self {
+        Self {
+            mode: BrowseMode::Books,
+            groups: Vec::new(),
+            selected_group: 0,
+            open_group: None,
+            selected_book: 0,
+            loading: false,
+            reveal: None,
+        }
+    }
+
+    pub fn set_groups(&mut self, groups: Vec<BrowseGroup>) {
+        self.loading = false;
+        self.groups = groups;
+        self.selected_group = 0;
+        self.open_group = None;
+        self.selected_book = 0;
+
+        if let Some(id) = self.reveal.take()
+            && let Some(index) = self.groups.iter().position(|group| group.id == id)
+        {
+            self.selected_group = index;
+            self.open_group = Some(index);
+        }
+    }
+
+    /// Fills in the books of the group with the given id.
+    pub fn set_books(&mut self, id: &str, books: Vec<LibraryItem>) {
+        if let Some(group) = self.groups.iter_mut().find(|group| group.id == id) {
+            group.count = books.len();
+            group.books = books;
+            self.loading = false;
+        }
+    }
+
+    pub fn open(&self) -> Option<&BrowseGroup> {
+        self.groups.get(self.open_group?)
+    }
+
+    pub fn selected_book(&self) -> Option<&LibraryItem> {
+        self.open()?.books.get(self.selected_book)
+    }
+
+    /// Rows shown: the open group's books, or else the groups.
+    pub fn len(&self) -> usize {
+        match self.open() {
+            Some(group) => group.books.len(),
+            None => self.groups.len(),
+        }
+    }
+
+    pub fn selected(&self) -> usize {
+        match self.open_group {
+            Some(_) => self.selected_book,
+            None => self.selected_group,
+        }
+    }
+
+    pub fn select(&mut self, row: usize) {
+        if row >= self.len() {
+            return;
+        }
+        match self.open_group {
+            Some(_) => self.selected_book = row,
+            None => self.selected_group = row,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if self.len() > 0 {
+            self.select(increment(self.selected(), self.len(), false));
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if self.len() > 0 {
+            self.select(decrement(self.selected(), self.len(), false));
+        }
+    }
+}
+
+/// Position of `item` in the series, as the server stores it, e.g. "2.5".
+pub fn series_sequence<'a>(item: &'a LibraryItem, series_id: &str) -> Option<&'a str> {
+    item.media
+        .as_ref()?
+        .metadata
+        .series
+        .as_ref()?
+        .iter()
+        .find(|s| s.id == series_id)?
+        .sequence
+        .as_deref()
+}
+
+/// Puts books in reading order. Books without a sequence go last.
+pub fn sort_by_sequence(books: &mut [LibraryItem], series_id: &str) {
+    let key = |item: &LibraryItem| {
+        series_sequence(item, series_id)
+            .and_then(|s| s.trim().parse::<f64>().ok())
+            .unwrap_or(f64::INFINITY)
+    };
+    books.sort_by(|a, b| key(a).total_cmp(&key(b)));
+}
+
+/// Puts books in title order.
+pub fn sort_by_title(books: &mut [LibraryItem]) {
+    books.sort_by_cached_key(|item| {
+        item.media
+            .as_ref()
+            .and_then(|m| m.metadata.title.as_deref())
+            .unwrap_or_default()
+            .to_lowercase()
+    });
+}
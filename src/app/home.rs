@@ -0,0 +1,97 @@
+use crate::api::models::{LibraryItem, PersonalizedShelf};
+use crate::app::{decrement, increment};
+
+/// The home view: a library's personalized shelves, such as Continue
+/// Listening and Recently Added, one row of books each.
+pub struct Home {
+    pub library_id: String,
+    pub shelves: Vec<PersonalizedShelf>,
+    pub selected_shelf: usize,
+    pub selected_entry: usize,
+    pub loading: bool,
+}
+
+impl Home {
+    pub fn new(library_id: String) -> Self {
+        Self {
+            library_id,
+            shelves: Vec::new(),
+            selected_shelf: 0,
+            selected_entry: 0,
+            loading: true,
+        }
+    }
+
+    /// Keeps the shelves of books. Others list series or authors, which
+    /// can't be played directly.
+    pub fn set_shelves(&mut self, shelves: Vec<PersonalizedShelf>) {
+        self.loading = false;
+        self.shelves = shelves
+            .into_iter()
+            .filter(|shelf| shelf.shelf_type == "book")
+            .map(|mut shelf| {
+                shelf.entities.retain(|item| item.media.is_some());
+                shelf
+            })
+            .filter(|shelf| !shelf.entities.is_empty())
+            .collect();
+        self.selected_shelf = 0;
+        self.selected_entry = 0;
+    }
+
+    pub fn selected_item(&self) -> Option<&LibraryItem> {
+        self.shelves
+            .get(self.selected_shelf)?
+            .entities
+            .get(self.selected_entry)
+    }
+
+    pub fn select(&mut self, shelf: usize, entry: usize) {
+        if self
+            .shelves
+            .get(shelf)
+            .is_some_and(|s| entry < s.entities.len())
+        {
+            self.selected_shelf = shelf;
+            self.selected_entry = entry;
+        }
+    }
+
+    pub fn next_shelf(&mut self) {
+        if !self.shelves.is_empty() {
+            self.selected_shelf = increment(self.selected_shelf, self.shelves.len(), false);
+            self.clamp_entry();
+        }
+    }
+
+    pub fn previous_shelf(&mut self) {
+        if !self.shelves.is_empty() {
+            self.selected_shelf = decrement(self.selected_shelf, self.shelves.len(), false);
+            self.clamp_entry();
+        }
+    }
+
+    pub fn next_entry(&mut self) {
+        if let Some(shelf) = self.shelves.get(self.selected_shelf) {
+            self.selected_entry = increment(self.selected_entry, shelf.entities.len(), false);
+        }
+    }
+
+    pub fn previous_entry(&mut self) {
+        if let Some(shelf) = self.shelves.get(self.selected_shelf) {
+            self.selected_entry = decrement(self.selected_entry, shelf.entities.len(), false);
+        }
+    }
+
+    /// Ids of every book on the shelves, for fetching their covers.
+    pub fn item_ids(&self) -> impl Iterator<Item = &str> {
+        self.shelves
+            .iter()
+            .flat_map(|shelf| shelf.entities.iter().map(|item| item.id.as_str()))
+    }
+
+    fn clamp_entry(&mut self) {
+        let len = self.shelves[self.selected_shelf].entities.len();
+        self.selected_entry = self.selected_entry.min(len.saturating_sub(1));
+    }
+}
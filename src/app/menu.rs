@@ -0,0 +1,47 @@
+use crate::app::{decrement, increment};
+
+/// What a menu's choice applies to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MenuKind {
+    OutputDevice,
+    SleepTimer,
+    LibrarySort,
+    LibraryFilter,
+    FilterGenre,
+    FilterTag,
+    FilterNarrator,
+    NextInSeries,
+    Person,
+    Playlist,
+}
+
+/// A popup list of choices shown over the main view.
+pub struct Menu {
+    pub kind: MenuKind,
+    pub title: String,
+    pub items: Vec<String>,
+    pub selected: usize,
+}
+
+impl Menu {
+    pub fn new(kind: MenuKind, title: impl Into<String>, items: Vec<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            items,
+            selected: 0,
+        }
+    }
+
+    pub fn next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = increment(self.selected, self.items.len(), true);
+        }
+    }
+
+    pub fn previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = decrement(self.selected, self.items.len(), true);
+        }
+    }
+}
@@ -1,3 +1,10 @@
+pub mod browse;
+pub mod home;
+pub mod menu;
+pub mod prompt;
+pub mod queue;
+pub mod search;
+pub mod sleep_timer;
 pub mod state;
 
 pub fn increment(x: usize, len: usize, wrap: bool) -> usize {
@@ -0,0 +1,42 @@
+/// What a prompt's input is for.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptKind {
+    SleepMinutes,
+    /// Title for a new bookmark at the given item and position
+    NewBookmark(String, f64),
+    /// New title for the bookmark at the given item and position
+    EditBookmark(String, f64),
+    /// Name for a new playlist in the given library, starting with the item
+    NewPlaylist(String, String),
+}
+
+/// A single line of text input shown over the main view.
+pub struct Prompt {
+    pub kind: PromptKind,
+    pub title: String,
+    pub input: String,
+}
+
+impl Prompt {
+    pub fn new(kind: PromptKind, title: impl Into<String>) -> Self {
+        Self {
+            kind,
+            title: title.into(),
+            input: String::new(),
+        }
+    }
+
+    /// Starts with `input` already filled in, for editing.
+    pub fn with_input(mut self, input: impl Into<String>) -> Self {
+        self.input = input.into();
+        self
+    }
+
+    pub fn push(&mut self, c: char) {
+        self.input.push(c);
+    }
+
+    pub fn pop(&mut self) {
+        self.input.pop();
+    }
+}
@@ -0,0 +1,65 @@
+use serde::{Deserialize, Serialize};
+
+use crate::api::models::{Chapter, LibraryItem};
+
+/// Chapters of a book played in a row, from the start of the first to the
+/// end of the last.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterRange {
+    pub first: String,
+    pub last: String,
+    pub start: f64,
+    pub end: f64,
+}
+
+impl ChapterRange {
+    pub fn new(chapter: &Chapter) -> Self {
+        Self {
+            first: chapter.title.clone(),
+            last: chapter.title.clone(),
+            start: chapter.start,
+            end: chapter.end,
+        }
+    }
+
+    /// Takes in `chapter` if it follows straight on. Returns false if it
+    /// doesn't.
+    pub fn extend(&mut self, chapter: &Chapter) -> bool {
+        if (chapter.start - self.end).abs() > 0.5 {
+            return false;
+        }
+        self.last = chapter.title.clone();
+        self.end = chapter.end;
+        true
+    }
+}
+
+/// A book, or some of its chapters, waiting to be played.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueueEntry {
+    pub item: LibraryItem,
+    /// The whole book when `None`
+    #[serde(default)]
+    pub range: Option<ChapterRange>,
+}
+
+impl QueueEntry {
+    pub fn title(&self) -> String {
+        self.item
+            .media
+            .as_ref()
+            .and_then(|m| m.metadata.title.clone())
+            .unwrap_or_else(|| self.item.id.clone())
+    }
+
+    /// Title and, for a chapter range, which chapters.
+    pub fn label(&self) -> String {
+        match &self.range {
+            None => self.title(),
+            Some(range) if range.first == range.last => {
+                format!("{} · {}", self.title(), range.first)
+            }
+            Some(range) => format!("{} · {} – {}", self.title(), range.first, range.last),
+        }
+    }
+}
@@ -0,0 +1,228 @@
+use crate::api::models::{LibraryItem, MediaMetadata};
+
+/// Libraries with at least this many items are searched on the server,
+/// where the index covers more than the loaded metadata.
+pub const SERVER_SEARCH_MIN_ITEMS: usize = 500;
+
+/// Metadata a search query is matched against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SearchField {
+    Title,
+    Author,
+    Narrator,
+    Series,
+}
+
+/// Where a query matched within one field.
+#[derive(Debug, Clone)]
+pub struct FieldMatch {
+    pub field: SearchField,
+    pub text: String,
+    /// Char indices into `text` that matched the query
+    pub positions: Vec<usize>,
+}
+
+/// A library list item matching the query.
+#[derive(Debug, Clone)]
+pub struct ItemMatch {
+    pub index: usize,
+    /// `None` for server results the query doesn't match locally
+    pub field: Option<FieldMatch>,
+    score: i32,
+}
+
+/// The `/` search over the library list.
+pub struct LibrarySearch {
+    pub query: String,
+    /// True while the query is being typed
+    pub editing: bool,
+    /// Matches in list order
+    pub matches: Vec<ItemMatch>,
+    /// Index into `matches` of the selected one
+    pub current: usize,
+    /// The full list while server results are shown in its place
+    pub stashed_items: Option<Vec<LibraryItem>>,
+}
+
+impl LibrarySearch {
+    pub fn new() -> Self {
+        Self {
+            query: String::new(),
+            editing: true,
+            matches: Vec::new(),
+            current: 0,
+            stashed_items: None,
+        }
+    }
+
+    /// Matches `items` against the query and selects the best match.
+    ///
+    /// When server results are shown every item is kept as a match, since
+    /// the server also searches fields that aren't loaded here.
+    pub fn update(&mut self, items: &[LibraryItem]) {
+        let keep_all = self.stashed_items.is_some();
+        self.matches = items
+            .iter()
+            .enumerate()
+            .filter_map(|(index, item)| {
+                let field = item
+                    .media
+                    .as_ref()
+                    .and_then(|m| best_field_match(&self.query, &m.metadata));
+                match field {
+                    Some((score, field)) => Some(ItemMatch {
+                        index,
+                        field: Some(field),
+                        score,
+                    }),
+                    None if keep_all => Some(ItemMatch {
+                        index,
+                        field: None,
+                        score: 0,
+                    }),
+                    None => None,
+                }
+            })
+            .collect();
+
+        // First of the best scores, so ties favour the top of the list
+        self.current = self
+            .matches
+            .iter()
+            .enumerate()
+            .rev()
+            .max_by_key(|(_, m)| m.score)
+            .map_or(0, |(i, _)| i);
+    }
+
+    /// Matches again after more items were loaded, keeping the selection
+    /// at list index `selected`.
+    pub fn refresh(&mut self, items: &[LibraryItem], selected: usize) {
+        self.update(items);
+        self.current = self
+            .matches
+            .partition_point(|m| m.index < selected)
+            .min(self.matches.len().saturating_sub(1));
+    }
+
+    /// List index of the selected match.
+    pub fn current_index(&self) -> Option<usize> {
+        self.matches.get(self.current).map(|m| m.index)
+    }
+
+    pub fn match_for(&self, index: usize) -> Option<&ItemMatch> {
+        self.matches
+            .binary_search_by_key(&index, |m| m.index)
+            .ok()
+            .map(|i| &self.matches[i])
+    }
+}
+
+fn best_field_match(query: &str, metadata: &MediaMetadata) -> Option<(i32, FieldMatch)> {
+    let join = |names: Vec<&str>| (!names.is_empty()).then(|| names.join(", "));
+
+    let author = metadata.author_name.clone().or_else(|| {
+        metadata
+            .authors
+            .as_ref()
+            .and_then(|a| join(a.iter().map(|a| a.name.as_str()).collect()))
+    });
+    let narrator = metadata.narrator_name.clone().or_else(|| {
+        metadata
+            .narrators
+            .as_ref()
+            .and_then(|n| join(n.iter().map(String::as_str).collect()))
+    });
+    let series = metadata.series_name.clone().or_else(|| {
+        metadata
+            .series
+            .as_ref()
+            .and_then(|s| join(s.iter().map(|s| s.name.as_str()).collect()))
+    });
+
+    let fields = [
+        (SearchField::Title, metadata.title.clone()),
+        (SearchField::Author, author),
+        (SearchField::Narrator, narrator),
+        (SearchField::Series, series),
+    ];
+
+    let mut best: Option<(i32, FieldMatch)> = None;
+    for (field, text) in fields {
+        let Some(text) = text else {
+            continue;
+        };
+        if let Some((score, positions)) = fuzzy_match(query, &text)
+            && best.as_ref().is_none_or(|(best, _)| score > *best)
+        {
+            best = Some((
+                score,
+                FieldMatch {
+                    field,
+                    text,
+                    positions,
+                },
+            ));
+        }
+    }
+    best
+}
+
+/// Matches `query` as a case-insensitive subsequence of `text`.
+///
+/// Returns a score and the char indices that matched. Runs of consecutive
+/// characters and matches at the start of words score higher, so "wok"
+/// ranks "The Way of Kings" above "Two Weeks".
+pub fn fuzzy_match(query: &str, text: &str) -> Option<(i32, Vec<usize>)> {
+    let query: Vec<char> = query.trim().chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return None;
+    }
+    // One lowercase char per original char keeps the indices aligned
+    let text: Vec<char> = text
+        .chars()
+        .map(|c| c.to_lowercase().next().unwrap_or(c))
+        .collect();
+
+    // Greedy from each place the first char occurs, keeping the best
+    let mut best: Option<(i32, Vec<usize>)> = None;
+    for start in (0..text.len()).filter(|&i| text[i] == query[0]) {
+        let mut positions = Vec::with_capacity(query.len());
+        let mut i = start;
+        for &c in &query {
+            while i < text.len() && text[i] != c {
+                i += 1;
+            }
+            if i == text.len() {
+                break;
+            }
+            positions.push(i);
+            i += 1;
+        }
+        if positions.len() < query.len() {
+            // Later starts can only find less
+            break;
+        }
+
+        let score = score_positions(&text, &positions);
+        if best.as_ref().is_none_or(|(best, _)| score > *best) {
+            best = Some((score, positions));
+        }
+    }
+    best
+}
+
+fn score_positions(text: &[char], positions: &[usize]) -> i32 {
+    let mut score = 0;
+    for (n, &i) in positions.iter().enumerate() {
+        score += 1;
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 8;
+        }
+        if n > 0 && positions[n - 1] + 1 == i {
+            score += 5;
+        }
+    }
+    // Slightly prefer matches near the start
+    score - (positions[0] as i32).min(5)
+}
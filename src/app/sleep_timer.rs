@@ -0,0 +1,19 @@
+use std::time::Duration;
+
+/// How long playback fades out before the timer pauses it.
+pub const FADE_OUT: Duration = Duration::from_secs(20);
+/// Any input this close to the end extends the timer.
+pub const EXTEND_WINDOW: Duration = Duration::from_secs(60);
+pub const EXTENSION: Duration = Duration::from_secs(5 * 60);
+
+/// Lengths offered in the sleep timer menu, in minutes.
+pub const PRESET_MINUTES: [u64; 5] = [5, 15, 30, 45, 60];
+
+/// When playback should pause for the night.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SleepTimer {
+    /// Playing time left; time spent paused doesn't count
+    Remaining(Duration),
+    /// Until the chapter being played ends
+    EndOfChapter,
+}
@@ -1,26 +1,64 @@
-use std::path::PathBuf;
+use std::collections::{HashMap, HashSet};
 use std::sync::mpsc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crossterm::event::{KeyCode, KeyEvent, MouseButton, MouseEvent, MouseEventKind};
 use ratatui::layout::Rect;
 
-use crate::api::models::{AudioTrack, Chapter, Library, LibraryItem};
-use crate::api::thread::ApiCommand;
+use crate::api::models::{
+    Bookmark, Chapter, Collection, Library, LibraryAuthor, LibraryFilterData, LibraryItem,
+    MediaProgress, Narrator, PersonalizedShelf, Playlist, Series,
+};
+use crate::api::query::{LibraryFilter, LibraryQuery, LibrarySort, ProgressFilter};
+use crate::api::thread::{ApiCommand, ProgressUpdate};
+use crate::app::browse::{self, BrowseGroup, BrowseMode, Browser};
+use crate::app::home::Home;
+use crate::app::menu::{Menu, MenuKind};
+use crate::app::prompt::{Prompt, PromptKind};
+use crate::app::queue::{ChapterRange, QueueEntry};
+use crate::app::search::{self, LibrarySearch};
+use crate::app::sleep_timer::{self, SleepTimer};
 use crate::app::{decrement, increment};
-use crate::events::types::TrackInfo;
-use crate::player::commands::{PlayerCommand, PlayerState};
+use crate::config::RewindRule;
+use crate::events::types::{DownloadProgress, ItemsPage, TrackInfo};
+use crate::offline::{OFFLINE_LIBRARY_ID, now_ms, offline_library};
+use crate::player::commands::{AudioSource, PlayerCommand, PlayerState};
+use crate::player::stretch::{MAX_SPEED, MIN_SPEED};
+use crate::player::thread::MAX_VOLUME;
+use crate::prefs::Preferences;
+use crate::ui::format_duration;
 use crate::ui::loading::LoadingAnimation;
 use crate::ui::notifications::NotificationManager;
 
+/// How often progress is synced to the server while playing.
+const SESSION_SYNC_INTERVAL: Duration = Duration::from_secs(15);
+/// How close to the end of the loaded items the selection gets before the
+/// next page is fetched.
+const PREFETCH_MARGIN: usize = 20;
+
 #[derive(Default, Clone)]
 #[allow(dead_code)]
 pub struct LayoutRegions {
     pub library_list: Option<Rect>,
     pub chapters: Option<Rect>,
+    pub bookmarks: Option<Rect>,
+    pub queue: Option<Rect>,
     pub controls: Option<Rect>,
     pub progress_bar: Option<Rect>,
     pub info_panel: Option<Rect>,
+    /// Screen rows of the info panel's author and narrator lines
+    pub info_links: Vec<(u16, BrowseMode)>,
+    /// Area of the home view while it's shown
+    pub home: Option<Rect>,
+    /// Each book on the home view with its shelf and position on it
+    pub home_cards: Vec<(Rect, usize, usize)>,
+    /// First row shown in each list, so clicks map to the right item
+    pub library_offset: usize,
+    pub chapters_offset: usize,
+    pub bookmarks_offset: usize,
+    pub queue_offset: usize,
+    /// First shelf shown on the home view
+    pub home_offset: usize,
 }
 
 pub struct App {
@@ -32,6 +70,8 @@ pub struct App {
     pub libraries: Vec<Library>,
     pub library_items: Vec<LibraryItem>,
     pub chapters: Vec<Chapter>,
+    pub bookmarks: Vec<Bookmark>,
+    pub selected_bookmark_index: usize,
 
     pub current_chapter: Option<Chapter>,
     pub current_library_item: Option<LibraryItem>,
@@ -39,8 +79,17 @@ pub struct App {
 
     pub loading_libraries: bool,
     pub loading_items: bool,
+    /// A further page of library items is on its way
+    loading_more_items: bool,
+    /// Items in the library list across all pages, loaded or not
+    pub items_total: usize,
+    items_next_page: usize,
     pub loading_chapters: bool,
 
+    // Offline
+    pub offline: bool,
+    pub offline_items: HashSet<String>,
+
     // Selection state
     pub focus: Focus,
 
@@ -51,10 +100,29 @@ pub struct App {
     pub player_state: PlayerState,
     pub current_position: Duration,
     pub total_duration: Duration,
-    pub _playback_speed: f32,
+    pub playback_speed: f32,
+    pub volume: f32,
+    pub muted: bool,
+    pub voice_boost: bool,
+    pub trim_silence: bool,
+    /// Cut by silence trimming from the current book
+    pub time_saved: Duration,
+    /// Book `time_saved` belongs to, and how much of it was saved before
+    /// the player last started, which resets the player's count
+    time_saved_item: Option<String>,
+    time_saved_before: Duration,
 
     pub current_track_info: Option<TrackInfo>,
-    pub current_tracks: Vec<AudioTrack>,
+
+    // Listening session
+    pub listening_since: Option<Instant>,
+    pub unsynced_listening: f64,
+    pub last_session_sync: Instant,
+
+    // Sleep timer
+    pub sleep_timer: Option<SleepTimer>,
+    pub sleep_fading: bool,
+    pub last_tick: Instant,
 
     // Communication
     pub player_tx: mpsc::Sender<PlayerCommand>,
@@ -63,8 +131,12 @@ pub struct App {
     // Notifications
     pub notifications: NotificationManager,
 
+    pub prefs: Preferences,
+    pub rewind_rules: Vec<RewindRule>,
+
     pub loading_animation: LoadingAnimation,
     pub is_downloading: bool,
+    pub download_progress: Option<DownloadProgress>,
     pub show_remaining_time: bool,
 
     // Control
@@ -73,18 +145,56 @@ pub struct App {
     pub pending_resume_position: Option<f64>,
     pub error_message: Option<String>,
     pub layout_regions: LayoutRegions,
+    pub menu: Option<Menu>,
+    pub prompt: Option<Prompt>,
+    pub search: Option<LibrarySearch>,
+    /// Filter values of the last library asked for, by library id
+    filter_data: Option<(String, LibraryFilterData)>,
+    /// Filter menu to open once filter values arrive
+    pending_filter_menu: Option<MenuKind>,
+    pub browse: Browser,
+    /// Listening progress by item id
+    pub item_progress: HashMap<String, MediaProgress>,
+    series_auto_advance: bool,
+    /// Item and series of a finished book whose successor is being looked up
+    finished_in_series: Option<(String, String)>,
+    /// Book offered to play after the one that finished
+    next_in_series: Option<LibraryItem>,
+    /// Authors and narrators offered by the info panel, by id
+    people_choices: Vec<(BrowseMode, String)>,
+    playlists: Vec<Playlist>,
+    /// Item the playlist menu adds or removes
+    playlist_target: Option<LibraryItem>,
+    /// Open the playlist menu once playlists arrive
+    pending_playlist_menu: bool,
+    /// Ids of the playlists offered by the playlist menu, in order
+    playlist_choices: Vec<String>,
+    pub selected_queue_index: usize,
+    /// Where the queue entry being played ends, if it's a chapter range
+    play_until: Option<f64>,
+    /// The last track ran out; playback stopping next means the book ended
+    track_ended: bool,
+    /// Shown in place of the panels while open
+    pub home: Option<Home>,
 }
 
 #[derive(PartialEq, Clone, Copy)]
 pub enum Focus {
     Libraries,
     Chapters,
+    Bookmarks,
+    Queue,
     Controls,
     InfoPanel,
 }
 
 impl App {
-    pub fn new(player_tx: mpsc::Sender<PlayerCommand>, api_tx: mpsc::Sender<ApiCommand>) -> Self {
+    pub fn new(
+        player_tx: mpsc::Sender<PlayerCommand>,
+        api_tx: mpsc::Sender<ApiCommand>,
+        rewind_rules: Vec<RewindRule>,
+        series_auto_advance: bool,
+    ) -> Self {
         Self {
             selected_library_index: 0,
             selected_library_item_index: 0,
@@ -93,6 +203,8 @@ impl App {
             libraries: Vec::new(),
             library_items: Vec::new(),
             chapters: Vec::new(),
+            bookmarks: Vec::new(),
+            selected_bookmark_index: 0,
 
             current_chapter: None,
             current_item_id: None,
@@ -100,8 +212,14 @@ impl App {
 
             loading_libraries: false,
             loading_items: false,
+            loading_more_items: false,
+            items_total: 0,
+            items_next_page: 0,
             loading_chapters: false,
 
+            offline: false,
+            offline_items: HashSet::new(),
+
             focus: Focus::Libraries,
 
             info_scroll: 0,
@@ -109,18 +227,35 @@ impl App {
             player_state: PlayerState::Stopped,
             current_position: Duration::ZERO,
             total_duration: Duration::ZERO,
-            _playback_speed: 1.0,
+            playback_speed: 1.0,
+            volume: 1.0,
+            muted: false,
+            voice_boost: false,
+            trim_silence: false,
+            time_saved: Duration::ZERO,
+            time_saved_item: None,
+            time_saved_before: Duration::ZERO,
 
             current_track_info: None,
-            current_tracks: Vec::new(),
+
+            listening_since: None,
+            unsynced_listening: 0.0,
+            last_session_sync: Instant::now(),
+            sleep_timer: None,
+            sleep_fading: false,
+            last_tick: Instant::now(),
 
             player_tx,
             api_tx,
 
             notifications: NotificationManager::new(),
 
+            prefs: Preferences::load(),
+            rewind_rules,
+
             loading_animation: LoadingAnimation::new(),
             is_downloading: false,
+            download_progress: None,
             show_remaining_time: false,
 
             should_quit: false,
@@ -128,6 +263,25 @@ impl App {
             pending_resume_position: None,
             error_message: None,
             layout_regions: LayoutRegions::default(),
+            menu: None,
+            prompt: None,
+            search: None,
+            filter_data: None,
+            pending_filter_menu: None,
+            browse: Browser::new(),
+            item_progress: HashMap::new(),
+            series_auto_advance,
+            finished_in_series: None,
+            next_in_series: None,
+            people_choices: Vec::new(),
+            playlists: Vec::new(),
+            playlist_target: None,
+            pending_playlist_menu: false,
+            playlist_choices: Vec::new(),
+            selected_queue_index: 0,
+            play_until: None,
+            track_ended: false,
+            home: None,
         }
     }
 
@@ -137,10 +291,194 @@ impl App {
     }
 
     pub fn load_library_items(&mut self, library_id: &str) {
+        if let Some(mode) = browse::pseudo_library_mode(library_id) {
+            // Its groups are listed instead
+            self.browse.mode = mode;
+            self.library_items.clear();
+            self.selected_library_item_index = 0;
+            self.items_total = 0;
+            self.loading_items = false;
+            return;
+        }
+        if self.browse.mode.is_pseudo_library() {
+            self.browse.mode = BrowseMode::Books;
+        }
+
         self.loading_items = true;
-        let _ = self
-            .api_tx
-            .send(ApiCommand::FetchLibraryItems(library_id.to_string()));
+        self.request_items_page(library_id, 0);
+    }
+
+    fn request_items_page(&mut self, library_id: &str, page: usize) {
+        let query = self.library_query(library_id);
+        let _ = self.api_tx.send(ApiCommand::FetchLibraryItems(
+            library_id.to_string(),
+            query,
+            page,
+        ));
+    }
+
+    fn library_query(&self, library_id: &str) -> LibraryQuery {
+        self.prefs
+            .library_queries
+            .get(library_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Fetches the next page of items once the selection gets near the end
+    /// of what's loaded.
+    fn prefetch_items(&mut self) {
+        if self.loading_items
+            || self.loading_more_items
+            || self.library_items.len() >= self.items_total
+            || self.selected_library_item_index + PREFETCH_MARGIN < self.library_items.len()
+        {
+            return;
+        }
+        // Server search results stand in for the list
+        if self
+            .search
+            .as_ref()
+            .is_some_and(|s| s.stashed_items.is_some())
+        {
+            return;
+        }
+        let Some(library) = self.libraries.get(self.selected_library_index) else {
+            return;
+        };
+
+        let library_id = library.id.clone();
+        self.loading_more_items = true;
+        self.request_items_page(&library_id, self.items_next_page);
+    }
+
+    /// The selected library, unless it's the offline one, which the server
+    /// can't sort or filter.
+    fn queryable_library_id(&mut self) -> Option<String> {
+        let library = self.libraries.get(self.selected_library_index)?;
+        if library.id == OFFLINE_LIBRARY_ID {
+            self.notifications
+                .warning("Sorting and filtering need the server");
+            return None;
+        }
+        Some(library.id.clone())
+    }
+
+    fn open_sort_menu(&mut self) {
+        let Some(library_id) = self.queryable_library_id() else {
+            return;
+        };
+        let query = self
+            .prefs
+            .library_queries
+            .get(&library_id)
+            .cloned()
+            .unwrap_or_default();
+
+        let items = LibrarySort::ALL
+            .iter()
+            .map(|&sort| {
+                if sort == query.sort {
+                    let arrow = if query.descending { "↓" } else { "↑" };
+                    format!("{} {}", sort.label(), arrow)
+                } else {
+                    sort.label().to_string()
+                }
+            })
+            .collect();
+
+        let mut menu = Menu::new(MenuKind::LibrarySort, " Sort By ", items);
+        menu.selected = LibrarySort::ALL
+            .iter()
+            .position(|&sort| sort == query.sort)
+            .unwrap_or(0);
+        self.menu = Some(menu);
+    }
+
+    fn open_filter_menu(&mut self) {
+        let Some(library_id) = self.queryable_library_id() else {
+            return;
+        };
+        let filter = self
+            .prefs
+            .library_queries
+            .get(&library_id)
+            .and_then(|query| query.filter.clone());
+
+        let progress_count = ProgressFilter::ALL.len();
+        let mut items = vec!["All".to_string()];
+        items.extend(ProgressFilter::ALL.iter().map(|p| p.label().to_string()));
+        items.extend(["Genre...", "Tag...", "Narrator..."].map(String::from));
+
+        let mut menu = Menu::new(MenuKind::LibraryFilter, " Filter ", items);
+        menu.selected = match filter {
+            None => 0,
+            Some(LibraryFilter::Progress(progress)) => {
+                1 + ProgressFilter::ALL
+                    .iter()
+                    .position(|&p| p == progress)
+                    .unwrap_or(0)
+            }
+            Some(LibraryFilter::Genre(_)) => progress_count + 1,
+            Some(LibraryFilter::Tag(_)) => progress_count + 2,
+            Some(LibraryFilter::Narrator(_)) => progress_count + 3,
+        };
+        self.menu = Some(menu);
+    }
+
+    /// Lists the library's genres, tags or narrators, fetching them first
+    /// if needed.
+    fn open_filter_values_menu(&mut self, kind: MenuKind) {
+        let Some(library_id) = self.queryable_library_id() else {
+            return;
+        };
+        let Some((_, data)) = self
+            .filter_data
+            .as_ref()
+            .filter(|(id, _)| *id == library_id)
+        else {
+            self.pending_filter_menu = Some(kind);
+            let _ = self.api_tx.send(ApiCommand::FetchFilterData(library_id));
+            return;
+        };
+
+        let (title, values) = match kind {
+            MenuKind::FilterGenre => ("Genre", &data.genres),
+            MenuKind::FilterTag => ("Tag", &data.tags),
+            _ => ("Narrator", &data.narrators),
+        };
+        if values.is_empty() {
+            self.notifications
+                .info(format!("No {}s in this library", title.to_lowercase()));
+            return;
+        }
+
+        let menu = Menu::new(kind, format!(" {} ", title), values.clone());
+        self.menu = Some(menu);
+    }
+
+    pub fn on_filter_data_loaded(&mut self, library_id: String, data: LibraryFilterData) {
+        self.filter_data = Some((library_id, data));
+        if let Some(kind) = self.pending_filter_menu.take() {
+            self.open_filter_values_menu(kind);
+        }
+    }
+
+    /// Changes how the selected library is listed, remembers it and
+    /// reloads the list.
+    fn update_library_query(&mut self, change: impl FnOnce(&mut LibraryQuery)) {
+        let Some(library_id) = self.queryable_library_id() else {
+            return;
+        };
+
+        change(
+            self.prefs
+                .library_queries
+                .entry(library_id.clone())
+                .or_default(),
+        );
+        self.prefs.save();
+        self.load_library_items(&library_id);
     }
 
     pub fn load_chapters(&mut self, item_id: &str) {
@@ -148,21 +486,154 @@ impl App {
         let _ = self
             .api_tx
             .send(ApiCommand::FetchItemChapters(item_id.to_string()));
+        let _ = self
+            .api_tx
+            .send(ApiCommand::FetchBookmarks(item_id.to_string()));
     }
 
-    fn sync_progress(&self) {
-        if let Some(ref item_id) = self.current_item_id {
-            let current_time = self.current_position.as_secs_f64();
-            let duration = self.get_total_duration();
-            let is_finished = duration > 0.0 && current_time >= duration - 1.0;
-
-            let _ = self.api_tx.send(ApiCommand::UpdateProgress {
-                item_id: item_id.clone(),
-                current_time,
-                duration,
-                is_finished,
-            });
+    /// Builds a progress update with the time listened since the last one.
+    fn take_progress_update(&mut self) -> Option<ProgressUpdate> {
+        let item_id = self.current_item_id.clone()?;
+
+        if let Some(ref mut since) = self.listening_since {
+            self.unsynced_listening += since.elapsed().as_secs_f64();
+            *since = Instant::now();
+        }
+
+        let current_time = self.current_position.as_secs_f64();
+        let duration = self.get_total_duration();
+
+        Some(ProgressUpdate {
+            item_id,
+            current_time,
+            duration,
+            time_listened: std::mem::take(&mut self.unsynced_listening),
+            is_finished: self.at_end_of_book(),
+        })
+    }
+
+    fn at_end_of_book(&self) -> bool {
+        let duration = self.get_total_duration();
+        duration > 0.0 && self.current_position.as_secs_f64() >= duration - 1.0
+    }
+
+    fn sync_session(&mut self) {
+        self.last_session_sync = Instant::now();
+        if let Some(update) = self.take_progress_update() {
+            let _ = self.api_tx.send(ApiCommand::SyncSession(update));
+        }
+    }
+
+    fn close_session(&mut self) {
+        if let Some(update) = self.take_progress_update() {
+            let _ = self.api_tx.send(ApiCommand::CloseSession(update));
+        }
+    }
+
+    /// Called on every pass of the main loop.
+    pub fn on_tick(&mut self) {
+        let elapsed = self.last_tick.elapsed();
+        self.last_tick = Instant::now();
+
+        if self.player_state != PlayerState::Playing {
+            return;
+        }
+
+        if self.last_session_sync.elapsed() >= SESSION_SYNC_INTERVAL {
+            self.sync_session();
+        }
+
+        if let Some(SleepTimer::Remaining(remaining)) = &mut self.sleep_timer {
+            *remaining = remaining.saturating_sub(elapsed);
+        }
+
+        // The player pauses itself once the fade is done
+        if !self.sleep_fading
+            && let Some(remaining) = self.sleep_remaining()
+            && remaining <= sleep_timer::FADE_OUT
+        {
+            self.sleep_fading = true;
+            let fade = remaining.max(Duration::from_secs(1));
+            let _ = self.player_tx.send(PlayerCommand::FadeOut(fade));
+        }
+    }
+
+    /// Playing time until the sleep timer pauses playback.
+    pub fn sleep_remaining(&self) -> Option<Duration> {
+        match self.sleep_timer? {
+            SleepTimer::Remaining(remaining) => Some(remaining),
+            SleepTimer::EndOfChapter => {
+                let chapter = self.current_chapter.as_ref()?;
+                let left = (chapter.end - self.current_position.as_secs_f64()).max(0.0);
+                Some(Duration::from_secs_f64(
+                    left / self.playback_speed.max(MIN_SPEED) as f64,
+                ))
+            }
+        }
+    }
+
+    fn open_sleep_timer_menu(&mut self) {
+        let mut items = vec!["Off".to_string()];
+        items.extend(
+            sleep_timer::PRESET_MINUTES
+                .iter()
+                .map(|minutes| format!("{} minutes", minutes)),
+        );
+        items.push("End of chapter".to_string());
+        items.push("Custom...".to_string());
+
+        self.menu = Some(Menu::new(MenuKind::SleepTimer, " Sleep Timer ", items));
+    }
+
+    fn set_sleep_timer(&mut self, timer: SleepTimer) {
+        if timer == SleepTimer::EndOfChapter && self.current_chapter.is_none() {
+            self.notifications.warning("Nothing is playing");
+            return;
+        }
+
+        self.cancel_sleep_fade();
+        self.sleep_timer = Some(timer);
+        match timer {
+            SleepTimer::Remaining(remaining) => self
+                .notifications
+                .info(format!("Sleep timer: {} minutes", remaining.as_secs() / 60)),
+            SleepTimer::EndOfChapter => self.notifications.info("Sleep timer: end of chapter"),
+        }
+    }
+
+    fn cancel_sleep_timer(&mut self) {
+        self.cancel_sleep_fade();
+        if self.sleep_timer.take().is_some() {
+            self.notifications.info("Sleep timer off");
+        }
+    }
+
+    fn cancel_sleep_fade(&mut self) {
+        if self.sleep_fading {
+            self.sleep_fading = false;
+            let _ = self.player_tx.send(PlayerCommand::CancelFade);
+        }
+    }
+
+    /// Input near the end of the timer means the listener is still awake.
+    /// The input itself is still handled as usual.
+    fn extend_sleep_timer(&mut self) {
+        if self.player_state != PlayerState::Playing {
+            return;
+        }
+        let Some(remaining) = self.sleep_remaining() else {
+            return;
+        };
+        if remaining > sleep_timer::EXTEND_WINDOW {
+            return;
         }
+
+        self.cancel_sleep_fade();
+        self.sleep_timer = Some(SleepTimer::Remaining(remaining + sleep_timer::EXTENSION));
+        self.notifications.info(format!(
+            "Sleep timer extended by {} minutes",
+            sleep_timer::EXTENSION.as_secs() / 60
+        ));
     }
 
     fn get_total_duration(&self) -> f64 {
@@ -173,7 +644,16 @@ impl App {
             .unwrap_or(self.total_duration.as_secs_f64())
     }
 
-    pub fn on_libraries_loaded(&mut self, libraries: Vec<Library>) {
+    pub fn on_libraries_loaded(&mut self, mut libraries: Vec<Library>) {
+        if !libraries.is_empty() {
+            libraries.extend(browse::pseudo_libraries());
+        }
+        self.show_libraries(libraries);
+    }
+
+    /// Lists `libraries` and loads the first.
+    fn show_libraries(&mut self, libraries: Vec<Library>) {
+        self.offline = false;
         self.loading_libraries = false;
         self.libraries = libraries;
         self.selected_library_index = 0;
@@ -189,204 +669,1421 @@ impl App {
         }
     }
 
-    pub fn on_items_loaded(&mut self, items: Vec<LibraryItem>) {
-        self.loading_items = false;
-        self.library_items = items;
-        self.selected_library_item_index = 0;
-        self.chapters.clear();
+    fn cycle_browse_mode(&mut self) {
+        if self.browse.mode.is_pseudo_library() {
+            return;
+        }
+        self.browse.mode = self.browse.mode.next();
+        self.focus = Focus::Libraries;
+        self.load_browse_groups();
     }
 
-    pub fn on_chapters_loaded(&mut self, chapters: Vec<Chapter>) {
-        self.loading_chapters = false;
-        self.chapters = chapters;
-        self.selected_chapter_index = 0;
+    /// Fetches the groups the library panel lists in the current mode.
+    fn load_browse_groups(&mut self) {
+        if self.browse.mode == BrowseMode::Books {
+            return;
+        }
+        let Some(library) = self.libraries.get(self.selected_library_index) else {
+            return;
+        };
+        if library.id == OFFLINE_LIBRARY_ID {
+            self.browse.set_groups(Vec::new());
+            return;
+        }
 
-        self.update_current_chapter();
+        let library_id = library.id.clone();
+        let command = match self.browse.mode {
+            BrowseMode::Authors => ApiCommand::FetchAuthors(library_id),
+            BrowseMode::Narrators => ApiCommand::FetchNarrators(library_id),
+            BrowseMode::Collections => ApiCommand::FetchCollections,
+            BrowseMode::Playlists => ApiCommand::FetchPlaylists,
+            _ => ApiCommand::FetchSeries(library_id),
+        };
+        self.browse.loading = true;
+        let _ = self.api_tx.send(command);
+        let _ = self.api_tx.send(ApiCommand::FetchProgress);
+    }
 
-        if let Some(position) = self.pending_resume_position.take() {
-            let chapter_start = self
-                .chapters
-                .iter()
-                .find(|ch| position >= ch.start && position < ch.end)
-                .map(|ch| ch.start)
-                .unwrap_or(0.0);
+    /// Shows the personalized shelves of the selected library, or the first
+    /// real one while a pseudo-library is selected.
+    fn open_home(&mut self) {
+        if self.offline {
+            self.notifications.warning("The home view needs the server");
+            return;
+        }
+        let library = self
+            .libraries
+            .get(self.selected_library_index)
+            .filter(|library| browse::pseudo_library_mode(&library.id).is_none())
+            .or_else(|| {
+                self.libraries
+                    .iter()
+                    .find(|library| browse::pseudo_library_mode(&library.id).is_none())
+            });
+        let Some(library) = library else {
+            return;
+        };
+
+        let library_id = library.id.clone();
+        let _ = self
+            .api_tx
+            .send(ApiCommand::FetchShelves(library_id.clone()));
+        // Entries start where they were left off
+        let _ = self.api_tx.send(ApiCommand::FetchProgress);
+        self.home = Some(Home::new(library_id));
+    }
 
-            let resume_position = (position - 10.0).max(chapter_start);
+    pub fn on_shelves_loaded(&mut self, library_id: String, shelves: Vec<PersonalizedShelf>) {
+        if let Some(home) = &mut self.home
+            && home.library_id == library_id
+        {
+            home.set_shelves(shelves);
+        }
+    }
 
-            if let Some(ref item) = self.current_library_item {
-                let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
-                    item.id.clone(),
-                    resume_position,
-                ));
+    /// Handles keys while the home view is shown. Returns false for keys it
+    /// leaves to the usual handling, like playback controls; keys for the
+    /// panels it covers do nothing there.
+    fn handle_home_input(&mut self, key: KeyEvent) -> bool {
+        let Some(home) = &mut self.home else {
+            return false;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => home.next_shelf(),
+            KeyCode::Char('k') | KeyCode::Up => home.previous_shelf(),
+            KeyCode::Char('l') | KeyCode::Right => home.next_entry(),
+            KeyCode::Char('h') | KeyCode::Left => home.previous_entry(),
+            KeyCode::Enter => self.play_home_entry(),
+            KeyCode::Char('g') | KeyCode::Esc => self.home = None,
+            _ => return false,
+        }
+        true
+    }
 
-                self.is_downloading = true;
+    fn handle_home_mouse(&mut self, event: MouseEvent) {
+        let clicked = self
+            .layout_regions
+            .home_cards
+            .iter()
+            .find(|(rect, _, _)| self.point_in_rect(event.column, event.row, rect))
+            .map(|&(_, shelf, entry)| (shelf, entry));
+        let Some(home) = &mut self.home else {
+            return;
+        };
+
+        match event.kind {
+            MouseEventKind::Down(button @ (MouseButton::Left | MouseButton::Right)) => {
+                if let Some((shelf, entry)) = clicked {
+                    home.select(shelf, entry);
+                    if button == MouseButton::Right {
+                        self.play_home_entry();
+                    }
+                }
             }
+            MouseEventKind::ScrollUp => home.previous_shelf(),
+            MouseEventKind::ScrollDown => home.next_shelf(),
+            _ => {}
         }
     }
 
-    pub fn on_download_finished(
-        &mut self,
-        path: PathBuf,
-        local_position: f64,
-        track_info: TrackInfo,
-    ) {
-        self.is_downloading = false;
-        self.current_track_info = Some(track_info);
+    /// Starts the selected shelf entry at the server's progress and leaves
+    /// the home view.
+    fn play_home_entry(&mut self) {
+        let Some(item) = self
+            .home
+            .as_ref()
+            .and_then(|home| home.selected_item())
+            .cloned()
+        else {
+            return;
+        };
+        self.home = None;
+        self.play_item(item);
+    }
 
-        let position = Duration::from_secs_f64(local_position);
-        let _ = self.player_tx.send(PlayerCommand::Play { path, position });
+    /// Whether groups fetched for `mode` of the given library are still
+    /// wanted.
+    fn browsing(&self, mode: BrowseMode, library_id: &str) -> bool {
+        self.browse.mode == mode
+            && self
+                .libraries
+                .get(self.selected_library_index)
+                .is_some_and(|library| library.id == library_id)
     }
 
-    pub fn on_continue_listening_loaded(&mut self, item: LibraryItem, position: f64) {
-        if !self.auto_resume_pending {
+    /// Shows the given groups, fetching the books of one opened right away.
+    fn set_browse_groups(&mut self, groups: Vec<BrowseGroup>) {
+        self.browse.set_groups(groups);
+        if self.browse.open_group.is_some() {
+            self.load_open_group();
+        }
+    }
+
+    pub fn on_series_loaded(&mut self, library_id: String, series: Vec<Series>) {
+        if !self.browsing(BrowseMode::Series, &library_id) {
             return;
         }
 
-        if let Some(index) = self.library_items.iter().position(|i| i.id == item.id) {
-            self.selected_library_item_index = index;
+        let groups = series
+            .into_iter()
+            .map(|series| {
+                let mut books = series.books;
+                browse::sort_by_sequence(&mut books, &series.id);
+                BrowseGroup {
+                    id: series.id,
+                    name: series.name,
+                    count: books.len(),
+                    books,
+                }
+            })
+            .collect();
+        self.set_browse_groups(groups);
+    }
+
+    pub fn on_authors_loaded(&mut self, library_id: String, authors: Vec<LibraryAuthor>) {
+        if !self.browsing(BrowseMode::Authors, &library_id) {
+            return;
         }
 
-        self.current_library_item = Some(item.clone());
-        self.current_item_id = Some(item.id.clone());
-        self.pending_resume_position = Some(position);
-        self.current_position = Duration::from_secs_f64(position);
+        let groups = authors
+            .into_iter()
+            .map(|author| BrowseGroup {
+                id: author.id,
+                name: author.name,
+                count: author.num_books,
+                books: Vec::new(),
+            })
+            .collect();
+        self.set_browse_groups(groups);
+    }
 
-        if let Some(ref media) = item.media {
-            if let Some(duration) = media.duration {
-                self.total_duration = Duration::from_secs_f64(duration);
-            }
-            if let Some(ref tracks) = media.tracks {
-                self.current_tracks = tracks.clone();
-            }
+    pub fn on_narrators_loaded(&mut self, library_id: String, narrators: Vec<Narrator>) {
+        if !self.browsing(BrowseMode::Narrators, &library_id) {
+            return;
         }
 
-        self.load_chapters(&item.id);
+        let groups = narrators
+            .into_iter()
+            .map(|narrator| BrowseGroup {
+                id: narrator.name.clone(),
+                name: narrator.name,
+                count: narrator.num_books,
+                books: Vec::new(),
+            })
+            .collect();
+        self.set_browse_groups(groups);
+    }
 
-        self.focus = Focus::Chapters;
+    pub fn on_collections_loaded(&mut self, collections: Vec<Collection>) {
+        if self.browse.mode != BrowseMode::Collections {
+            return;
+        }
+
+        let groups = collections
+            .into_iter()
+            .map(|collection| BrowseGroup {
+                id: collection.id,
+                name: collection.name,
+                count: collection.books.len(),
+                books: collection.books,
+            })
+            .collect();
+        self.set_browse_groups(groups);
     }
 
-    pub fn on_api_error(&mut self, error: String) {
-        self.loading_libraries = false;
-        self.loading_items = false;
-        self.loading_chapters = false;
-        self.error_message = Some(error.clone());
-        self.notifications.error(format!("API Error: {}", error));
+    pub fn on_playlists_loaded(&mut self, playlists: Vec<Playlist>) {
+        self.playlists = playlists;
+
+        if std::mem::take(&mut self.pending_playlist_menu) {
+            self.show_playlist_menu();
+        }
+
+        if self.browse.mode == BrowseMode::Playlists {
+            let groups = self
+                .playlists
+                .iter()
+                .map(|playlist| {
+                    let books: Vec<LibraryItem> = playlist
+                        .items
+                        .iter()
+                        .filter(|item| item.episode_id.is_none())
+                        .filter_map(|item| item.library_item.clone())
+                        .collect();
+                    BrowseGroup {
+                        id: playlist.id.clone(),
+                        name: playlist.name.clone(),
+                        count: books.len(),
+                        books,
+                    }
+                })
+                .collect();
+            // Keep the open playlist open after it changed
+            self.browse.reveal = self.browse.open().map(|group| group.id.clone());
+            self.set_browse_groups(groups);
+        }
     }
 
-    pub fn on_player_state_changed(&mut self, state: PlayerState) {
-        let previous_state = self.player_state;
-        self.player_state = state;
+    /// The book selected in the library panel, however it's listed.
+    fn selected_item(&self) -> Option<&LibraryItem> {
+        match self.browse.mode {
+            BrowseMode::Books => self.library_items.get(self.selected_library_item_index),
+            _ => self.browse.selected_book(),
+        }
+    }
 
-        if self.auto_resume_pending {
-            self.auto_resume_pending = false;
-            let _ = self.player_tx.send(PlayerCommand::Pause);
+    /// Offers to add the selected book to a playlist, or remove it from
+    /// one. Playlists are fetched first so the menu is up to date.
+    fn open_playlist_menu(&mut self) {
+        if self.offline {
+            self.notifications.warning("Playlists need the server");
             return;
         }
+        let Some(item) = self.selected_item().cloned() else {
+            return;
+        };
+        self.playlist_target = Some(item);
+        self.pending_playlist_menu = true;
+        let _ = self.api_tx.send(ApiCommand::FetchPlaylists);
+    }
 
-        // Sync progress when playback state changes
-        match (previous_state, state) {
-            (PlayerState::Playing, PlayerState::Paused) => {
-                self.sync_progress();
+    fn show_playlist_menu(&mut self) {
+        let Some(item) = &self.playlist_target else {
+            return;
+        };
+
+        let playlists: Vec<&Playlist> = self
+            .playlists
+            .iter()
+            .filter(|playlist| playlist.library_id == item.library_id)
+            .collect();
+        let mut items: Vec<String> = playlists
+            .iter()
+            .map(|playlist| match playlist.contains(&item.id) {
+                true => format!("Remove from {}", playlist.name),
+                false => format!("Add to {}", playlist.name),
+            })
+            .collect();
+        items.push("New playlist...".to_string());
+
+        self.playlist_choices = playlists.iter().map(|p| p.id.clone()).collect();
+        self.menu = Some(Menu::new(MenuKind::Playlist, " Playlist ", items));
+    }
+
+    /// Plays the selected playlist from its first unfinished book, or an
+    /// open one from the selected book, queueing the books after it.
+    fn play_playlist(&mut self) {
+        let group = match self.browse.open() {
+            Some(group) => group,
+            None => match self.browse.groups.get(self.browse.selected_group) {
+                Some(group) => group,
+                None => return,
+            },
+        };
+        let start = match self.browse.open_group {
+            Some(_) => self.browse.selected_book,
+            None => group
+                .books
+                .iter()
+                .position(|book| {
+                    !self
+                        .item_progress
+                        .get(&book.id)
+                        .is_some_and(|progress| progress.is_finished)
+                })
+                .unwrap_or(0),
+        };
+
+        let Some(first) = group.books.get(start).cloned() else {
+            return;
+        };
+        // Ahead of anything queued before
+        let rest: Vec<QueueEntry> = group.books[start + 1..]
+            .iter()
+            .map(|book| QueueEntry {
+                item: book.clone(),
+                range: None,
+            })
+            .collect();
+        self.notifications.info(format!("Playing {}", group.name));
+        self.prefs.queue.splice(0..0, rest);
+        self.prefs.save();
+        self.play_item(first);
+    }
+
+    /// Fetches the books of the open group unless they came with it.
+    fn load_open_group(&mut self) {
+        let Some(group) = self.browse.open() else {
+            return;
+        };
+        let Some(filter_group) = self.browse.mode.filter_group() else {
+            return;
+        };
+        let Some(library) = self.libraries.get(self.selected_library_index) else {
+            return;
+        };
+        if !group.books.is_empty() || group.count == 0 {
+            return;
+        }
+
+        let command = ApiCommand::FetchGroupBooks(
+            library.id.clone(),
+            filter_group.to_string(),
+            group.id.clone(),
+        );
+        self.browse.loading = true;
+        let _ = self.api_tx.send(command);
+    }
+
+    pub fn on_group_books_loaded(
+        &mut self,
+        group: String,
+        value: String,
+        mut books: Vec<LibraryItem>,
+    ) {
+        if group == "series"
+            && self
+                .finished_in_series
+                .as_ref()
+                .is_some_and(|(_, series_id)| *series_id == value)
+        {
+            self.offer_next_in_series(value, books);
+            return;
+        }
+
+        if self.browse.mode.filter_group() == Some(group.as_str()) {
+            browse::sort_by_title(&mut books);
+            self.browse.set_books(&value, books);
+        }
+    }
+
+    /// Offers to show the books of the current item's authors and
+    /// narrators.
+    fn open_people_menu(&mut self, only: Option<BrowseMode>) {
+        let Some(metadata) = self
+            .current_library_item
+            .as_ref()
+            .and_then(|item| item.media.as_ref())
+            .map(|media| &media.metadata)
+        else {
+            return;
+        };
+
+        let authors = metadata
+            .authors
+            .iter()
+            .flatten()
+            .map(|author| (BrowseMode::Authors, author.id.clone(), author.name.clone()));
+        let narrators = metadata
+            .narrators
+            .iter()
+            .flatten()
+            .map(|name| (BrowseMode::Narrators, name.clone(), name.clone()));
+        let people: Vec<_> = authors
+            .chain(narrators)
+            .filter(|(mode, _, _)| only.is_none_or(|only| only == *mode))
+            .collect();
+
+        match people.as_slice() {
+            [] => {}
+            [(mode, id, _)] => self.show_person(*mode, id.clone()),
+            _ => {
+                let items = people
+                    .iter()
+                    .map(|(mode, _, name)| match mode {
+                        BrowseMode::Narrators => format!("Narrator: {}", name),
+                        _ => format!("Author: {}", name),
+                    })
+                    .collect();
+                self.people_choices = people.into_iter().map(|(mode, id, _)| (mode, id)).collect();
+                self.menu = Some(Menu::new(MenuKind::Person, " Show books by ", items));
+            }
+        }
+    }
+
+    /// Lists the books of an author or narrator in the library panel.
+    fn show_person(&mut self, mode: BrowseMode, id: String) {
+        let Some(library_id) = self
+            .current_library_item
+            .as_ref()
+            .map(|item| item.library_id.clone())
+        else {
+            return;
+        };
+        let Some(index) = self.libraries.iter().position(|l| l.id == library_id) else {
+            return;
+        };
+        self.end_search();
+        if index != self.selected_library_index {
+            self.selected_library_index = index;
+            self.load_library_items(&library_id);
+        }
+
+        self.browse.mode = mode;
+        self.browse.reveal = Some(id);
+        self.focus = Focus::Libraries;
+        self.load_browse_groups();
+    }
+
+    pub fn on_progress_loaded(&mut self, progress: Vec<MediaProgress>) {
+        self.item_progress = progress
+            .into_iter()
+            .filter(|p| p.episode_id.is_none())
+            .map(|p| (p.library_item_id.clone(), p))
+            .collect();
+    }
+
+    /// Handles keys for the library panel while it lists groups. Returns
+    /// false for keys it leaves to the usual handling.
+    fn handle_browse_input(&mut self, key: KeyEvent) -> bool {
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => self.browse.next(),
+            KeyCode::Char('k') | KeyCode::Up => self.browse.previous(),
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Enter => self.activate_browse_row(),
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::Esc => {
+                if let Some(group) = self.browse.open_group.take() {
+                    self.browse.selected_group = group;
+                }
+            }
+            KeyCode::Char('P') if self.browse.mode == BrowseMode::Playlists => self.play_playlist(),
+            // These act on the book list
+            KeyCode::Char('d' | 'D' | 'S' | 'F' | 'n' | 'N' | 'P') => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// Opens the selected group, or shows the selected book of an open one.
+    fn activate_browse_row(&mut self) {
+        if self.browse.open_group.is_none() {
+            if self.browse.selected_group < self.browse.groups.len() {
+                self.browse.open_group = Some(self.browse.selected_group);
+                self.browse.selected_book = 0;
+                self.load_open_group();
+            }
+            return;
+        }
+
+        let Some(book) = self.browse.selected_book().cloned() else {
+            return;
+        };
+        self.load_chapters(&book.id);
+        self.current_library_item = Some(book);
+        self.focus = Focus::Chapters;
+    }
+
+    /// Moves on to the next queue entry, or else looks up what follows the
+    /// book that just finished in its series.
+    fn on_book_finished(&mut self) {
+        if !self.prefs.queue.is_empty() {
+            let next = self.prefs.queue.remove(0);
+            self.prefs.save();
+            self.notifications
+                .info(format!("Up next: {}", next.label()));
+            self.play_entry(next);
+            return;
+        }
+
+        let Some(item) = &self.current_library_item else {
+            return;
+        };
+        let Some(series) = item
+            .media
+            .as_ref()
+            .and_then(|m| m.metadata.series.as_ref())
+            .and_then(|series| series.first())
+        else {
+            return;
+        };
+
+        self.finished_in_series = Some((item.id.clone(), series.id.clone()));
+        let _ = self.api_tx.send(ApiCommand::FetchGroupBooks(
+            item.library_id.clone(),
+            "series".to_string(),
+            series.id.clone(),
+        ));
+        // Queued after the session close, so the finished book shows as such
+        let _ = self.api_tx.send(ApiCommand::FetchProgress);
+    }
+
+    fn offer_next_in_series(&mut self, series_id: String, mut books: Vec<LibraryItem>) {
+        let Some((finished_id, _)) = self
+            .finished_in_series
+            .take_if(|(_, pending)| *pending == series_id)
+        else {
+            return;
+        };
+
+        browse::sort_by_sequence(&mut books, &series_id);
+        let Some(position) = books.iter().position(|book| book.id == finished_id) else {
+            return;
+        };
+        let next = books.into_iter().skip(position + 1).find(|book| {
+            !self
+                .item_progress
+                .get(&book.id)
+                .is_some_and(|progress| progress.is_finished)
+        });
+        let Some(next) = next else {
+            self.notifications
+                .info("That was the last book in the series");
+            return;
+        };
+
+        let title = next
+            .media
+            .as_ref()
+            .and_then(|m| m.metadata.title.clone())
+            .unwrap_or_else(|| next.id.clone());
+        if self.series_auto_advance {
+            self.notifications.info(format!("Up next: {}", title));
+            self.play_item(next);
+        } else {
+            self.next_in_series = Some(next);
+            self.menu = Some(Menu::new(
+                MenuKind::NextInSeries,
+                format!(" Up next: {} ", title),
+                vec!["Play now".to_string(), "Not now".to_string()],
+            ));
+        }
+    }
+
+    /// Starts `item` where it was left off, or from the beginning.
+    fn play_item(&mut self, item: LibraryItem) {
+        let position = self
+            .item_progress
+            .get(&item.id)
+            .filter(|progress| !progress.is_finished)
+            .map_or(0.0, |progress| progress.current_time);
+        self.play_item_at(item, position);
+    }
+
+    /// Plays a queue entry, stopping at the end of its chapters if it has a
+    /// range.
+    fn play_entry(&mut self, entry: QueueEntry) {
+        match entry.range {
+            Some(range) => {
+                self.play_item_at(entry.item, range.start);
+                self.play_until = Some(range.end);
+            }
+            None => self.play_item(entry.item),
+        }
+    }
+
+    fn play_item_at(&mut self, item: LibraryItem, position: f64) {
+        self.clear_play_until();
+
+        if self.player_state != PlayerState::Stopped {
+            self.close_session();
+        }
+
+        self.current_chapter = None;
+        self.current_item_id = Some(item.id.clone());
+        self.current_position = Duration::from_secs_f64(position);
+        if let Some(duration) = item.media.as_ref().and_then(|m| m.duration) {
+            self.total_duration = Duration::from_secs_f64(duration);
+        }
+        self.load_chapters(&item.id);
+        let _ = self
+            .api_tx
+            .send(ApiCommand::DownloadForPlayback(item.id.clone(), position));
+        self.current_library_item = Some(item);
+        self.is_downloading = true;
+    }
+
+    pub fn on_offline_mode_entered(&mut self, items: Vec<LibraryItem>) {
+        self.show_libraries(vec![offline_library()]);
+        self.offline = true;
+        self.offline_items = items.iter().map(|item| item.id.clone()).collect();
+        self.notifications
+            .warning("Server unreachable, showing downloaded books");
+    }
+
+    pub fn on_offline_items_changed(&mut self, item_ids: Vec<String>) {
+        self.offline_items = item_ids.into_iter().collect();
+    }
+
+    pub fn on_offline_download_finished(&mut self, item_id: String) {
+        if self
+            .download_progress
+            .as_ref()
+            .is_some_and(|p| p.item_id == item_id && p.track_index.is_none())
+        {
+            self.download_progress = None;
+        }
+        self.notifications.info(format!(
+            "Downloaded for offline: {}",
+            self.item_title(&item_id)
+        ));
+    }
+
+    pub fn on_offline_download_failed(&mut self, item_id: String, error: String) {
+        if self
+            .download_progress
+            .as_ref()
+            .is_some_and(|p| p.item_id == item_id && p.track_index.is_none())
+        {
+            self.download_progress = None;
+        }
+        self.notifications.error(format!(
+            "Offline download failed for {}: {}",
+            self.item_title(&item_id),
+            error
+        ));
+    }
+
+    pub fn on_server_reachable(&mut self) {
+        if !self.offline {
+            return;
+        }
+
+        self.notifications.info("Server reachable again");
+        if self.player_state == PlayerState::Stopped {
+            self.load_libraries();
+        }
+    }
+
+    fn item_title(&self, item_id: &str) -> String {
+        self.library_items
+            .iter()
+            .find(|item| item.id == item_id)
+            .and_then(|item| item.media.as_ref())
+            .and_then(|media| media.metadata.title.clone())
+            .unwrap_or_else(|| item_id.to_string())
+    }
+
+    pub fn download_selected_for_offline(&mut self) {
+        let Some(item) = self.library_items.get(self.selected_library_item_index) else {
+            return;
+        };
+
+        if self.offline_items.contains(&item.id) {
+            self.notifications.info("Already downloaded");
+            return;
+        }
+
+        let _ = self
+            .api_tx
+            .send(ApiCommand::DownloadForOffline(item.id.clone()));
+        self.notifications.info(format!(
+            "Downloading for offline: {}",
+            self.item_title(&item.id)
+        ));
+    }
+
+    pub fn remove_selected_offline(&mut self) {
+        let Some(item) = self.library_items.get(self.selected_library_item_index) else {
+            return;
+        };
+
+        if self.offline || !self.offline_items.contains(&item.id) {
+            return;
+        }
+
+        let _ = self.api_tx.send(ApiCommand::RemoveOffline(item.id.clone()));
+        self.notifications.info(format!(
+            "Removed offline copy: {}",
+            self.item_title(&item.id)
+        ));
+    }
+
+    pub fn on_items_loaded(&mut self, page: ItemsPage) {
+        // Drop pages of a library or sort that is no longer shown
+        let is_shown = self
+            .libraries
+            .get(self.selected_library_index)
+            .is_some_and(|library| library.id == page.library_id)
+            && self.library_query(&page.library_id) == page.query;
+        if !is_shown {
+            return;
+        }
+
+        if page.page == 0 {
+            self.loading_items = false;
+            self.library_items = page.items;
+            self.selected_library_item_index = 0;
+            self.chapters.clear();
+            self.search = None;
+        } else {
+            if page.page != self.items_next_page {
+                return;
+            }
+            self.loading_more_items = false;
+
+            match &mut self.search {
+                Some(search) if search.stashed_items.is_some() => {
+                    search
+                        .stashed_items
+                        .get_or_insert_default()
+                        .extend(page.items);
+                }
+                Some(search) => {
+                    self.library_items.extend(page.items);
+                    search.refresh(&self.library_items, self.selected_library_item_index);
+                }
+                None => self.library_items.extend(page.items),
+            }
+        }
+
+        self.items_total = page.total;
+        self.items_next_page = page.page + 1;
+        self.prefetch_items();
+    }
+
+    fn start_search(&mut self) {
+        if self.browse.mode.is_pseudo_library() {
+            self.notifications
+                .warning("Switch to a library to search it");
+            return;
+        }
+        self.end_search();
+        self.browse.mode = BrowseMode::Books;
+        self.focus = Focus::Libraries;
+        self.search = Some(LibrarySearch::new());
+    }
+
+    /// Clears the search, bringing back the full list if server results
+    /// were shown in its place.
+    fn end_search(&mut self) {
+        let Some(search) = self.search.take() else {
+            return;
+        };
+        let Some(items) = search.stashed_items else {
+            return;
+        };
+
+        let selected_id = self
+            .library_items
+            .get(self.selected_library_item_index)
+            .map(|item| item.id.clone());
+        self.library_items = items;
+        self.selected_library_item_index = selected_id
+            .and_then(|id| self.library_items.iter().position(|item| item.id == id))
+            .unwrap_or(0);
+    }
+
+    fn update_search(&mut self) {
+        if let Some(search) = &mut self.search {
+            search.update(&self.library_items);
+            if let Some(index) = search.current_index() {
+                self.selected_library_item_index = index;
+            }
+        }
+        self.prefetch_items();
+    }
+
+    fn submit_search(&mut self) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        search.editing = false;
+
+        if search.query.trim().is_empty() {
+            self.end_search();
+            return;
+        }
+
+        let large = self.items_total >= search::SERVER_SEARCH_MIN_ITEMS;
+        if large
+            && !self.offline
+            && let Some(library) = self.libraries.get(self.selected_library_index)
+        {
+            let _ = self.api_tx.send(ApiCommand::SearchLibrary(
+                library.id.clone(),
+                search.query.trim().to_string(),
+            ));
+        } else if search.matches.is_empty() {
+            self.notifications
+                .warning(format!("No matches for \"{}\"", search.query));
+        }
+    }
+
+    pub fn on_search_results(&mut self, query: String, items: Vec<LibraryItem>) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        if search.query.trim() != query {
+            return;
+        }
+
+        if items.is_empty() {
+            self.notifications
+                .warning(format!("No matches for \"{}\"", query));
+            return;
+        }
+
+        search
+            .stashed_items
+            .get_or_insert_with(|| std::mem::take(&mut self.library_items));
+        self.library_items = items;
+        self.selected_library_item_index = 0;
+        self.update_search();
+    }
+
+    fn handle_search_input(&mut self, key: KeyEvent) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char(c) => {
+                search.query.push(c);
+                self.update_search();
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                self.update_search();
+            }
+            KeyCode::Esc => self.end_search(),
+            KeyCode::Enter => self.submit_search(),
+            _ => {}
+        }
+    }
+
+    /// Moves to the next or previous search match, counting from the
+    /// selected item.
+    fn jump_to_match(&mut self, backwards: bool) {
+        let Some(search) = &mut self.search else {
+            return;
+        };
+        if search.matches.is_empty() {
+            return;
+        }
+
+        let selected = self.selected_library_item_index;
+        search.current = if backwards {
+            search
+                .matches
+                .iter()
+                .rposition(|m| m.index < selected)
+                .unwrap_or(search.matches.len() - 1)
+        } else {
+            search
+                .matches
+                .iter()
+                .position(|m| m.index > selected)
+                .unwrap_or(0)
+        };
+
+        if let Some(index) = search.current_index() {
+            self.selected_library_item_index = index;
+        }
+        self.prefetch_items();
+    }
+
+    pub fn on_chapters_loaded(&mut self, chapters: Vec<Chapter>) {
+        self.loading_chapters = false;
+        self.chapters = chapters;
+        self.selected_chapter_index = 0;
+
+        self.update_current_chapter();
+
+        if let Some(position) = self.pending_resume_position.take()
+            && let Some(item_id) = self.current_library_item.as_ref().map(|i| i.id.clone())
+        {
+            let rewind = self.smart_rewind_secs(&item_id);
+            let resume_position = (position - rewind).max(self.chapter_start_at(position));
+            // Startup leaves the book paused here; count the pause from now so
+            // resuming doesn't rewind a second time
+            self.prefs.item_paused_at.insert(item_id.clone(), now_ms());
+
+            let _ = self
+                .api_tx
+                .send(ApiCommand::DownloadForPlayback(item_id, resume_position));
+            self.is_downloading = true;
+        }
+    }
+
+    pub fn on_bookmarks_loaded(&mut self, item_id: String, bookmarks: Vec<Bookmark>) {
+        // Only the bookmarks of the book being shown are kept
+        if self.current_library_item.as_ref().map(|i| &i.id) != Some(&item_id) {
+            return;
+        }
+
+        self.bookmarks = bookmarks;
+        self.selected_bookmark_index = self
+            .selected_bookmark_index
+            .min(self.bookmarks.len().saturating_sub(1));
+    }
+
+    pub fn on_playback_ready(
+        &mut self,
+        source: AudioSource,
+        local_position: f64,
+        track_info: TrackInfo,
+    ) {
+        self.is_downloading = false;
+        self.current_track_info = Some(track_info.clone());
+        self.prepare_next_track(&track_info);
+        self.restore_item_volume();
+
+        if self.time_saved_item != self.current_item_id {
+            self.time_saved_item = self.current_item_id.clone();
+            self.time_saved = Duration::ZERO;
+        }
+        self.time_saved_before = self.time_saved;
+
+        let position = Duration::from_secs_f64(local_position);
+        let _ = self.player_tx.send(PlayerCommand::Play {
+            source,
+            track: track_info,
+            position,
+        });
+        if let Some(end) = self.play_until {
+            let _ = self
+                .player_tx
+                .send(PlayerCommand::StopAt(Some(Duration::from_secs_f64(end))));
+        }
+    }
+
+    pub fn on_track_changed(&mut self, track_info: TrackInfo) {
+        self.prepare_next_track(&track_info);
+        self.current_track_info = Some(track_info);
+    }
+
+    pub fn on_next_track_ready(&mut self, item_id: String, source: AudioSource, track: TrackInfo) {
+        let follows_current = self
+            .current_track_info
+            .as_ref()
+            .is_some_and(|current| current.index + 1 == track.index);
+
+        if follows_current && self.current_item_id.as_ref() == Some(&item_id) {
+            let _ = self
+                .player_tx
+                .send(PlayerCommand::Enqueue { source, track });
+        }
+    }
+
+    /// Asks for the track after `track_info` so the player can queue it.
+    fn prepare_next_track(&self, track_info: &TrackInfo) {
+        if let Some(ref item_id) = self.current_item_id
+            && track_info.duration > 0.0
+        {
+            let _ = self.api_tx.send(ApiCommand::PrepareNextTrack(
+                item_id.clone(),
+                track_info.index,
+            ));
+        }
+    }
+
+    pub fn on_download_progress(&mut self, progress: DownloadProgress) {
+        self.download_progress = Some(progress);
+    }
+
+    pub fn on_download_finished(&mut self, progress: DownloadProgress) {
+        if self.is_same_download(&progress) {
+            self.download_progress = None;
+        }
+    }
+
+    pub fn on_download_failed(&mut self, progress: DownloadProgress, error: String) {
+        if self.is_same_download(&progress) {
+            self.download_progress = None;
+        }
+        self.notifications
+            .warning(format!("Download failed, streaming instead: {}", error));
+    }
+
+    fn is_same_download(&self, progress: &DownloadProgress) -> bool {
+        self.download_progress.as_ref().is_none_or(|current| {
+            current.item_id == progress.item_id && current.track_index == progress.track_index
+        })
+    }
+
+    pub fn on_continue_listening_loaded(&mut self, item: LibraryItem, position: f64) {
+        if !self.auto_resume_pending {
+            return;
+        }
+
+        if let Some(index) = self.library_items.iter().position(|i| i.id == item.id) {
+            self.selected_library_item_index = index;
+        }
+
+        self.current_library_item = Some(item.clone());
+        self.current_item_id = Some(item.id.clone());
+        self.pending_resume_position = Some(position);
+        self.current_position = Duration::from_secs_f64(position);
+
+        if let Some(duration) = item.media.as_ref().and_then(|m| m.duration) {
+            self.total_duration = Duration::from_secs_f64(duration);
+        }
+
+        self.load_chapters(&item.id);
+
+        self.focus = Focus::Chapters;
+    }
+
+    pub fn on_api_error(&mut self, error: String) {
+        self.loading_libraries = false;
+        self.loading_items = false;
+        self.loading_more_items = false;
+        self.loading_chapters = false;
+        if let Some(home) = &mut self.home {
+            home.loading = false;
+        }
+        self.error_message = Some(error.clone());
+        self.notifications.error(format!("API Error: {}", error));
+    }
+
+    pub fn on_player_state_changed(&mut self, state: PlayerState) {
+        let previous_state = self.player_state;
+        self.player_state = state;
+
+        if state == PlayerState::Playing {
+            self.listening_since.get_or_insert_with(Instant::now);
+        } else if let Some(since) = self.listening_since.take() {
+            self.unsynced_listening += since.elapsed().as_secs_f64();
+        }
+
+        if self.sleep_fading && state != PlayerState::Playing {
+            // The fade finished, or playback was paused or stopped during it
+            self.sleep_fading = false;
+            self.sleep_timer = None;
+            self.notifications.info("Sleep timer ended");
+        }
+
+        if self.auto_resume_pending {
+            self.auto_resume_pending = false;
+            let _ = self.player_tx.send(PlayerCommand::Pause);
+            return;
+        }
+
+        match (previous_state, state) {
+            (PlayerState::Playing, PlayerState::Paused) => {
+                self.record_pause();
+                self.sync_session();
+            }
+            // Stopping to switch tracks keeps the session open
+            (PlayerState::Playing | PlayerState::Paused, PlayerState::Stopped) => {
+                let track_ended = std::mem::take(&mut self.track_ended);
+                if self.is_downloading {
+                    self.sync_session();
+                } else {
+                    let finished = track_ended || self.at_end_of_book();
+                    self.close_session();
+                    if finished {
+                        self.on_book_finished();
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn on_position_update(&mut self, position: Duration) {
+        self.current_position = position;
+        self.update_current_chapter();
+    }
+
+    fn update_current_chapter(&mut self) {
+        if self.current_item_id.is_none() {
+            return;
+        }
+
+        let pos_secs = self.current_position.as_secs_f64();
+
+        if let Some(ref current) = self.current_chapter
+            && pos_secs >= current.start
+            && pos_secs < current.end
+        {
+            return; // Still in the same chapter
+        }
+
+        for (i, chapter) in self.chapters.iter().enumerate() {
+            if pos_secs >= chapter.start && pos_secs < chapter.end {
+                self.current_chapter = Some(chapter.clone());
+                self.selected_chapter_index = i;
+                return;
+            }
+        }
+
+        if let Some(last) = self.chapters.last()
+            && pos_secs >= last.end
+        {
+            self.current_chapter = None;
+        }
+    }
+
+    pub fn on_duration_changed(&mut self, duration: Duration) {
+        // Tracks of a multi-file book report their own length, not the book's
+        let book_duration = self
+            .current_library_item
+            .as_ref()
+            .and_then(|item| item.media.as_ref())
+            .and_then(|media| media.duration);
+        self.total_duration = book_duration.map_or(duration, Duration::from_secs_f64);
+    }
+
+    /// The player reports stopping right after the last track ends, which
+    /// closes the session and moves on to what's next. If a track before
+    /// that ends, the following one wasn't queued in time, e.g. because
+    /// preparing it failed, so it's fetched now.
+    pub fn on_track_ended(&mut self) {
+        let Some(track) = self.current_track_info.clone() else {
+            self.track_ended = true;
+            return;
+        };
+
+        let track_end = track.start_offset + track.duration;
+        let is_last = track.duration <= 0.0 || track_end >= self.get_total_duration() - 1.0;
+        if is_last {
+            self.track_ended = true;
+        } else if let Some(ref item_id) = self.current_item_id {
+            self.is_downloading = true;
+            let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
+                item_id.clone(),
+                track_end + 0.1,
+            ));
+        }
+    }
+
+    /// The player paused at the end of a queued chapter range.
+    pub fn on_stop_reached(&mut self) {
+        if self.play_until.is_some() {
+            self.on_range_finished();
+        }
+    }
+
+    // The player follows up with a state change when the error stops playback
+    pub fn on_player_error(&mut self, error: String) {
+        self.error_message = Some(format!("Player error: {}", error));
+        self.notifications.error(format!("Player: {}", error));
+    }
+
+    pub fn on_output_devices(&mut self, devices: Vec<String>, current: Option<String>) {
+        let selected = current
+            .and_then(|name| devices.iter().position(|d| *d == name))
+            .map_or(0, |i| i + 1);
+
+        let mut items = vec!["System default".to_string()];
+        items.extend(devices);
+
+        let mut menu = Menu::new(MenuKind::OutputDevice, " Output Device ", items);
+        menu.selected = selected;
+        self.menu = Some(menu);
+    }
+
+    pub fn on_output_device_changed(&mut self, name: String) {
+        self.notifications.info(format!("Output: {}", name));
+    }
+
+    fn handle_menu_input(&mut self, key: KeyEvent) {
+        let Some(ref mut menu) = self.menu else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => menu.next(),
+            KeyCode::Char('k') | KeyCode::Up => menu.previous(),
+            KeyCode::Esc | KeyCode::Char('q') => self.menu = None,
+            KeyCode::Enter => {
+                if let Some(menu) = self.menu.take() {
+                    self.on_menu_selected(menu);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn on_menu_selected(&mut self, menu: Menu) {
+        match menu.kind {
+            MenuKind::OutputDevice => {
+                // The first entry is the system default
+                let device = menu
+                    .items
+                    .get(menu.selected)
+                    .filter(|_| menu.selected > 0)
+                    .cloned();
+                let _ = self.player_tx.send(PlayerCommand::SetOutputDevice(device));
+            }
+            MenuKind::SleepTimer => {
+                let presets = sleep_timer::PRESET_MINUTES.len();
+                match menu.selected {
+                    0 => self.cancel_sleep_timer(),
+                    i if i <= presets => {
+                        let minutes = sleep_timer::PRESET_MINUTES[i - 1];
+                        self.set_sleep_timer(SleepTimer::Remaining(Duration::from_secs(
+                            minutes * 60,
+                        )));
+                    }
+                    i if i == presets + 1 => self.set_sleep_timer(SleepTimer::EndOfChapter),
+                    _ => {
+                        self.prompt = Some(Prompt::new(
+                            PromptKind::SleepMinutes,
+                            " Sleep in how many minutes? ",
+                        ))
+                    }
+                }
+            }
+            MenuKind::LibrarySort => {
+                if let Some(&sort) = LibrarySort::ALL.get(menu.selected) {
+                    // Picking the current sort again flips its direction
+                    self.update_library_query(|query| {
+                        if query.sort == sort {
+                            query.descending = !query.descending;
+                        } else {
+                            query.sort = sort;
+                            query.descending = sort.descending_by_default();
+                        }
+                    });
+                }
+            }
+            MenuKind::LibraryFilter => {
+                let progress_count = ProgressFilter::ALL.len();
+                match menu.selected {
+                    0 => self.update_library_query(|query| query.filter = None),
+                    i if i <= progress_count => {
+                        let progress = ProgressFilter::ALL[i - 1];
+                        self.update_library_query(|query| {
+                            query.filter = Some(LibraryFilter::Progress(progress))
+                        });
+                    }
+                    i if i == progress_count + 1 => {
+                        self.open_filter_values_menu(MenuKind::FilterGenre)
+                    }
+                    i if i == progress_count + 2 => {
+                        self.open_filter_values_menu(MenuKind::FilterTag)
+                    }
+                    _ => self.open_filter_values_menu(MenuKind::FilterNarrator),
+                }
+            }
+            MenuKind::Playlist => {
+                let Some(item) = self.playlist_target.take() else {
+                    return;
+                };
+                match self.playlist_choices.get(menu.selected) {
+                    Some(playlist_id) => {
+                        let Some(playlist) = self.playlists.iter().find(|p| p.id == *playlist_id)
+                        else {
+                            return;
+                        };
+                        let command = if playlist.contains(&item.id) {
+                            self.notifications
+                                .info(format!("Removed from {}", playlist.name));
+                            ApiCommand::RemoveFromPlaylist(playlist.id.clone(), item.id)
+                        } else {
+                            self.notifications
+                                .info(format!("Added to {}", playlist.name));
+                            ApiCommand::AddToPlaylist(playlist.id.clone(), item.id)
+                        };
+                        let _ = self.api_tx.send(command);
+                    }
+                    None => {
+                        self.prompt = Some(Prompt::new(
+                            PromptKind::NewPlaylist(item.library_id, item.id),
+                            " New playlist name ",
+                        ));
+                    }
+                }
+            }
+            MenuKind::Person => {
+                if let Some((mode, id)) = self.people_choices.get(menu.selected).cloned() {
+                    self.show_person(mode, id);
+                }
             }
-            (PlayerState::Playing, PlayerState::Stopped) => {
-                self.sync_progress();
+            MenuKind::NextInSeries => {
+                if let Some(next) = self.next_in_series.take()
+                    && menu.selected == 0
+                {
+                    self.play_item(next);
+                }
             }
-            (PlayerState::Paused, PlayerState::Stopped) => {
-                self.sync_progress();
+            MenuKind::FilterGenre | MenuKind::FilterTag | MenuKind::FilterNarrator => {
+                let Some(value) = menu.items.get(menu.selected).cloned() else {
+                    return;
+                };
+                let filter = match menu.kind {
+                    MenuKind::FilterGenre => LibraryFilter::Genre(value),
+                    MenuKind::FilterTag => LibraryFilter::Tag(value),
+                    _ => LibraryFilter::Narrator(value),
+                };
+                self.update_library_query(|query| query.filter = Some(filter));
             }
-            _ => {}
-        }
-    }
-
-    pub fn on_position_update(&mut self, position: Duration) {
-        // Convert track-local position to global position
-        if let Some(ref track_info) = self.current_track_info {
-            self.current_position =
-                Duration::from_secs_f64(track_info.start_offset + position.as_secs_f64());
-        } else {
-            self.current_position = position;
         }
-
-        self.update_current_chapter();
-        self.check_track_boundary();
     }
 
-    fn check_track_boundary(&mut self) {
-        let Some(ref track_info) = self.current_track_info else {
+    fn handle_prompt_input(&mut self, key: KeyEvent) {
+        let Some(ref mut prompt) = self.prompt else {
             return;
         };
 
-        let track_end = track_info.start_offset + track_info.duration;
-        let global_pos = self.current_position.as_secs_f64();
-
-        if global_pos >= track_end - 0.5 {
-            let has_next = self
-                .current_tracks
-                .iter()
-                .any(|t| t.index == track_info.index + 1);
-
-            if has_next {
-                if let Some(ref item) = self.current_library_item {
-                    let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
-                        item.id.clone(),
-                        track_end + 0.1,
-                    ));
+        match key.code {
+            KeyCode::Char(c) => prompt.push(c),
+            KeyCode::Backspace => prompt.pop(),
+            KeyCode::Esc => self.prompt = None,
+            KeyCode::Enter => {
+                if let Some(prompt) = self.prompt.take() {
+                    self.on_prompt_submitted(prompt);
                 }
             }
+            _ => {}
         }
     }
 
-    fn update_current_chapter(&mut self) {
-        if self.current_item_id.is_none() {
-            return;
-        }
-
-        let pos_secs = self.current_position.as_secs_f64();
-
-        if let Some(ref current) = self.current_chapter {
-            if pos_secs >= current.start && pos_secs < current.end {
-                return; // Still in the same chapter
+    fn on_prompt_submitted(&mut self, prompt: Prompt) {
+        match prompt.kind {
+            PromptKind::SleepMinutes => match prompt.input.trim().parse::<u64>() {
+                Ok(minutes) if minutes > 0 => {
+                    self.set_sleep_timer(SleepTimer::Remaining(Duration::from_secs(minutes * 60)))
+                }
+                _ => self
+                    .notifications
+                    .warning(format!("Not a number of minutes: {}", prompt.input)),
+            },
+            PromptKind::NewBookmark(item_id, time) => {
+                let title = match prompt.input.trim() {
+                    "" => self.default_bookmark_title(time),
+                    note => note.to_string(),
+                };
+                let _ = self
+                    .api_tx
+                    .send(ApiCommand::CreateBookmark(item_id, time, title));
+                self.notifications.info("Bookmark added");
             }
-        }
-
-        for (i, chapter) in self.chapters.iter().enumerate() {
-            if pos_secs >= chapter.start && pos_secs < chapter.end {
-                self.current_chapter = Some(chapter.clone());
-                self.selected_chapter_index = i;
-                return;
+            PromptKind::NewPlaylist(library_id, item_id) => {
+                let name = prompt.input.trim();
+                if name.is_empty() {
+                    return;
+                }
+                let _ = self.api_tx.send(ApiCommand::CreatePlaylist(
+                    library_id,
+                    name.to_string(),
+                    item_id,
+                ));
+                self.notifications.info(format!("Created {}", name));
             }
-        }
-
-        if let Some(last) = self.chapters.last() {
-            if pos_secs >= last.end {
-                self.current_chapter = None;
+            PromptKind::EditBookmark(item_id, time) => {
+                let title = match prompt.input.trim() {
+                    "" => self.default_bookmark_title(time),
+                    note => note.to_string(),
+                };
+                let _ = self
+                    .api_tx
+                    .send(ApiCommand::UpdateBookmark(item_id, time, title));
             }
         }
     }
 
-    pub fn on_duration_changed(&mut self, duration: Duration) {
-        self.total_duration = duration;
-    }
-
-    pub fn on_track_ended(&mut self) {}
-
-    pub fn on_player_error(&mut self, error: String) {
-        self.error_message = Some(format!("Player error: {}", error));
-        self.notifications.error(format!("Player: {}", error));
-        self.player_state = PlayerState::Stopped;
-    }
-
     pub fn play_current_chapter(&mut self) {
-        if let Some(chapter) = self.chapters.get(self.selected_chapter_index) {
-            if let Some(item) = self.library_items.get(self.selected_library_item_index) {
-                let path = self.get_audio_file_path(item);
-                let position = Duration::from_secs_f64(chapter.start);
+        if let Some(chapter) = self.chapters.get(self.selected_chapter_index)
+            && let Some(item) = self.current_library_item.as_ref()
+        {
+            let item_id = item.id.clone();
+            let chapter = chapter.clone();
+            self.clear_play_until();
+
+            if self.current_item_id.as_ref() != Some(&item_id)
+                && self.player_state != PlayerState::Stopped
+            {
+                self.close_session();
+            }
 
-                self.current_chapter = Some(chapter.clone());
-                self.current_item_id = Some(item.id.clone());
+            self.current_chapter = Some(chapter.clone());
+            self.current_item_id = Some(item_id.clone());
 
-                let _ = self.player_tx.send(PlayerCommand::Play { path, position });
-            }
+            let _ = self
+                .api_tx
+                .send(ApiCommand::DownloadForPlayback(item_id, chapter.start));
+            self.is_downloading = true;
         }
     }
 
@@ -400,36 +2097,67 @@ impl App {
         }
     }
 
-    pub fn handle_input(&mut self, key: KeyEvent) -> () {
+    pub fn handle_input(&mut self, key: KeyEvent) {
+        self.extend_sleep_timer();
+
+        if self.prompt.is_some() {
+            self.handle_prompt_input(key);
+            return;
+        }
+
+        if self.menu.is_some() {
+            self.handle_menu_input(key);
+            return;
+        }
+
+        if self.search.as_ref().is_some_and(|s| s.editing) {
+            self.handle_search_input(key);
+            return;
+        }
+
+        if self.home.is_some() && self.handle_home_input(key) {
+            return;
+        }
+
+        if self.has_focus(Focus::Libraries)
+            && self.browse.mode != BrowseMode::Books
+            && self.handle_browse_input(key)
+        {
+            return;
+        }
+
         match key.code {
             KeyCode::Char('q') => {
-                self.sync_progress();
+                if self.player_state == PlayerState::Playing {
+                    self.record_pause();
+                }
+                self.close_session();
                 self.should_quit = true;
             }
-            KeyCode::Tab => {
+            KeyCode::Tab if self.home.is_none() => {
                 self.cycle_focus(false);
             }
-            KeyCode::BackTab => {
+            KeyCode::BackTab if self.home.is_none() => {
                 self.cycle_focus(true);
             }
-            KeyCode::Char('L') => {
-                if self.focus == Focus::Libraries {
-                    self.next_library();
-                    self.load_library_items(
-                        &self.libraries.clone()[self.selected_library_index].id,
-                    );
-                }
+            KeyCode::Char('S') if self.has_focus(Focus::Libraries) => {
+                self.open_sort_menu();
             }
-            KeyCode::Char('H') => {
-                if self.focus == Focus::Libraries {
-                    self.previous_library();
-                    self.load_library_items(
-                        &self.libraries.clone()[self.selected_library_index].id,
-                    );
-                }
+            KeyCode::Char('F') if self.has_focus(Focus::Libraries) => {
+                self.open_filter_menu();
+            }
+            KeyCode::Char('L') if self.has_focus(Focus::Libraries) => {
+                self.next_library();
+                self.load_library_items(&self.libraries.clone()[self.selected_library_index].id);
+                self.load_browse_groups();
+            }
+            KeyCode::Char('H') if self.has_focus(Focus::Libraries) => {
+                self.previous_library();
+                self.load_library_items(&self.libraries.clone()[self.selected_library_index].id);
+                self.load_browse_groups();
             }
             KeyCode::Char('l') | KeyCode::Right => {
-                if self.focus == Focus::Libraries {
+                if self.has_focus(Focus::Libraries) {
                     self.current_library_item = self
                         .library_items
                         .get(self.selected_library_item_index)
@@ -440,39 +2168,102 @@ impl App {
                     );
 
                     self.cycle_focus(false);
-                } else if self.focus == Focus::Chapters {
-                } else if self.focus == Focus::Controls {
+                } else if self.has_focus(Focus::Chapters) {
+                } else if self.has_focus(Focus::Controls) {
                     self.seek_forward(5.0);
-                } else if self.focus == Focus::InfoPanel {
+                } else if self.has_focus(Focus::InfoPanel) {
+                    self.open_people_menu(None);
                 }
             }
             KeyCode::Char('h') | KeyCode::Left => {
-                if self.focus == Focus::Libraries {
-                } else if self.focus == Focus::Chapters {
+                if self.has_focus(Focus::Libraries) {
+                } else if self.has_focus(Focus::Chapters)
+                    || self.has_focus(Focus::Bookmarks)
+                    || self.has_focus(Focus::Queue)
+                {
                     self.cycle_focus(true);
-                } else if self.focus == Focus::Controls {
+                } else if self.has_focus(Focus::Controls) {
                     self.seek_backward(5.0);
-                } else if self.focus == Focus::InfoPanel {
+                } else if self.has_focus(Focus::InfoPanel) {
                 }
             }
             KeyCode::Char('j') | KeyCode::Down => {
-                if self.focus == Focus::Libraries {
+                if self.has_focus(Focus::Libraries) {
                     self.next_library_item();
-                } else if self.focus == Focus::Chapters {
+                } else if self.has_focus(Focus::Chapters) {
                     self.next_chapter();
-                } else if self.focus == Focus::InfoPanel {
+                } else if self.has_focus(Focus::Bookmarks) {
+                    self.next_bookmark();
+                } else if self.has_focus(Focus::Queue) {
+                    self.next_queue_entry();
+                } else if self.has_focus(Focus::InfoPanel) {
                     self.scroll_info_down(100);
                 }
             }
             KeyCode::Char('k') | KeyCode::Up => {
-                if self.focus == Focus::Libraries {
+                if self.has_focus(Focus::Libraries) {
                     self.previous_libaray_item();
-                } else if self.focus == Focus::Chapters {
+                } else if self.has_focus(Focus::Chapters) {
                     self.previous_chapter();
-                } else if self.focus == Focus::InfoPanel {
+                } else if self.has_focus(Focus::Bookmarks) {
+                    self.previous_bookmark();
+                } else if self.has_focus(Focus::Queue) {
+                    self.previous_queue_entry();
+                } else if self.has_focus(Focus::InfoPanel) {
                     self.scroll_info_up();
                 }
             }
+            KeyCode::Char('p') if self.has_focus(Focus::Libraries) => {
+                self.open_playlist_menu();
+            }
+            KeyCode::Char('d') if self.has_focus(Focus::Libraries) => {
+                self.download_selected_for_offline();
+            }
+            KeyCode::Char('D') if self.has_focus(Focus::Libraries) => {
+                self.remove_selected_offline();
+            }
+            KeyCode::Char('a') => {
+                self.add_bookmark();
+            }
+            KeyCode::Char('e') if self.has_focus(Focus::Bookmarks) => {
+                self.edit_selected_bookmark();
+            }
+            KeyCode::Char('x') | KeyCode::Delete if self.has_focus(Focus::Bookmarks) => {
+                self.delete_selected_bookmark();
+            }
+            KeyCode::Char('u') if self.has_focus(Focus::Libraries) => {
+                self.enqueue_selected_item();
+            }
+            KeyCode::Char('u') if self.has_focus(Focus::Chapters) => {
+                self.enqueue_selected_chapter();
+            }
+            KeyCode::Char('x') | KeyCode::Delete if self.has_focus(Focus::Queue) => {
+                self.remove_selected_entry();
+            }
+            KeyCode::Char('J') if self.has_focus(Focus::Queue) => {
+                self.move_selected_entry(false);
+            }
+            KeyCode::Char('K') if self.has_focus(Focus::Queue) => {
+                self.move_selected_entry(true);
+            }
+            KeyCode::Char('/') if self.home.is_none() => {
+                self.start_search();
+            }
+            KeyCode::Char('v') if self.home.is_none() => {
+                self.cycle_browse_mode();
+            }
+            KeyCode::Char('g') => {
+                self.open_home();
+            }
+            KeyCode::Char('n') if self.has_focus(Focus::Libraries) && self.search.is_some() => {
+                self.jump_to_match(false);
+            }
+            KeyCode::Char('N') if self.has_focus(Focus::Libraries) && self.search.is_some() => {
+                self.jump_to_match(true);
+            }
+            KeyCode::Esc if self.has_focus(Focus::Libraries) && self.search.is_some() => {
+                self.end_search();
+            }
             KeyCode::Char('n') => {
                 self.show_remaining_time = !self.show_remaining_time;
                 let mode = if self.show_remaining_time {
@@ -482,8 +2273,35 @@ impl App {
                 };
                 self.notifications.info(format!("Time display: {}", mode));
             }
+            KeyCode::Char('+') | KeyCode::Char('=') => {
+                self.set_volume(self.volume + 0.05);
+            }
+            KeyCode::Char('-') => {
+                self.set_volume(self.volume - 0.05);
+            }
+            KeyCode::Char('m') => {
+                self.toggle_mute();
+            }
+            KeyCode::Char('b') => {
+                self.toggle_voice_boost();
+            }
+            KeyCode::Char('s') => {
+                self.toggle_trim_silence();
+            }
+            KeyCode::Char('t') => {
+                self.open_sleep_timer_menu();
+            }
+            KeyCode::Char('o') => {
+                let _ = self.player_tx.send(PlayerCommand::ListOutputDevices);
+            }
+            KeyCode::Char(']') => {
+                self.set_playback_speed(self.playback_speed + 0.1);
+            }
+            KeyCode::Char('[') => {
+                self.set_playback_speed(self.playback_speed - 0.1);
+            }
             KeyCode::Enter => {
-                if self.focus == Focus::Libraries {
+                if self.has_focus(Focus::Libraries) {
                     self.current_library_item = self
                         .library_items
                         .get(self.selected_library_item_index)
@@ -492,20 +2310,26 @@ impl App {
                     self.load_chapters(
                         &self.library_items.clone()[self.selected_library_item_index].id,
                     );
-                } else if self.focus == Focus::Chapters {
-                    if let (Some(selected_chapter), Some(selected_item)) = (
+                } else if self.has_focus(Focus::Chapters)
+                    && let (Some(selected_chapter), Some(selected_item)) = (
                         self.chapters.get(self.selected_chapter_index),
-                        self.library_items.get(self.selected_library_item_index),
-                    ) {
-                        self.is_downloading = true;
-                        self.current_chapter = Some(selected_chapter.clone());
-                        self.current_item_id = Some(selected_item.id.clone());
-
-                        let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
-                            selected_item.id.clone(),
-                            selected_chapter.start,
-                        ));
-                    }
+                        self.current_library_item.as_ref(),
+                    )
+                {
+                    self.is_downloading = true;
+                    self.current_chapter = Some(selected_chapter.clone());
+                    self.current_item_id = Some(selected_item.id.clone());
+
+                    let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
+                        selected_item.id.clone(),
+                        selected_chapter.start,
+                    ));
+                } else if self.has_focus(Focus::Bookmarks) {
+                    self.jump_to_selected_bookmark();
+                } else if self.has_focus(Focus::Queue) {
+                    self.play_selected_entry();
+                } else if self.has_focus(Focus::InfoPanel) {
+                    self.open_people_menu(None);
                 }
             }
             KeyCode::Char(' ') => {
@@ -520,48 +2344,103 @@ impl App {
     }
 
     pub fn handle_mouse(&mut self, event: MouseEvent) {
+        if matches!(event.kind, MouseEventKind::Down(_)) {
+            self.extend_sleep_timer();
+        }
+
+        if self.menu.is_some() || self.prompt.is_some() {
+            return;
+        }
+
+        if self.home.is_some()
+            && let Some(ref region) = self.layout_regions.home
+            && self.point_in_rect(event.column, event.row, region)
+        {
+            self.handle_home_mouse(event);
+            return;
+        }
+
         match event.kind {
             MouseEventKind::Down(MouseButton::Left) => {
                 let x = event.column;
                 let y = event.row;
 
-                if let Some(ref region) = self.layout_regions.library_list {
-                    if self.point_in_rect(x, y, region) {
-                        self.focus = Focus::Libraries;
-                        if y > region.y && y < region.y + region.height - 1 {
-                            let clicked_index = (y - region.y - 1) as usize;
-                            if clicked_index < self.library_items.len() {
-                                self.selected_library_item_index = clicked_index;
-                            }
+                if let Some(ref region) = self.layout_regions.library_list
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Libraries;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.library_offset + (y - region.y - 1) as usize;
+                        if self.browse.mode != BrowseMode::Books {
+                            self.browse.select(clicked_index);
+                        } else if clicked_index < self.library_items.len() {
+                            self.selected_library_item_index = clicked_index;
                         }
-                        return;
                     }
+                    return;
                 }
 
-                if let Some(ref region) = self.layout_regions.chapters {
-                    if self.point_in_rect(x, y, region) {
-                        self.focus = Focus::Chapters;
-                        if y > region.y && y < region.y + region.height - 1 {
-                            let clicked_index = (y - region.y - 1) as usize;
-                            if clicked_index < self.chapters.len() {
-                                self.selected_chapter_index = clicked_index;
-                            }
+                if let Some(ref region) = self.layout_regions.chapters
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Chapters;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.chapters_offset + (y - region.y - 1) as usize;
+                        if clicked_index < self.chapters.len() {
+                            self.selected_chapter_index = clicked_index;
+                        }
+                    }
+                    return;
+                }
+
+                if let Some(ref region) = self.layout_regions.bookmarks
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Bookmarks;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.bookmarks_offset + (y - region.y - 1) as usize;
+                        if clicked_index < self.bookmarks.len() {
+                            self.selected_bookmark_index = clicked_index;
                         }
-                        return;
                     }
+                    return;
                 }
 
-                if let Some(ref region) = self.layout_regions.controls {
-                    if self.point_in_rect(x, y, region) {
-                        self.focus = Focus::Controls;
-                        return;
+                if let Some(ref region) = self.layout_regions.queue
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Queue;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.queue_offset + (y - region.y - 1) as usize;
+                        if clicked_index < self.prefs.queue.len() {
+                            self.selected_queue_index = clicked_index;
+                        }
                     }
+                    return;
+                }
+
+                if let Some(ref region) = self.layout_regions.controls
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Controls;
+                    return;
                 }
 
-                if let Some(ref region) = self.layout_regions.info_panel {
-                    if self.point_in_rect(x, y, region) {
-                        self.focus = Focus::InfoPanel;
-                        return;
+                if let Some(ref region) = self.layout_regions.info_panel
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::InfoPanel;
+                    if let Some(&(_, mode)) = self
+                        .layout_regions
+                        .info_links
+                        .iter()
+                        .find(|(row, _)| *row == y)
+                    {
+                        self.open_people_menu(Some(mode));
                     }
                 }
             }
@@ -570,51 +2449,86 @@ impl App {
                 let x = event.column;
                 let y = event.row;
 
-                if let Some(ref region) = self.layout_regions.library_list {
-                    if self.point_in_rect(x, y, region) {
-                        self.focus = Focus::Libraries;
-                        if y > region.y && y < region.y + region.height - 1 {
-                            let clicked_index = (y - region.y - 1) as usize;
-                            if clicked_index < self.library_items.len() {
-                                self.selected_library_item_index = clicked_index;
-                                self.current_library_item = self
-                                    .library_items
-                                    .get(self.selected_library_item_index)
-                                    .cloned();
-                                self.load_chapters(
-                                    &self.library_items.clone()[self.selected_library_item_index]
-                                        .id,
-                                );
-                                self.focus = Focus::Chapters;
+                if let Some(ref region) = self.layout_regions.library_list
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Libraries;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.library_offset + (y - region.y - 1) as usize;
+                        if self.browse.mode != BrowseMode::Books {
+                            if clicked_index < self.browse.len() {
+                                self.browse.select(clicked_index);
+                                self.activate_browse_row();
                             }
+                        } else if clicked_index < self.library_items.len() {
+                            self.selected_library_item_index = clicked_index;
+                            self.current_library_item = self
+                                .library_items
+                                .get(self.selected_library_item_index)
+                                .cloned();
+                            self.load_chapters(
+                                &self.library_items.clone()[self.selected_library_item_index].id,
+                            );
+                            self.focus = Focus::Chapters;
                         }
-                        return;
                     }
+                    return;
                 }
 
-                if let Some(ref region) = self.layout_regions.chapters {
-                    if self.point_in_rect(x, y, region) {
-                        self.focus = Focus::Chapters;
-                        if y > region.y && y < region.y + region.height - 1 {
-                            let clicked_index = (y - region.y - 1) as usize;
-                            if clicked_index < self.chapters.len() {
-                                self.selected_chapter_index = clicked_index;
-                                if let (Some(selected_chapter), Some(selected_item)) = (
-                                    self.chapters.get(self.selected_chapter_index),
-                                    self.library_items.get(self.selected_library_item_index),
-                                ) {
-                                    self.current_chapter = Some(selected_chapter.clone());
-                                    self.current_item_id = Some(selected_item.id.clone());
-
-                                    self.is_downloading = true;
-                                    let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
-                                        selected_item.id.clone(),
-                                        selected_chapter.start,
-                                    ));
-                                }
+                if let Some(ref region) = self.layout_regions.chapters
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Chapters;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.chapters_offset + (y - region.y - 1) as usize;
+                        if clicked_index < self.chapters.len() {
+                            self.selected_chapter_index = clicked_index;
+                            if let (Some(selected_chapter), Some(selected_item)) = (
+                                self.chapters.get(self.selected_chapter_index),
+                                self.current_library_item.as_ref(),
+                            ) {
+                                self.current_chapter = Some(selected_chapter.clone());
+                                self.current_item_id = Some(selected_item.id.clone());
+
+                                self.is_downloading = true;
+                                let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
+                                    selected_item.id.clone(),
+                                    selected_chapter.start,
+                                ));
                             }
                         }
-                        return;
+                    }
+                    return;
+                }
+
+                if let Some(ref region) = self.layout_regions.bookmarks
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Bookmarks;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.bookmarks_offset + (y - region.y - 1) as usize;
+                        if clicked_index < self.bookmarks.len() {
+                            self.selected_bookmark_index = clicked_index;
+                            self.jump_to_selected_bookmark();
+                        }
+                    }
+                    return;
+                }
+
+                if let Some(ref region) = self.layout_regions.queue
+                    && self.point_in_rect(x, y, region)
+                {
+                    self.focus = Focus::Queue;
+                    if y > region.y && y < region.y + region.height - 1 {
+                        let clicked_index =
+                            self.layout_regions.queue_offset + (y - region.y - 1) as usize;
+                        if clicked_index < self.prefs.queue.len() {
+                            self.selected_queue_index = clicked_index;
+                            self.play_selected_entry();
+                        }
                     }
                 }
             }
@@ -624,15 +2538,21 @@ impl App {
             }
 
             MouseEventKind::ScrollUp => match self.focus {
+                Focus::Libraries if self.browse.mode != BrowseMode::Books => self.browse.previous(),
                 Focus::Libraries => self.previous_libaray_item(),
                 Focus::Chapters => self.previous_chapter(),
+                Focus::Bookmarks => self.previous_bookmark(),
+                Focus::Queue => self.previous_queue_entry(),
                 Focus::Controls => self.seek_forward(5.0),
                 Focus::InfoPanel => self.scroll_info_up(),
             },
 
             MouseEventKind::ScrollDown => match self.focus {
+                Focus::Libraries if self.browse.mode != BrowseMode::Books => self.browse.next(),
                 Focus::Libraries => self.next_library_item(),
                 Focus::Chapters => self.next_chapter(),
+                Focus::Bookmarks => self.next_bookmark(),
+                Focus::Queue => self.next_queue_entry(),
                 Focus::Controls => self.seek_backward(5.0),
                 Focus::InfoPanel => self.scroll_info_down(100),
             },
@@ -641,16 +2561,26 @@ impl App {
         }
     }
 
+    /// Whether `focus` has the keyboard. None of the panels does while the
+    /// home view covers them.
+    fn has_focus(&self, focus: Focus) -> bool {
+        self.home.is_none() && self.focus == focus
+    }
+
     pub fn cycle_focus(&mut self, reverse: bool) {
         self.focus = match (self.focus, reverse) {
             (Focus::Libraries, false) => Focus::Chapters,
-            (Focus::Chapters, false) => Focus::InfoPanel,
+            (Focus::Chapters, false) => Focus::Bookmarks,
+            (Focus::Bookmarks, false) => Focus::Queue,
+            (Focus::Queue, false) => Focus::InfoPanel,
             (Focus::InfoPanel, false) => Focus::Controls,
             (Focus::Controls, false) => Focus::Libraries,
 
             (Focus::Libraries, true) => Focus::Controls,
             (Focus::Chapters, true) => Focus::Libraries,
-            (Focus::InfoPanel, true) => Focus::Chapters,
+            (Focus::Bookmarks, true) => Focus::Chapters,
+            (Focus::Queue, true) => Focus::Bookmarks,
+            (Focus::InfoPanel, true) => Focus::Queue,
             (Focus::Controls, true) => Focus::InfoPanel,
         };
     }
@@ -669,6 +2599,7 @@ impl App {
         let library_items_count = self.library_items.len();
         self.selected_library_item_index =
             increment(self.selected_library_item_index, library_items_count, false);
+        self.prefetch_items();
     }
 
     pub fn previous_libaray_item(&mut self) {
@@ -687,12 +2618,228 @@ impl App {
         self.selected_chapter_index = decrement(self.selected_chapter_index, chapters_count, false);
     }
 
+    pub fn next_bookmark(&mut self) {
+        if !self.bookmarks.is_empty() {
+            self.selected_bookmark_index =
+                increment(self.selected_bookmark_index, self.bookmarks.len(), false);
+        }
+    }
+
+    pub fn previous_bookmark(&mut self) {
+        if !self.bookmarks.is_empty() {
+            self.selected_bookmark_index =
+                decrement(self.selected_bookmark_index, self.bookmarks.len(), false);
+        }
+    }
+
+    /// Asks for a note and bookmarks the position being played.
+    fn add_bookmark(&mut self) {
+        let Some(item_id) = self.current_item_id.clone() else {
+            self.notifications.warning("Nothing is playing");
+            return;
+        };
+        if self.player_state == PlayerState::Stopped {
+            self.notifications.warning("Nothing is playing");
+            return;
+        }
+
+        let time = self.current_position.as_secs_f64();
+        self.prompt = Some(Prompt::new(
+            PromptKind::NewBookmark(item_id, time),
+            format!(" Bookmark at {} (note optional) ", format_duration(time)),
+        ));
+    }
+
+    fn edit_selected_bookmark(&mut self) {
+        if let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index) {
+            self.prompt = Some(
+                Prompt::new(
+                    PromptKind::EditBookmark(bookmark.library_item_id.clone(), bookmark.time),
+                    " Edit bookmark ",
+                )
+                .with_input(&bookmark.title),
+            );
+        }
+    }
+
+    fn delete_selected_bookmark(&mut self) {
+        if let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index) {
+            let _ = self.api_tx.send(ApiCommand::DeleteBookmark(
+                bookmark.library_item_id.clone(),
+                bookmark.time,
+            ));
+            self.notifications.info("Bookmark deleted");
+        }
+    }
+
+    pub fn next_queue_entry(&mut self) {
+        if !self.prefs.queue.is_empty() {
+            self.selected_queue_index =
+                increment(self.selected_queue_index, self.prefs.queue.len(), false);
+        }
+    }
+
+    pub fn previous_queue_entry(&mut self) {
+        if !self.prefs.queue.is_empty() {
+            self.selected_queue_index =
+                decrement(self.selected_queue_index, self.prefs.queue.len(), false);
+        }
+    }
+
+    fn enqueue(&mut self, entry: QueueEntry) {
+        self.notifications.info(format!("Queued {}", entry.label()));
+        self.prefs.queue.push(entry);
+        self.prefs.save();
+    }
+
+    fn enqueue_selected_item(&mut self) {
+        if let Some(item) = self.selected_item().cloned() {
+            self.enqueue(QueueEntry { item, range: None });
+        }
+    }
+
+    /// Queues the selected chapter. Queueing the chapter after the last
+    /// queued one of the same book extends that range instead.
+    fn enqueue_selected_chapter(&mut self) {
+        let (Some(chapter), Some(item)) = (
+            self.chapters.get(self.selected_chapter_index),
+            self.current_library_item.as_ref(),
+        ) else {
+            return;
+        };
+
+        if let Some(last) = self.prefs.queue.last_mut()
+            && last.item.id == item.id
+            && let Some(range) = &mut last.range
+            && range.extend(chapter)
+        {
+            self.notifications.info(format!("Queued {}", last.label()));
+            self.prefs.save();
+            return;
+        }
+
+        let entry = QueueEntry {
+            item: item.clone(),
+            range: Some(ChapterRange::new(chapter)),
+        };
+        self.enqueue(entry);
+    }
+
+    fn remove_selected_entry(&mut self) {
+        if self.selected_queue_index < self.prefs.queue.len() {
+            self.prefs.queue.remove(self.selected_queue_index);
+            self.prefs.save();
+            self.selected_queue_index = self
+                .selected_queue_index
+                .min(self.prefs.queue.len().saturating_sub(1));
+        }
+    }
+
+    /// Swaps the selected entry with the one above or below, keeping it
+    /// selected.
+    fn move_selected_entry(&mut self, up: bool) {
+        let index = self.selected_queue_index;
+        let target = if up {
+            index.checked_sub(1)
+        } else {
+            Some(index + 1).filter(|&i| i < self.prefs.queue.len())
+        };
+        if let Some(target) = target {
+            self.prefs.queue.swap(index, target);
+            self.prefs.save();
+            self.selected_queue_index = target;
+        }
+    }
+
+    /// Plays the selected entry now, taking it out of the queue.
+    fn play_selected_entry(&mut self) {
+        if self.selected_queue_index < self.prefs.queue.len() {
+            let entry = self.prefs.queue.remove(self.selected_queue_index);
+            self.prefs.save();
+            self.selected_queue_index = self
+                .selected_queue_index
+                .min(self.prefs.queue.len().saturating_sub(1));
+            self.play_entry(entry);
+        }
+    }
+
+    /// The chapters of the queue entry being played are over. Moves on to
+    /// the next entry, or pauses if there is none.
+    fn on_range_finished(&mut self) {
+        self.play_until = None;
+        if self.prefs.queue.is_empty() {
+            return;
+        }
+
+        let next = self.prefs.queue.remove(0);
+        self.prefs.save();
+        self.notifications
+            .info(format!("Up next: {}", next.label()));
+        self.play_entry(next);
+    }
+
+    /// Stops the player from pausing at the end of a chapter range.
+    fn clear_play_until(&mut self) {
+        if self.play_until.take().is_some() {
+            let _ = self.player_tx.send(PlayerCommand::StopAt(None));
+        }
+    }
+
+    /// Bookmark title for an empty note: the chapter it's in.
+    fn default_bookmark_title(&self, time: f64) -> String {
+        self.chapters
+            .iter()
+            .find(|ch| time >= ch.start && time < ch.end)
+            .map(|ch| ch.title.clone())
+            .unwrap_or_else(|| format!("Bookmark at {}", format_duration(time)))
+    }
+
+    /// Plays from the selected bookmark, starting its book if another one
+    /// is playing.
+    fn jump_to_selected_bookmark(&mut self) {
+        let Some(bookmark) = self.bookmarks.get(self.selected_bookmark_index).cloned() else {
+            return;
+        };
+
+        let is_playing_item = self.current_item_id.as_ref() == Some(&bookmark.library_item_id);
+        if is_playing_item && self.player_state != PlayerState::Stopped {
+            self.seek_to_global_position(bookmark.time);
+            return;
+        }
+
+        if self.player_state != PlayerState::Stopped {
+            self.close_session();
+        }
+
+        self.current_chapter = self
+            .chapters
+            .iter()
+            .find(|ch| bookmark.time >= ch.start && bookmark.time < ch.end)
+            .cloned();
+        self.current_item_id = Some(bookmark.library_item_id.clone());
+
+        let _ = self.api_tx.send(ApiCommand::DownloadForPlayback(
+            bookmark.library_item_id,
+            bookmark.time,
+        ));
+        self.is_downloading = true;
+    }
+
     pub fn toggle_playback(&mut self) {
         match self.player_state {
             PlayerState::Playing => {
                 let _ = self.player_tx.send(PlayerCommand::Pause);
             }
             PlayerState::Paused => {
+                let rewind = self
+                    .current_item_id
+                    .as_deref()
+                    .map_or(0.0, |id| self.smart_rewind_secs(id));
+                if rewind > 0.0 {
+                    let position = self.current_position.as_secs_f64();
+                    let target = (position - rewind).max(self.chapter_start_at(position));
+                    self.seek_to_global_position(target);
+                }
                 let _ = self.player_tx.send(PlayerCommand::Resume);
             }
             PlayerState::Stopped => {
@@ -702,6 +2849,128 @@ impl App {
         }
     }
 
+    /// Remembers when the current book was paused, across restarts.
+    fn record_pause(&mut self) {
+        if let Some(ref item_id) = self.current_item_id {
+            self.prefs.item_paused_at.insert(item_id.clone(), now_ms());
+            self.prefs.save();
+        }
+    }
+
+    /// Seconds to rewind when resuming a book, from the first rule that
+    /// matches how long it has been paused. A book with no recorded pause
+    /// counts as paused for a long time.
+    fn smart_rewind_secs(&self, item_id: &str) -> f64 {
+        let paused = self
+            .prefs
+            .item_paused_at
+            .get(item_id)
+            .map_or(Duration::MAX, |&at| {
+                Duration::from_millis((now_ms() - at).max(0) as u64)
+            });
+
+        self.rewind_rules
+            .iter()
+            .find(|rule| {
+                rule.paused_under_secs
+                    .is_none_or(|under| paused < Duration::from_secs(under))
+            })
+            .map_or(0.0, |rule| rule.rewind_secs)
+    }
+
+    /// Start of the chapter containing `position`, so a rewind doesn't
+    /// cross into the previous chapter.
+    fn chapter_start_at(&self, position: f64) -> f64 {
+        self.chapters
+            .iter()
+            .find(|ch| position >= ch.start && position < ch.end)
+            .map(|ch| ch.start)
+            .unwrap_or(0.0)
+    }
+
+    pub fn set_playback_speed(&mut self, speed: f32) {
+        let speed = ((speed * 10.0).round() / 10.0).clamp(MIN_SPEED, MAX_SPEED);
+        if speed == self.playback_speed {
+            return;
+        }
+
+        self.playback_speed = speed;
+        let _ = self.player_tx.send(PlayerCommand::SetSpeed(speed));
+        self.notifications.info(format!("Speed: {:.1}x", speed));
+    }
+
+    /// Sets the volume and remembers it for the current book.
+    pub fn set_volume(&mut self, volume: f32) {
+        let volume = ((volume * 20.0).round() / 20.0).clamp(0.0, MAX_VOLUME);
+        if self.muted {
+            self.toggle_mute();
+        }
+        if volume == self.volume {
+            return;
+        }
+
+        self.volume = volume;
+        let _ = self.player_tx.send(PlayerCommand::SetVolume(volume));
+        self.notifications
+            .info(format!("Volume: {:.0}%", volume * 100.0));
+
+        if let Some(ref item_id) = self.current_item_id {
+            self.prefs.item_volumes.insert(item_id.clone(), volume);
+            self.prefs.save();
+        }
+    }
+
+    pub fn toggle_mute(&mut self) {
+        self.muted = !self.muted;
+        let _ = self.player_tx.send(PlayerCommand::Mute(self.muted));
+        self.notifications
+            .info(if self.muted { "Muted" } else { "Unmuted" });
+    }
+
+    pub fn toggle_voice_boost(&mut self) {
+        self.voice_boost = !self.voice_boost;
+        let _ = self
+            .player_tx
+            .send(PlayerCommand::SetVoiceBoost(self.voice_boost));
+        self.notifications.info(if self.voice_boost {
+            "Voice boost on"
+        } else {
+            "Voice boost off"
+        });
+    }
+
+    pub fn toggle_trim_silence(&mut self) {
+        self.trim_silence = !self.trim_silence;
+        let _ = self
+            .player_tx
+            .send(PlayerCommand::SetTrimSilence(self.trim_silence));
+        self.notifications.info(if self.trim_silence {
+            "Trim silence on"
+        } else {
+            "Trim silence off"
+        });
+    }
+
+    pub fn on_time_saved(&mut self, saved: Duration) {
+        self.time_saved = self.time_saved_before + saved;
+    }
+
+    /// Applies the volume saved for the current book, or full volume for a
+    /// book that has none, so one book's setting doesn't carry over.
+    fn restore_item_volume(&mut self) {
+        let volume = self
+            .current_item_id
+            .as_ref()
+            .and_then(|id| self.prefs.item_volumes.get(id))
+            .copied()
+            .unwrap_or(1.0);
+
+        if volume != self.volume {
+            self.volume = volume;
+            let _ = self.player_tx.send(PlayerCommand::SetVolume(volume));
+        }
+    }
+
     #[allow(dead_code)]
     pub fn stop_playback(&mut self) {
         let _ = self.player_tx.send(PlayerCommand::Stop);
@@ -751,8 +3020,4 @@ impl App {
                 .send(PlayerCommand::Seek(Duration::from_secs_f64(global_pos)));
         }
     }
-
-    fn get_audio_file_path(&self, item: &LibraryItem) -> PathBuf {
-        PathBuf::from(format!("/tmp/decibelle_{}.audio", item.id))
-    }
 }
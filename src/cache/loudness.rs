@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+const LOUDNESS_FILE: &str = "loudness.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LoudnessEntry {
+    size: u64,
+    mtime_ms: u64,
+    lufs: f64,
+}
+
+/// Integrated loudness measured for local audio files, kept next to the
+/// audio cache so each file is only analysed once.
+///
+/// Entries are keyed by path and remember the file's size and mtime, so a
+/// replaced file is measured again.
+pub struct LoudnessStore {
+    path: PathBuf,
+    entries: HashMap<PathBuf, LoudnessEntry>,
+}
+
+impl LoudnessStore {
+    pub fn open() -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .context("Could not find cache directory")?
+            .join("decibelle");
+        fs::create_dir_all(&dir).context("Failed to create cache directory")?;
+
+        let path = dir.join(LOUDNESS_FILE);
+        let mut entries: HashMap<PathBuf, LoudnessEntry> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        // Forget files that were evicted or removed
+        let before = entries.len();
+        entries.retain(|file, _| file.exists());
+
+        let store = Self { path, entries };
+        if store.entries.len() != before {
+            store.save()?;
+        }
+        Ok(store)
+    }
+
+    /// Returns the loudness measured for `file` if it hasn't changed since.
+    pub fn get(&self, file: &Path) -> Option<f64> {
+        let entry = self.entries.get(file)?;
+        (file_stamp(file)? == (entry.size, entry.mtime_ms)).then_some(entry.lufs)
+    }
+
+    pub fn insert(&mut self, file: &Path, lufs: f64) -> Result<()> {
+        let (size, mtime_ms) = file_stamp(file).context("Failed to stat measured file")?;
+        self.entries.insert(
+            file.to_path_buf(),
+            LoudnessEntry {
+                size,
+                mtime_ms,
+                lufs,
+            },
+        );
+        self.save()
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&self.entries).context("Failed to serialize loudness")?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json).context("Failed to write loudness")?;
+        fs::rename(&tmp, &self.path).context("Failed to write loudness")?;
+        Ok(())
+    }
+}
+
+fn file_stamp(file: &Path) -> Option<(u64, u64)> {
+    let metadata = fs::metadata(file).ok()?;
+    let mtime_ms = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    Some((metadata.len(), mtime_ms))
+}
@@ -0,0 +1,492 @@
+pub mod loudness;
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::api::models::AudioTrack;
+
+const MANIFEST_FILE: &str = "manifest.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheEntry {
+    file_name: String,
+    size: u64,
+    mtime_ms: Option<i64>,
+    last_access: u64,
+}
+
+/// A download that was started but not finished, kept for resuming.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PartialEntry {
+    file_name: String,
+    last_access: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    entries: HashMap<String, CacheEntry>,
+    #[serde(default)]
+    partials: HashMap<String, PartialEntry>,
+}
+
+/// On-disk cache of audio tracks with a byte budget and LRU eviction.
+///
+/// Every file is recorded in a manifest together with the size and mtime
+/// the server reported for it, so a truncated or outdated file is never
+/// handed to the player.
+pub struct AudioCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    manifest: Manifest,
+    in_flight: HashSet<String>,
+}
+
+impl AudioCache {
+    pub fn open(max_bytes: u64) -> Result<Self> {
+        let dir = dirs::cache_dir()
+            .context("Could not find cache directory")?
+            .join("decibelle")
+            .join("audio");
+        let cache = Self::open_in(dir, max_bytes)?;
+        remove_legacy_downloads();
+        Ok(cache)
+    }
+
+    fn open_in(dir: PathBuf, max_bytes: u64) -> Result<Self> {
+        fs::create_dir_all(&dir).context("Failed to create cache directory")?;
+
+        let manifest = fs::read_to_string(dir.join(MANIFEST_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        let mut cache = Self {
+            dir,
+            max_bytes,
+            manifest,
+            in_flight: HashSet::new(),
+        };
+        cache.remove_orphans();
+        cache.evict(0);
+        cache.save()?;
+
+        Ok(cache)
+    }
+
+    /// Returns the cached file for `track` if it is complete and still
+    /// matches the server's copy, and marks it as recently used.
+    pub fn lookup(&mut self, item_id: &str, track: &AudioTrack) -> Option<PathBuf> {
+        let key = cache_key(item_id, track);
+        let entry = self.manifest.entries.get(&key)?;
+        let path = self.dir.join(&entry.file_name);
+
+        let expected = track.metadata.as_ref();
+        let on_disk = fs::metadata(&path).map(|m| m.len()).ok();
+        let is_valid = expected
+            .is_some_and(|m| m.size as u64 == entry.size && m.mtime_ms == entry.mtime_ms)
+            && on_disk == Some(entry.size);
+
+        if !is_valid {
+            self.remove(&key);
+            let _ = self.save();
+            return None;
+        }
+
+        if let Some(entry) = self.manifest.entries.get_mut(&key) {
+            entry.last_access = now_ms();
+        }
+        let _ = self.save();
+
+        Some(path)
+    }
+
+    /// Claims a track for downloading. Returns the `.part` path to write
+    /// to, or `None` if the track can't be cached or is already in flight.
+    ///
+    /// The server mtime is part of the file name so that a partial download
+    /// is only resumed against the same version of the file. A partial of an
+    /// older version is deleted.
+    pub fn begin_download(&mut self, item_id: &str, track: &AudioTrack) -> Option<PathBuf> {
+        let metadata = track.metadata.as_ref()?;
+        if metadata.size as u64 > self.max_bytes {
+            return None;
+        }
+
+        let key = cache_key(item_id, track);
+        if !self.in_flight.insert(key.clone()) {
+            return None;
+        }
+
+        let file_name = format!("{}-{}.part", key, metadata.mtime_ms.unwrap_or(0));
+        if self
+            .manifest
+            .partials
+            .get(&key)
+            .is_some_and(|p| p.file_name != file_name)
+        {
+            self.remove_partial(&key);
+        }
+        self.manifest.partials.insert(
+            key,
+            PartialEntry {
+                file_name: file_name.clone(),
+                last_access: now_ms(),
+            },
+        );
+        let _ = self.save();
+
+        Some(self.dir.join(file_name))
+    }
+
+    /// Moves a finished download into the cache. The file is discarded if
+    /// its size doesn't match what the server reported.
+    pub fn finish_download(
+        &mut self,
+        item_id: &str,
+        track: &AudioTrack,
+        part_path: &Path,
+    ) -> Result<()> {
+        let key = cache_key(item_id, track);
+        self.in_flight.remove(&key);
+        self.manifest.partials.remove(&key);
+
+        let Some(metadata) = track.metadata.as_ref() else {
+            let _ = fs::remove_file(part_path);
+            let _ = self.save();
+            anyhow::bail!("Track {} has no file metadata", key);
+        };
+
+        let size = fs::metadata(part_path)
+            .context("Failed to stat download")?
+            .len();
+        if size != metadata.size as u64 {
+            let _ = fs::remove_file(part_path);
+            let _ = self.save();
+            anyhow::bail!(
+                "Incomplete download for {}: {} of {} bytes",
+                key,
+                size,
+                metadata.size
+            );
+        }
+
+        let file_name = format!("{}.{}", key, metadata.ext.trim_start_matches('.'));
+        fs::rename(part_path, self.dir.join(&file_name))
+            .context("Failed to move download into cache")?;
+
+        self.remove(&key);
+        self.evict(size);
+        self.manifest.entries.insert(
+            key,
+            CacheEntry {
+                file_name,
+                size,
+                mtime_ms: metadata.mtime_ms,
+                last_access: now_ms(),
+            },
+        );
+
+        self.save()
+    }
+
+    /// Releases a failed download. The `.part` file is kept so the next
+    /// attempt can resume it, and counts against the budget until then.
+    pub fn abort_download(&mut self, item_id: &str, track: &AudioTrack) {
+        self.in_flight.remove(&cache_key(item_id, track));
+    }
+
+    /// Size of the finished files plus what's on disk of partial downloads.
+    fn total_size(&self) -> u64 {
+        let complete: u64 = self.manifest.entries.values().map(|e| e.size).sum();
+        let partial: u64 = self
+            .manifest
+            .partials
+            .values()
+            .filter_map(|p| fs::metadata(self.dir.join(&p.file_name)).ok())
+            .map(|m| m.len())
+            .sum();
+        complete + partial
+    }
+
+    /// Evicts least recently used entries and partial downloads until
+    /// `incoming` more bytes fit. Downloads in progress are left alone.
+    fn evict(&mut self, incoming: u64) {
+        while self.total_size() + incoming > self.max_bytes {
+            let entries = self
+                .manifest
+                .entries
+                .iter()
+                .map(|(k, e)| (e.last_access, k, false));
+            let partials = self
+                .manifest
+                .partials
+                .iter()
+                .filter(|(k, _)| !self.in_flight.contains(*k))
+                .map(|(k, p)| (p.last_access, k, true));
+            let oldest = entries
+                .chain(partials)
+                .min_by_key(|(last_access, _, _)| *last_access)
+                .map(|(_, k, is_partial)| (k.clone(), is_partial));
+
+            match oldest {
+                Some((key, false)) => self.remove(&key),
+                Some((key, true)) => self.remove_partial(&key),
+                None => break,
+            }
+        }
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.manifest.entries.remove(key) {
+            let _ = fs::remove_file(self.dir.join(entry.file_name));
+        }
+    }
+
+    fn remove_partial(&mut self, key: &str) {
+        if let Some(partial) = self.manifest.partials.remove(key) {
+            let _ = fs::remove_file(self.dir.join(partial.file_name));
+        }
+    }
+
+    fn remove_orphans(&mut self) {
+        let known: HashSet<&str> = self
+            .manifest
+            .entries
+            .values()
+            .map(|e| e.file_name.as_str())
+            .chain(
+                self.manifest
+                    .partials
+                    .values()
+                    .map(|p| p.file_name.as_str()),
+            )
+            .collect();
+
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        for entry in dir.flatten() {
+            let name = entry.file_name();
+            let name = name.to_string_lossy();
+            if name != MANIFEST_FILE && !known.contains(name.as_ref()) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+
+        let dir = self.dir.clone();
+        self.manifest
+            .entries
+            .retain(|_, e| dir.join(&e.file_name).exists());
+        self.manifest
+            .partials
+            .retain(|_, p| dir.join(&p.file_name).exists());
+    }
+
+    fn save(&self) -> Result<()> {
+        let json = serde_json::to_string(&self.manifest).context("Failed to serialize manifest")?;
+        let tmp = self.dir.join(format!("{}.tmp", MANIFEST_FILE));
+        fs::write(&tmp, json).context("Failed to write manifest")?;
+        fs::rename(&tmp, self.dir.join(MANIFEST_FILE)).context("Failed to write manifest")?;
+        Ok(())
+    }
+}
+
+/// Older versions downloaded tracks to /tmp and never removed them.
+fn remove_legacy_downloads() {
+    let Ok(dir) = fs::read_dir(std::env::temp_dir()) else {
+        return;
+    };
+
+    for entry in dir.flatten() {
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+        if name.starts_with("decibelle_") && name.ends_with(".audio") {
+            let _ = fs::remove_file(entry.path());
+        }
+    }
+}
+
+fn cache_key(item_id: &str, track: &AudioTrack) -> String {
+    format!("{}_{}", item_id, track.index)
+}
+
+fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::api::models::FileMetadata;
+
+    /// Cache directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "decibelle-cache-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn track(index: i32, size: i64, mtime_ms: i64) -> AudioTrack {
+        AudioTrack {
+            index,
+            start_offset: 0.0,
+            duration: 60.0,
+            title: format!("Track {}", index),
+            content_url: format!("/track/{}", index),
+            mime_type: "audio/mpeg".to_string(),
+            metadata: Some(FileMetadata {
+                filename: format!("{}.mp3", index),
+                ext: ".mp3".to_string(),
+                path: String::new(),
+                rel_path: String::new(),
+                size,
+                mtime_ms: Some(mtime_ms),
+                ctime_ms: None,
+                birthtime_ms: None,
+            }),
+        }
+    }
+
+    /// Downloads `track` into the cache with `len` bytes of content.
+    fn download(cache: &mut AudioCache, track: &AudioTrack, len: usize) -> Result<()> {
+        let part = cache.begin_download("item", track).unwrap();
+        fs::write(&part, vec![7u8; len]).unwrap();
+        cache.finish_download("item", track, &part)
+    }
+
+    fn files_in(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn finished_download_survives_reopening() {
+        let dir = TempDir::new("reopen");
+        let first = track(0, 100, 1);
+
+        let mut cache = AudioCache::open_in(dir.0.clone(), 1000).unwrap();
+        download(&mut cache, &first, 100).unwrap();
+        let path = cache.lookup("item", &first).unwrap();
+        drop(cache);
+
+        let mut cache = AudioCache::open_in(dir.0.clone(), 1000).unwrap();
+        assert_eq!(cache.lookup("item", &first), Some(path));
+        assert_eq!(cache.total_size(), 100);
+    }
+
+    #[test]
+    fn changed_track_is_not_served() {
+        let dir = TempDir::new("changed");
+        let mut cache = AudioCache::open_in(dir.0.clone(), 1000).unwrap();
+        download(&mut cache, &track(0, 100, 1), 100).unwrap();
+
+        assert_eq!(cache.lookup("item", &track(0, 100, 2)), None);
+        assert_eq!(cache.total_size(), 0);
+        assert_eq!(files_in(&dir.0), vec![MANIFEST_FILE.to_string()]);
+    }
+
+    #[test]
+    fn incomplete_download_is_discarded() {
+        let dir = TempDir::new("incomplete");
+        let mut cache = AudioCache::open_in(dir.0.clone(), 1000).unwrap();
+        let first = track(0, 100, 1);
+
+        assert!(download(&mut cache, &first, 60).is_err());
+        assert_eq!(cache.lookup("item", &first), None);
+        assert_eq!(files_in(&dir.0), vec![MANIFEST_FILE.to_string()]);
+    }
+
+    #[test]
+    fn evicts_least_recently_used_to_stay_in_budget() {
+        let dir = TempDir::new("evict");
+        let mut cache = AudioCache::open_in(dir.0.clone(), 250).unwrap();
+        let (first, second, third) = (track(0, 100, 1), track(1, 100, 1), track(2, 100, 1));
+
+        download(&mut cache, &first, 100).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        download(&mut cache, &second, 100).unwrap();
+        std::thread::sleep(Duration::from_millis(5));
+        assert!(cache.lookup("item", &first).is_some());
+        std::thread::sleep(Duration::from_millis(5));
+        download(&mut cache, &third, 100).unwrap();
+
+        assert!(cache.lookup("item", &first).is_some());
+        assert!(cache.lookup("item", &second).is_none());
+        assert!(cache.lookup("item", &third).is_some());
+        assert_eq!(cache.total_size(), 200);
+    }
+
+    #[test]
+    fn track_larger_than_budget_is_not_cached() {
+        let dir = TempDir::new("too-large");
+        let mut cache = AudioCache::open_in(dir.0.clone(), 50).unwrap();
+        assert_eq!(cache.begin_download("item", &track(0, 100, 1)), None);
+    }
+
+    #[test]
+    fn partial_downloads_count_towards_budget() {
+        let dir = TempDir::new("partials");
+        let mut cache = AudioCache::open_in(dir.0.clone(), 250).unwrap();
+        let (first, second) = (track(0, 100, 1), track(1, 200, 1));
+
+        let part = cache.begin_download("item", &first).unwrap();
+        fs::write(&part, vec![7u8; 80]).unwrap();
+        cache.abort_download("item", &first);
+        assert_eq!(cache.total_size(), 80);
+
+        // The abandoned partial is the oldest, so it makes room
+        std::thread::sleep(Duration::from_millis(5));
+        download(&mut cache, &second, 200).unwrap();
+        assert!(!part.exists());
+        assert_eq!(cache.total_size(), 200);
+    }
+
+    #[test]
+    fn stale_partials_are_removed() {
+        let dir = TempDir::new("stale");
+        let mut cache = AudioCache::open_in(dir.0.clone(), 1000).unwrap();
+
+        let old = cache.begin_download("item", &track(0, 100, 1)).unwrap();
+        fs::write(&old, vec![7u8; 40]).unwrap();
+        cache.abort_download("item", &track(0, 100, 1));
+
+        // The server's copy changed, so the old partial can't be resumed
+        let new = cache.begin_download("item", &track(0, 100, 2)).unwrap();
+        assert_ne!(old, new);
+        assert!(!old.exists());
+        drop(cache);
+
+        fs::write(dir.0.join("unknown-1.part"), b"left over").unwrap();
+        AudioCache::open_in(dir.0.clone(), 1000).unwrap();
+        assert_eq!(files_in(&dir.0), vec![MANIFEST_FILE.to_string()]);
+    }
+}
@@ -15,6 +15,15 @@ pub enum ImageProtocol {
     Halfblocks,
 }
 
+/// How far to rewind when resuming after a pause of a given length.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewindRule {
+    /// Applies to pauses shorter than this; `None` matches any pause
+    #[serde(default)]
+    pub paused_under_secs: Option<u64>,
+    pub rewind_secs: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Config {
     pub server_url: String,
@@ -23,6 +32,45 @@ pub struct Config {
     pub theme: ThemeName,
     #[serde(default)]
     pub image_protocol: ImageProtocol,
+    #[serde(default = "default_cache_size_mb")]
+    pub cache_size_mb: u64,
+    #[serde(default)]
+    pub output_device: Option<String>,
+    /// Integrated loudness to normalize tracks to, in LUFS. `None` disables
+    /// normalization.
+    #[serde(default = "default_loudness_target")]
+    pub loudness_target: Option<f64>,
+    /// Checked in order; the first matching rule is used.
+    #[serde(default = "default_smart_rewind")]
+    pub smart_rewind: Vec<RewindRule>,
+    /// Start the next book in a series when one finishes instead of asking.
+    #[serde(default)]
+    pub series_auto_advance: bool,
+}
+
+fn default_cache_size_mb() -> u64 {
+    2048
+}
+
+fn default_loudness_target() -> Option<f64> {
+    Some(-18.0)
+}
+
+fn default_smart_rewind() -> Vec<RewindRule> {
+    vec![
+        RewindRule {
+            paused_under_secs: Some(60),
+            rewind_secs: 0.0,
+        },
+        RewindRule {
+            paused_under_secs: Some(3600),
+            rewind_secs: 10.0,
+        },
+        RewindRule {
+            paused_under_secs: None,
+            rewind_secs: 30.0,
+        },
+    ]
 }
 
 impl Default for Config {
@@ -32,6 +80,11 @@ impl Default for Config {
             api_key: "".to_string(),
             theme: ThemeName::default(),
             image_protocol: ImageProtocol::default(),
+            cache_size_mb: default_cache_size_mb(),
+            output_device: None,
+            loudness_target: default_loudness_target(),
+            smart_rewind: default_smart_rewind(),
+            series_auto_advance: false,
         }
     }
 }
@@ -63,6 +116,13 @@ pub fn load_or_create_config() -> Result<Config> {
         eprintln!("  api_key: Your Audiobookshelf API key");
         eprintln!("  theme: tokyo_night, catppuccin_mocha, gruvbox, kanagawa, hackerman");
         eprintln!("  image_protocol: auto, sixel, kitty, iterm2, halfblocks");
+        eprintln!("  cache_size_mb: Disk space for cached audio (default 2048)");
+        eprintln!("  output_device: Audio output device name (default: system default)");
+        eprintln!(
+            "  loudness_target: Loudness to normalize to in LUFS, null to disable (default -18)"
+        );
+        eprintln!("  smart_rewind: Seconds to rewind on resume, by how long playback was paused");
+        eprintln!("  series_auto_advance: Start the next book in a series without asking");
         anyhow::bail!("\nConfig file not configured. Please set your API key and server URL.");
     }
 
@@ -1,8 +1,11 @@
-use crate::player::commands::PlayerState;
+use crate::api::models::LibraryItem;
+use crate::api::query::LibraryQuery;
+use crate::player::commands::{AudioSource, PlayerState};
 use crossterm::event::{KeyEvent, MouseEvent};
-use std::{path::PathBuf, time::Duration};
+use std::time::Duration;
 
 #[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
 pub enum AppEvent {
     // From input thread
     Input(KeyEvent),
@@ -14,16 +17,51 @@ pub enum AppEvent {
     PositionUpdate(Duration),
     DurationChanged(Duration),
     TrackEnded,
+    /// Playback paused at the position given with `PlayerCommand::StopAt`
+    StopReached,
+    /// The player moved on to the next queued track
+    TrackChanged(TrackInfo),
     PlayerError(String),
+    /// Available output devices and the one selected, `None` being the default
+    OutputDevices(Vec<String>, Option<String>),
+    OutputDeviceChanged(String),
+    /// Total time cut from pauses by silence trimming
+    TimeSaved(Duration),
 
     // From API thread
     LibrariesLoaded(Vec<crate::api::models::Library>),
-    ItemsLoaded(Vec<crate::api::models::LibraryItem>),
+    ItemsLoaded(ItemsPage),
+    /// Series of the given library
+    SeriesLoaded(String, Vec<crate::api::models::Series>),
+    AuthorsLoaded(String, Vec<crate::api::models::LibraryAuthor>),
+    NarratorsLoaded(String, Vec<crate::api::models::Narrator>),
+    /// Books in the given filter group and value
+    GroupBooksLoaded(String, String, Vec<crate::api::models::LibraryItem>),
+    CollectionsLoaded(Vec<crate::api::models::Collection>),
+    PlaylistsLoaded(Vec<crate::api::models::Playlist>),
+    ProgressLoaded(Vec<crate::api::models::MediaProgress>),
+    /// Filter values of the given library
+    FilterDataLoaded(String, crate::api::models::LibraryFilterData),
+    /// Server search results for the given query
+    SearchResults(String, Vec<crate::api::models::LibraryItem>),
     ChaptersLoaded(Vec<crate::api::models::Chapter>),
+    /// Bookmarks of the given item
+    BookmarksLoaded(String, Vec<crate::api::models::Bookmark>),
 
-    DownloadFinished(PathBuf, f64, TrackInfo),
+    PlaybackReady(AudioSource, f64, TrackInfo),
+    NextTrackReady(String, AudioSource, TrackInfo),
+    DownloadProgress(DownloadProgress),
+    DownloadFinished(DownloadProgress),
+    DownloadFailed(DownloadProgress, String),
+    OfflineDownloadFinished(String),
+    OfflineDownloadFailed(String, String),
+    OfflineItemsChanged(Vec<String>),
+    OfflineModeEntered(Vec<crate::api::models::LibraryItem>),
+    ServerReachable,
 
     ContinueListeningLoaded(crate::api::models::LibraryItem, f64),
+    /// Personalized shelves of the given library
+    ShelvesLoaded(String, Vec<crate::api::models::PersonalizedShelf>),
 
     ApiError(String),
 }
@@ -44,3 +82,34 @@ impl TrackInfo {
         }
     }
 }
+
+/// One page of a library's items, as listed with `query`.
+#[derive(Debug, Clone)]
+pub struct ItemsPage {
+    pub library_id: String,
+    pub query: LibraryQuery,
+    pub page: usize,
+    pub items: Vec<LibraryItem>,
+    /// Items in the whole list, across all pages
+    pub total: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct DownloadProgress {
+    pub item_id: String,
+    /// `None` when downloading a whole book for offline use
+    pub track_index: Option<i32>,
+    pub bytes_done: u64,
+    pub bytes_total: u64,
+    pub bytes_per_sec: f64,
+}
+
+impl DownloadProgress {
+    pub fn ratio(&self) -> f64 {
+        if self.bytes_total > 0 {
+            (self.bytes_done as f64 / self.bytes_total as f64).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+}
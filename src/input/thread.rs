@@ -11,10 +11,10 @@ pub fn spawn(event_tx: mpsc::Sender<AppEvent>) -> JoinHandle<()> {
         loop {
             match event::read() {
                 Ok(Event::Key(key_event)) => {
-                    if key_event.kind == KeyEventKind::Press {
-                        if event_tx.send(AppEvent::Input(key_event)).is_err() {
-                            break;
-                        }
+                    if key_event.kind == KeyEventKind::Press
+                        && event_tx.send(AppEvent::Input(key_event)).is_err()
+                    {
+                        break;
                     }
                 }
                 Ok(Event::Mouse(mouse_event)) => {
@@ -1,4 +1,4 @@
-use std::{io, process::exit, sync::mpsc, time::Duration};
+use std::{collections::HashSet, io, process::exit, sync::mpsc, time::Duration};
 
 use crate::{
     api::thread::ApiCommand,
@@ -10,10 +10,13 @@ use crate::{
 
 mod api;
 mod app;
+mod cache;
 mod config;
 mod events;
 mod input;
+mod offline;
 mod player;
+mod prefs;
 mod ui;
 
 fn main() -> io::Result<()> {
@@ -34,22 +37,40 @@ fn main() -> io::Result<()> {
     let (api_cmd_tx, api_cmd_rx) = mpsc::channel::<ApiCommand>();
 
     let _input_handle = input::thread::spawn(event_tx.clone());
-    let _player_handle = player::thread::spawn(player_cmd_rx, event_tx.clone());
-    let _api_handle = api::thread::spawn(config.clone(), api_cmd_rx, event_tx.clone());
+    let _player_handle = player::thread::spawn(
+        player_cmd_rx,
+        event_tx.clone(),
+        config.output_device.clone(),
+        config.loudness_target,
+    );
+    let api_handle = api::thread::spawn(config.clone(), api_cmd_rx, event_tx.clone());
 
-    let mut app = App::new(player_cmd_tx, api_cmd_tx);
+    let mut app = App::new(
+        player_cmd_tx,
+        api_cmd_tx,
+        config.smart_rewind.clone(),
+        config.series_auto_advance,
+    );
     app.load_libraries();
 
     let mut image_cache = ImageCache::new(config.image_protocol);
 
     let cover_fetcher = CoverFetcher::new(config.clone());
     let mut last_item_id: Option<String> = None;
+    let mut requested_thumbnails: HashSet<String> = HashSet::new();
 
     loop {
         while let Ok(msg) = cover_fetcher.try_recv() {
             match msg {
                 CoverMessage::Loaded { item_id, data } => {
-                    if let Err(e) = image_cache.load_cover(&item_id, &data) {
+                    if requested_thumbnails.contains(&item_id)
+                        && let Err(e) = image_cache.load_thumbnail(&item_id, &data)
+                    {
+                        eprintln!("Failed to load cover: {}", e);
+                    }
+                    if last_item_id.as_ref() == Some(&item_id)
+                        && let Err(e) = image_cache.load_cover(&item_id, &data)
+                    {
                         eprintln!("Failed to load cover: {}", e);
                     }
                 }
@@ -70,7 +91,16 @@ fn main() -> io::Result<()> {
             image_cache.clear();
         }
 
+        if let Some(ref home) = app.home {
+            for item_id in home.item_ids() {
+                if requested_thumbnails.insert(item_id.to_string()) {
+                    cover_fetcher.fetch(item_id.to_string());
+                }
+            }
+        }
+
         app.loading_animation.tick();
+        app.on_tick();
         terminal.draw(|f| ui::render::render(f, &mut app, &mut image_cache))?;
 
         match event_rx.recv_timeout(Duration::from_millis(50)) {
@@ -82,16 +112,65 @@ fn main() -> io::Result<()> {
                 AppEvent::PositionUpdate(pos) => app.on_position_update(pos),
                 AppEvent::DurationChanged(dur) => app.on_duration_changed(dur),
                 AppEvent::TrackEnded => app.on_track_ended(),
+                AppEvent::StopReached => app.on_stop_reached(),
+                AppEvent::TrackChanged(track_info) => app.on_track_changed(track_info),
                 AppEvent::PlayerError(e) => app.on_player_error(e),
+                AppEvent::OutputDevices(devices, current) => {
+                    app.on_output_devices(devices, current)
+                }
+                AppEvent::OutputDeviceChanged(name) => app.on_output_device_changed(name),
+                AppEvent::TimeSaved(saved) => app.on_time_saved(saved),
                 AppEvent::LibrariesLoaded(libraries) => app.on_libraries_loaded(libraries),
-                AppEvent::ItemsLoaded(items) => app.on_items_loaded(items),
+                AppEvent::ItemsLoaded(page) => app.on_items_loaded(page),
+                AppEvent::SearchResults(query, items) => app.on_search_results(query, items),
+                AppEvent::SeriesLoaded(library_id, series) => {
+                    app.on_series_loaded(library_id, series)
+                }
+                AppEvent::AuthorsLoaded(library_id, authors) => {
+                    app.on_authors_loaded(library_id, authors)
+                }
+                AppEvent::NarratorsLoaded(library_id, narrators) => {
+                    app.on_narrators_loaded(library_id, narrators)
+                }
+                AppEvent::GroupBooksLoaded(group, value, books) => {
+                    app.on_group_books_loaded(group, value, books)
+                }
+                AppEvent::CollectionsLoaded(collections) => app.on_collections_loaded(collections),
+                AppEvent::PlaylistsLoaded(playlists) => app.on_playlists_loaded(playlists),
+                AppEvent::ProgressLoaded(progress) => app.on_progress_loaded(progress),
+                AppEvent::FilterDataLoaded(library_id, data) => {
+                    app.on_filter_data_loaded(library_id, data)
+                }
                 AppEvent::ChaptersLoaded(chapters) => app.on_chapters_loaded(chapters),
-                AppEvent::DownloadFinished(path, position, track_info) => {
-                    app.on_download_finished(path, position, track_info)
+                AppEvent::BookmarksLoaded(item_id, bookmarks) => {
+                    app.on_bookmarks_loaded(item_id, bookmarks)
+                }
+                AppEvent::PlaybackReady(source, position, track_info) => {
+                    app.on_playback_ready(source, position, track_info)
+                }
+                AppEvent::NextTrackReady(item_id, source, track_info) => {
+                    app.on_next_track_ready(item_id, source, track_info)
+                }
+                AppEvent::DownloadProgress(progress) => app.on_download_progress(progress),
+                AppEvent::DownloadFinished(progress) => app.on_download_finished(progress),
+                AppEvent::DownloadFailed(progress, error) => {
+                    app.on_download_failed(progress, error)
                 }
                 AppEvent::ContinueListeningLoaded(item, position) => {
                     app.on_continue_listening_loaded(item, position)
                 }
+                AppEvent::ShelvesLoaded(library_id, shelves) => {
+                    app.on_shelves_loaded(library_id, shelves)
+                }
+                AppEvent::OfflineDownloadFinished(item_id) => {
+                    app.on_offline_download_finished(item_id)
+                }
+                AppEvent::OfflineDownloadFailed(item_id, error) => {
+                    app.on_offline_download_failed(item_id, error)
+                }
+                AppEvent::OfflineItemsChanged(item_ids) => app.on_offline_items_changed(item_ids),
+                AppEvent::OfflineModeEntered(items) => app.on_offline_mode_entered(items),
+                AppEvent::ServerReachable => app.on_server_reachable(),
                 AppEvent::ApiError(err) => app.on_api_error(err),
             },
             Err(mpsc::RecvTimeoutError::Timeout) => {}
@@ -106,5 +185,10 @@ fn main() -> io::Result<()> {
     }
 
     ratatui::restore();
+
+    // Let the API thread close the playback session before exiting
+    drop(app);
+    let _ = api_handle.join();
+
     Ok(())
 }
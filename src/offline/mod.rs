@@ -0,0 +1,431 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::api::models::{AudioTrack, Chapter, Library, LibraryItem};
+
+pub const OFFLINE_LIBRARY_ID: &str = "decibelle-offline";
+
+const ITEM_FILE: &str = "item.json";
+const COVER_FILE: &str = "cover";
+const JOURNAL_FILE: &str = "progress_journal.json";
+
+fn data_dir() -> Result<PathBuf> {
+    Ok(dirs::data_dir()
+        .context("Could not find data directory")?
+        .join("decibelle"))
+}
+
+/// Pseudo-library listing the downloaded books while offline.
+pub fn offline_library() -> Library {
+    Library {
+        id: OFFLINE_LIBRARY_ID.to_string(),
+        name: "Downloaded".to_string(),
+        media_type: "book".to_string(),
+        display_order: None,
+        icon: None,
+        provider: None,
+        folders: None,
+        settings: None,
+        created_at: None,
+        last_update: None,
+    }
+}
+
+/// Books downloaded for offline listening.
+///
+/// Each book lives in its own directory with every audio track, the cover
+/// and the expanded item JSON. The item JSON is written last, so a book is
+/// only listed once all of its tracks are on disk.
+#[derive(Clone)]
+pub struct OfflineStore {
+    dir: PathBuf,
+}
+
+impl OfflineStore {
+    pub fn open() -> Result<Self> {
+        let dir = data_dir()?.join("offline");
+        fs::create_dir_all(&dir).context("Failed to create offline directory")?;
+        Ok(Self { dir })
+    }
+
+    pub fn item_dir(&self, item_id: &str) -> PathBuf {
+        self.dir.join(item_id)
+    }
+
+    pub fn track_file(&self, item_id: &str, track: &AudioTrack) -> PathBuf {
+        let ext = track
+            .metadata
+            .as_ref()
+            .map(|m| m.ext.trim_start_matches('.').to_string())
+            .unwrap_or_else(|| "audio".to_string());
+        self.item_dir(item_id)
+            .join(format!("{}.{}", track.index, ext))
+    }
+
+    pub fn cover_file(&self, item_id: &str) -> PathBuf {
+        self.item_dir(item_id).join(COVER_FILE)
+    }
+
+    /// Returns the local copy of `track` if it's complete.
+    ///
+    /// Tracks are downloaded to a `.part` file that only gets the track's
+    /// name once the whole body has arrived, so a file under that name is
+    /// complete. Its size is checked as well when the server reported one.
+    pub fn track_path(&self, item_id: &str, track: &AudioTrack) -> Option<PathBuf> {
+        let path = self.track_file(item_id, track);
+        let size = fs::metadata(&path).ok()?.len();
+        let expected = track.metadata.as_ref().map(|m| m.size as u64);
+
+        if expected.is_none_or(|expected| expected == size) {
+            Some(path)
+        } else {
+            None
+        }
+    }
+
+    pub fn load_item(&self, item_id: &str) -> Option<LibraryItem> {
+        let content = fs::read_to_string(self.item_dir(item_id).join(ITEM_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn load_chapters(&self, item_id: &str) -> Option<Vec<Chapter>> {
+        self.load_item(item_id)
+            .and_then(|item| item.media)
+            .map(|media| media.chapters.unwrap_or_default())
+    }
+
+    pub fn list_items(&self) -> Vec<LibraryItem> {
+        let Ok(dir) = fs::read_dir(&self.dir) else {
+            return Vec::new();
+        };
+
+        let mut items: Vec<LibraryItem> = dir
+            .flatten()
+            .filter_map(|entry| self.load_item(&entry.file_name().to_string_lossy()))
+            .collect();
+
+        items.sort_by_key(|item| {
+            item.media
+                .as_ref()
+                .and_then(|m| m.metadata.title.clone())
+                .unwrap_or_default()
+                .to_lowercase()
+        });
+        items
+    }
+
+    pub fn list_item_ids(&self) -> Vec<String> {
+        self.list_items().into_iter().map(|item| item.id).collect()
+    }
+
+    /// Marks a book as complete by writing its item JSON.
+    pub fn save_item(&self, item: &LibraryItem) -> Result<()> {
+        let json = serde_json::to_string(item).context("Failed to serialize item")?;
+        write_atomic(&self.item_dir(&item.id).join(ITEM_FILE), json.as_bytes())
+    }
+
+    pub fn save_cover(&self, item_id: &str, data: &[u8]) -> Result<()> {
+        write_atomic(&self.cover_file(item_id), data)
+    }
+
+    pub fn remove_item(&self, item_id: &str) -> Result<()> {
+        fs::remove_dir_all(self.item_dir(item_id)).context("Failed to remove offline book")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub item_id: String,
+    pub current_time: f64,
+    pub duration: f64,
+    pub is_finished: bool,
+    pub recorded_at: i64,
+}
+
+/// What happened to a journal entry when pushing it to the server.
+pub enum SyncOutcome {
+    Sent,
+    /// The server already has newer progress from another device
+    Superseded,
+    /// The server refused the update; retrying won't help
+    Rejected,
+    Retry,
+}
+
+const RETRY_BASE: Duration = Duration::from_secs(5);
+const RETRY_MAX: Duration = Duration::from_secs(300);
+
+/// Durable outbox of progress updates.
+///
+/// Every update is written to disk before it is pushed, so nothing is lost
+/// if the server is down or the app crashes. Only the latest update per
+/// item is kept, and failed pushes are retried with exponential backoff.
+pub struct ProgressJournal {
+    path: PathBuf,
+    entries: Vec<JournalEntry>,
+    failures: u32,
+    next_attempt: Option<Instant>,
+}
+
+impl ProgressJournal {
+    pub fn open() -> Result<Self> {
+        Self::open_in(data_dir()?)
+    }
+
+    fn open_in(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir).context("Failed to create data directory")?;
+        let path = dir.join(JOURNAL_FILE);
+
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            path,
+            entries,
+            failures: 0,
+            next_attempt: None,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn record(&mut self, item_id: &str, current_time: f64, duration: f64, is_finished: bool) {
+        let entry = JournalEntry {
+            item_id: item_id.to_string(),
+            current_time,
+            duration,
+            is_finished,
+            recorded_at: now_ms(),
+        };
+
+        match self.entries.iter_mut().find(|e| e.item_id == item_id) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        self.save();
+    }
+
+    pub fn latest(&self) -> Option<&JournalEntry> {
+        self.entries.iter().max_by_key(|e| e.recorded_at)
+    }
+
+    /// True when there are entries and the backoff has elapsed.
+    pub fn is_due(&self) -> bool {
+        !self.entries.is_empty() && self.next_attempt.is_none_or(|t| Instant::now() >= t)
+    }
+
+    /// Time left until the next retry, if one is scheduled.
+    pub fn time_until_due(&self) -> Option<Duration> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        Some(
+            self.next_attempt
+                .map(|t| t.saturating_duration_since(Instant::now()))
+                .unwrap_or(Duration::ZERO),
+        )
+    }
+
+    /// Pushes entries in order with `send`. Stops at the first entry that
+    /// needs a retry and backs off. Returns true once the journal is empty.
+    pub fn flush(&mut self, mut send: impl FnMut(&JournalEntry) -> SyncOutcome) -> bool {
+        let mut done = 0;
+        let mut retry = false;
+        for entry in &self.entries {
+            match send(entry) {
+                SyncOutcome::Sent => {}
+                SyncOutcome::Superseded => {
+                    eprintln!("Dropping stale progress for {}", entry.item_id);
+                }
+                SyncOutcome::Rejected => {
+                    eprintln!("Server rejected progress for {}", entry.item_id);
+                }
+                SyncOutcome::Retry => {
+                    retry = true;
+                    break;
+                }
+            }
+            done += 1;
+        }
+
+        if done > 0 {
+            self.entries.drain(..done);
+            self.save();
+        }
+
+        if retry {
+            let backoff = RETRY_BASE
+                .saturating_mul(1 << self.failures.min(6))
+                .min(RETRY_MAX);
+            self.failures += 1;
+            self.next_attempt = Some(Instant::now() + backoff);
+        } else {
+            self.failures = 0;
+            self.next_attempt = None;
+        }
+
+        self.entries.is_empty()
+    }
+
+    fn save(&self) {
+        let result = serde_json::to_string(&self.entries)
+            .context("Failed to serialize journal")
+            .and_then(|json| write_atomic(&self.path, json.as_bytes()));
+        if let Err(e) = result {
+            eprintln!("Failed to save progress journal: {:?}", e);
+        }
+    }
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).context("Failed to create directory")?;
+    }
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).context("Failed to write file")?;
+    fs::rename(&tmp, path).context("Failed to write file")?;
+    Ok(())
+}
+
+pub fn now_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Data directory under the system temp dir, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = std::env::temp_dir().join(format!(
+                "decibelle-journal-{}-{}",
+                name,
+                std::process::id()
+            ));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn item_ids(journal: &ProgressJournal) -> Vec<&str> {
+        journal.entries.iter().map(|e| e.item_id.as_str()).collect()
+    }
+
+    #[test]
+    fn keeps_latest_update_per_item_across_reopening() {
+        let dir = TempDir::new("record");
+        let mut journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        journal.record("a", 10.0, 100.0, false);
+        journal.record("b", 20.0, 100.0, false);
+        journal.record("a", 30.0, 100.0, false);
+        drop(journal);
+
+        let journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        assert_eq!(item_ids(&journal), vec!["a", "b"]);
+        assert_eq!(journal.entries[0].current_time, 30.0);
+        assert!(journal.is_due());
+    }
+
+    #[test]
+    fn sent_entries_are_removed() {
+        let dir = TempDir::new("sent");
+        let mut journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        journal.record("a", 10.0, 100.0, false);
+        journal.record("b", 100.0, 100.0, true);
+
+        let mut sent = Vec::new();
+        assert!(journal.flush(|entry| {
+            sent.push(entry.item_id.clone());
+            SyncOutcome::Sent
+        }));
+        assert_eq!(sent, vec!["a", "b"]);
+        assert!(!journal.is_due());
+        assert_eq!(journal.time_until_due(), None);
+
+        let journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn superseded_and_rejected_entries_are_dropped() {
+        let dir = TempDir::new("dropped");
+        let mut journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        journal.record("a", 10.0, 100.0, false);
+        journal.record("b", 20.0, 100.0, false);
+
+        assert!(journal.flush(|entry| match entry.item_id.as_str() {
+            "a" => SyncOutcome::Superseded,
+            _ => SyncOutcome::Rejected,
+        }));
+        assert!(journal.is_empty());
+    }
+
+    #[test]
+    fn retry_keeps_remaining_entries_and_backs_off() {
+        let dir = TempDir::new("retry");
+        let mut journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        journal.record("a", 10.0, 100.0, false);
+        journal.record("b", 20.0, 100.0, false);
+        journal.record("c", 30.0, 100.0, false);
+
+        let mut attempts = 0;
+        assert!(!journal.flush(|entry| {
+            attempts += 1;
+            match entry.item_id.as_str() {
+                "a" => SyncOutcome::Sent,
+                _ => SyncOutcome::Retry,
+            }
+        }));
+        // Entries after the failed one aren't tried
+        assert_eq!(attempts, 2);
+        assert_eq!(item_ids(&journal), vec!["b", "c"]);
+        assert!(!journal.is_due());
+        let wait = journal.time_until_due().unwrap();
+        assert!(wait > RETRY_BASE / 2 && wait <= RETRY_BASE);
+
+        // Each further failure doubles the wait
+        journal.flush(|_| SyncOutcome::Retry);
+        let wait = journal.time_until_due().unwrap();
+        assert!(wait > RETRY_BASE && wait <= RETRY_BASE * 2);
+
+        let journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        assert_eq!(item_ids(&journal), vec!["b", "c"]);
+    }
+
+    #[test]
+    fn backoff_is_capped_and_reset_by_success() {
+        let dir = TempDir::new("backoff");
+        let mut journal = ProgressJournal::open_in(dir.0.clone()).unwrap();
+        journal.record("a", 10.0, 100.0, false);
+
+        for _ in 0..10 {
+            journal.flush(|_| SyncOutcome::Retry);
+        }
+        let wait = journal.time_until_due().unwrap();
+        assert!(wait > RETRY_MAX / 2 && wait <= RETRY_MAX);
+
+        assert!(journal.flush(|_| SyncOutcome::Sent));
+        journal.record("a", 20.0, 100.0, false);
+        assert!(journal.is_due());
+        assert_eq!(journal.time_until_due(), Some(Duration::ZERO));
+    }
+}
@@ -1,15 +1,64 @@
 use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::events::types::TrackInfo;
+
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub enum PlayerCommand {
-    Play { path: PathBuf, position: Duration },
+    /// Plays a track from `position`, relative to the start of the track.
+    /// Clears the queue.
+    Play {
+        source: AudioSource,
+        track: TrackInfo,
+        position: Duration,
+    },
+    /// Queues a track to play gaplessly after the current one.
+    Enqueue {
+        source: AudioSource,
+        track: TrackInfo,
+    },
     Pause,
     Resume,
     Stop,
     Seek(Duration),
     SetSpeed(f32),
+    /// Linear volume, 1.0 being unchanged
+    SetVolume(f32),
+    Mute(bool),
+    /// Turns the speech EQ and compressor on or off.
+    SetVoiceBoost(bool),
+    /// Turns shortening of pauses on or off.
+    SetTrimSilence(bool),
+    /// Pauses once the audio heard reaches this book position, rather than
+    /// the decoder, which runs seconds ahead. Cleared by `Play` and `Stop`.
+    StopAt(Option<Duration>),
+    /// Fades the volume out over the given time, then pauses.
+    FadeOut(Duration),
+    CancelFade,
+    ListOutputDevices,
+    /// Moves playback to the named device, or the system default for `None`.
+    SetOutputDevice(Option<String>),
+}
+
+/// Where the player reads audio from.
+#[derive(Debug, Clone)]
+pub enum AudioSource {
+    File(PathBuf),
+    Stream {
+        url: String,
+        api_key: String,
+        extension: Option<String>,
+    },
+    /// A track being downloaded to `path`, played from what's written so
+    /// far and streamed from `url` beyond that.
+    Downloading {
+        path: PathBuf,
+        len: u64,
+        url: String,
+        api_key: String,
+        extension: Option<String>,
+    },
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
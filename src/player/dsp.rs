@@ -0,0 +1,235 @@
+use std::f64::consts::PI;
+
+/// A processing stage in the player's effect chain.
+///
+/// Effects work in place on interleaved samples at the decoded sample rate
+/// and are rebuilt when the decoded format changes.
+pub trait Effect: Send {
+    fn process(&mut self, samples: &mut [f32]);
+
+    /// Drops internal state, e.g. after a seek.
+    fn reset(&mut self);
+}
+
+/// Effects applied in order to decoded audio.
+#[derive(Default)]
+pub struct EffectChain {
+    effects: Vec<Box<dyn Effect>>,
+}
+
+impl EffectChain {
+    /// Speech EQ followed by a compressor, for listening in noisy places.
+    pub fn voice_boost(channels: usize, rate: u32) -> Self {
+        let mut chain = Self::default();
+        chain.push(SpeechEq::new(channels, rate));
+        chain.push(Compressor::new(channels, rate));
+        chain
+    }
+
+    pub fn push(&mut self, effect: impl Effect + 'static) {
+        self.effects.push(Box::new(effect));
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for effect in &mut self.effects {
+            effect.process(samples);
+        }
+    }
+
+    pub fn reset(&mut self) {
+        for effect in &mut self.effects {
+            effect.reset();
+        }
+    }
+}
+
+/// Second-order IIR filter, direct form I.
+#[derive(Clone, Copy, Default)]
+pub struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    /// Coefficients normalized so that a0 is 1.
+    pub fn new(b0: f64, b1: f64, b2: f64, a1: f64, a2: f64) -> Self {
+        Self {
+            b0,
+            b1,
+            b2,
+            a1,
+            a2,
+            ..Default::default()
+        }
+    }
+
+    pub fn high_pass(rate: u32, freq: f64, q: f64) -> Self {
+        let (cos, alpha) = rbj_params(rate, freq, q);
+        let a0 = 1.0 + alpha;
+        Self::new(
+            (1.0 + cos) / 2.0 / a0,
+            -(1.0 + cos) / a0,
+            (1.0 + cos) / 2.0 / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha) / a0,
+        )
+    }
+
+    pub fn peaking(rate: u32, freq: f64, q: f64, gain_db: f64) -> Self {
+        let (cos, alpha) = rbj_params(rate, freq, q);
+        let a = 10f64.powf(gain_db / 40.0);
+        let a0 = 1.0 + alpha / a;
+        Self::new(
+            (1.0 + alpha * a) / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha * a) / a0,
+            -2.0 * cos / a0,
+            (1.0 - alpha / a) / a0,
+        )
+    }
+
+    pub fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
+            - self.a1 * self.y1
+            - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+
+    pub fn reset(&mut self) {
+        self.x1 = 0.0;
+        self.x2 = 0.0;
+        self.y1 = 0.0;
+        self.y2 = 0.0;
+    }
+}
+
+/// Cosine of the centre frequency and alpha, from the Audio EQ Cookbook.
+fn rbj_params(rate: u32, freq: f64, q: f64) -> (f64, f64) {
+    // Keep the centre below Nyquist for low sample rates
+    let freq = freq.min(rate as f64 * 0.45);
+    let w0 = 2.0 * PI * freq / rate as f64;
+    (w0.cos(), w0.sin() / (2.0 * q))
+}
+
+/// Cuts rumble and boominess and lifts the band where consonants sit, which
+/// keeps voices intelligible over background noise.
+pub struct SpeechEq {
+    channels: usize,
+    filters: Vec<[Biquad; 3]>,
+}
+
+impl SpeechEq {
+    pub fn new(channels: usize, rate: u32) -> Self {
+        let bands = [
+            Biquad::high_pass(rate, 90.0, std::f64::consts::FRAC_1_SQRT_2),
+            Biquad::peaking(rate, 300.0, 1.0, -2.0),
+            Biquad::peaking(rate, 2800.0, 0.8, 5.0),
+        ];
+        let channels = channels.max(1);
+        Self {
+            channels,
+            filters: vec![bands; channels],
+        }
+    }
+}
+
+impl Effect for SpeechEq {
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            for (sample, bands) in frame.iter_mut().zip(&mut self.filters) {
+                let mut y = *sample as f64;
+                for band in bands.iter_mut() {
+                    y = band.process(y);
+                }
+                *sample = y as f32;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        for band in self.filters.iter_mut().flatten() {
+            band.reset();
+        }
+    }
+}
+
+const COMPRESSOR_THRESHOLD_DB: f32 = -30.0;
+const COMPRESSOR_RATIO: f32 = 3.0;
+const COMPRESSOR_KNEE_DB: f32 = 6.0;
+const COMPRESSOR_MAKEUP_DB: f32 = 8.0;
+const COMPRESSOR_ATTACK_MS: f32 = 10.0;
+const COMPRESSOR_RELEASE_MS: f32 = 250.0;
+
+/// Feed-forward RMS compressor with a soft knee. Quiet passages come up
+/// through the makeup gain while loud ones are held back, with all channels
+/// sharing one gain so the stereo image stays put.
+pub struct Compressor {
+    channels: usize,
+    attack: f32,
+    release: f32,
+    // Smoothed mean square of the loudest channel
+    envelope: f32,
+}
+
+impl Compressor {
+    pub fn new(channels: usize, rate: u32) -> Self {
+        let coeff = |ms: f32| 1.0 - (-1000.0 / (ms * rate as f32)).exp();
+        Self {
+            channels: channels.max(1),
+            attack: coeff(COMPRESSOR_ATTACK_MS),
+            release: coeff(COMPRESSOR_RELEASE_MS),
+            envelope: 0.0,
+        }
+    }
+
+    /// Gain reduction in dB for a detected level.
+    fn reduction_db(level_db: f32) -> f32 {
+        let over = level_db - COMPRESSOR_THRESHOLD_DB;
+        let slope = 1.0 - 1.0 / COMPRESSOR_RATIO;
+        let half_knee = COMPRESSOR_KNEE_DB / 2.0;
+
+        if over <= -half_knee {
+            0.0
+        } else if over < half_knee {
+            slope * (over + half_knee).powi(2) / (2.0 * COMPRESSOR_KNEE_DB)
+        } else {
+            slope * over
+        }
+    }
+}
+
+impl Effect for Compressor {
+    fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let power = frame.iter().fold(0.0f32, |max, s| max.max(s * s));
+            let coeff = if power > self.envelope {
+                self.attack
+            } else {
+                self.release
+            };
+            self.envelope += (power - self.envelope) * coeff;
+
+            let level_db = 10.0 * self.envelope.max(1e-10).log10();
+            let gain_db = COMPRESSOR_MAKEUP_DB - Self::reduction_db(level_db);
+            let gain = 10f32.powf(gain_db / 20.0);
+            for sample in frame.iter_mut() {
+                *sample *= gain;
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
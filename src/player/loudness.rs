@@ -0,0 +1,383 @@
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+
+use crate::player::dsp::Biquad;
+
+/// Loudest the normalizer will boost a quiet track.
+const MAX_BOOST_DB: f64 = 12.0;
+/// Seconds of gated audio the running estimate needs before it's trusted.
+const MIN_ESTIMATE_SECS: f64 = 3.0;
+/// Time constant for gain changes while the estimate settles.
+const GAIN_SMOOTHING_SECS: f64 = 3.0;
+
+const LIMITER_CEILING_DB: f32 = -1.0;
+const LIMITER_LOOKAHEAD_MS: usize = 5;
+const LIMITER_RELEASE_MS: f32 = 150.0;
+
+/// The two K-weighting filters from ITU-R BS.1770, for any sample rate.
+fn k_weighting(rate: u32) -> [Biquad; 2] {
+    let rate = rate as f64;
+
+    // High shelf modelling the acoustic effect of the head
+    let k = (PI * 1681.974450955533 / rate).tan();
+    let q = 0.7071752369554196;
+    let vh = 10f64.powf(3.999843853973347 / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+    let shelf = Biquad::new(
+        (vh + vb * k / q + k * k) / a0,
+        2.0 * (k * k - vh) / a0,
+        (vh - vb * k / q + k * k) / a0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    );
+
+    // High pass
+    let k = (PI * 38.13547087602444 / rate).tan();
+    let q = 0.5003270373238773;
+    let a0 = 1.0 + k / q + k * k;
+    let high_pass = Biquad::new(
+        1.0,
+        -2.0,
+        1.0,
+        2.0 * (k * k - 1.0) / a0,
+        (1.0 - k / q + k * k) / a0,
+    );
+
+    [shelf, high_pass]
+}
+
+/// Integrated loudness meter following EBU R128.
+///
+/// Works on interleaved samples. Mean square power is collected in 100 ms
+/// steps; every step closes a 400 ms block, and the integrated value gates
+/// out silent blocks and blocks more than 10 LU below the average.
+pub struct LoudnessMeter {
+    channels: usize,
+    filters: Vec<[Biquad; 2]>,
+    weights: Vec<f64>,
+
+    step_frames: usize,
+    step_pos: usize,
+    step_power: f64,
+    recent_steps: VecDeque<f64>,
+    blocks: Vec<f64>,
+}
+
+impl LoudnessMeter {
+    pub fn new(channels: usize, rate: u32) -> Self {
+        let channels = channels.max(1);
+
+        // LFE is left out and surround channels count 1.41 times
+        let weights = (0..channels)
+            .map(|ch| match ch {
+                3 if channels > 4 => 0.0,
+                4 | 5 => 1.41,
+                _ => 1.0,
+            })
+            .collect();
+
+        Self {
+            channels,
+            filters: vec![k_weighting(rate); channels],
+            weights,
+            step_frames: (rate as usize / 10).max(1),
+            step_pos: 0,
+            step_power: 0.0,
+            recent_steps: VecDeque::with_capacity(4),
+            blocks: Vec::new(),
+        }
+    }
+
+    pub fn process(&mut self, samples: &[f32]) {
+        for frame in samples.chunks_exact(self.channels) {
+            for (ch, &sample) in frame.iter().enumerate() {
+                let [shelf, high_pass] = &mut self.filters[ch];
+                let y = high_pass.process(shelf.process(sample as f64));
+                self.step_power += self.weights[ch] * y * y;
+            }
+
+            self.step_pos += 1;
+            if self.step_pos == self.step_frames {
+                self.finish_step();
+            }
+        }
+    }
+
+    fn finish_step(&mut self) {
+        if self.recent_steps.len() == 4 {
+            self.recent_steps.pop_front();
+        }
+        self.recent_steps
+            .push_back(self.step_power / self.step_frames as f64);
+        self.step_pos = 0;
+        self.step_power = 0.0;
+
+        if self.recent_steps.len() == 4 {
+            self.blocks
+                .push(self.recent_steps.iter().sum::<f64>() / 4.0);
+        }
+    }
+
+    /// Seconds of audio measured so far.
+    pub fn measured_secs(&self) -> f64 {
+        self.blocks.len() as f64 / 10.0
+    }
+
+    /// Integrated loudness in LUFS, or `None` for silence.
+    pub fn integrated(&self) -> Option<f64> {
+        let absolute = power_of(-70.0);
+        let (sum, count) = gated_mean(&self.blocks, absolute)?;
+        let relative = power_of(loudness_of(sum / count as f64) - 10.0);
+        let (sum, count) = gated_mean(&self.blocks, absolute.max(relative))?;
+        Some(loudness_of(sum / count as f64))
+    }
+}
+
+fn gated_mean(blocks: &[f64], gate: f64) -> Option<(f64, usize)> {
+    let (sum, count) = blocks
+        .iter()
+        .filter(|&&power| power > gate)
+        .fold((0.0, 0), |(sum, count), power| (sum + power, count + 1));
+    (count > 0).then_some((sum, count))
+}
+
+fn loudness_of(power: f64) -> f64 {
+    -0.691 + 10.0 * power.log10()
+}
+
+fn power_of(loudness: f64) -> f64 {
+    10f64.powf((loudness + 0.691) / 10.0)
+}
+
+/// Applies gain that brings a track to the target loudness.
+///
+/// Uses the track's measured loudness when known. Until then a running
+/// measurement of what has been played so far stands in, and the gain
+/// moves smoothly as that estimate settles.
+pub struct Normalizer {
+    target: f64,
+    rate: u32,
+    measured: Option<f64>,
+    meter: LoudnessMeter,
+    gain_db: f64,
+    frames_processed: u64,
+}
+
+impl Normalizer {
+    /// `gain_db` is where to start, usually the gain of the previous track.
+    pub fn new(channels: usize, rate: u32, target: f64, gain_db: f64) -> Self {
+        Self {
+            target,
+            rate,
+            measured: None,
+            meter: LoudnessMeter::new(channels, rate),
+            gain_db,
+            frames_processed: 0,
+        }
+    }
+
+    pub fn gain_db(&self) -> f64 {
+        self.gain_db
+    }
+
+    pub fn set_measured(&mut self, loudness: f64) {
+        self.measured = Some(loudness);
+        // Nothing audible yet, so jump straight there
+        if self.frames_processed < self.rate as u64 / 10 {
+            self.gain_db = self.target_gain_db().unwrap_or(self.gain_db);
+        }
+    }
+
+    fn target_gain_db(&self) -> Option<f64> {
+        let loudness = match self.measured {
+            Some(loudness) => loudness,
+            None if self.meter.measured_secs() >= MIN_ESTIMATE_SECS => self.meter.integrated()?,
+            None => return None,
+        };
+        Some((self.target - loudness).min(MAX_BOOST_DB))
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.meter.channels;
+        if self.measured.is_none() {
+            self.meter.process(samples);
+        }
+
+        let frames = samples.len() / channels;
+        self.frames_processed += frames as u64;
+
+        if let Some(target) = self.target_gain_db() {
+            let secs = frames as f64 / self.rate as f64;
+            let smoothing = 1.0 - (-secs / GAIN_SMOOTHING_SECS).exp();
+            self.gain_db += (target - self.gain_db) * smoothing;
+        }
+
+        let gain = 10f64.powf(self.gain_db / 20.0) as f32;
+        for sample in samples.iter_mut() {
+            *sample *= gain;
+        }
+    }
+}
+
+/// Lookahead limiter that keeps estimated true peaks below -1 dBTP.
+///
+/// Peaks between samples are estimated with cubic interpolation at four
+/// times the sample rate. Audio is delayed by the lookahead so the gain is
+/// already down when a peak arrives.
+pub struct Limiter {
+    channels: usize,
+    ceiling: f32,
+    lookahead: usize,
+    release: f32,
+    attack: f32,
+
+    history: Vec<[f32; 3]>,
+    delay: VecDeque<f32>,
+    required: VecDeque<(u64, f32)>,
+    frame: u64,
+    gain: f32,
+}
+
+impl Limiter {
+    pub fn new(channels: usize, rate: u32) -> Self {
+        let channels = channels.max(1);
+        let lookahead = (rate as usize * LIMITER_LOOKAHEAD_MS / 1000).max(1);
+        Self {
+            channels,
+            ceiling: 10f32.powf(LIMITER_CEILING_DB / 20.0),
+            lookahead,
+            release: 1.0 - (-1000.0 / (LIMITER_RELEASE_MS * rate as f32)).exp(),
+            attack: 1.0 - (-3.0 / lookahead as f32).exp(),
+            history: vec![[0.0; 3]; channels],
+            delay: VecDeque::from(vec![0.0; lookahead * channels]),
+            required: VecDeque::new(),
+            frame: 0,
+            gain: 1.0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        *self = Self {
+            history: vec![[0.0; 3]; self.channels],
+            delay: VecDeque::from(vec![0.0; self.lookahead * self.channels]),
+            required: VecDeque::new(),
+            frame: 0,
+            gain: 1.0,
+            ..*self
+        };
+    }
+
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for frame in samples.chunks_exact_mut(self.channels) {
+            let mut peak = 0.0f32;
+            for (ch, &sample) in frame.iter().enumerate() {
+                peak = peak.max(true_peak(&mut self.history[ch], sample));
+            }
+
+            // Sliding minimum of the gain each frame in the window needs
+            let needed = if peak > self.ceiling {
+                self.ceiling / peak
+            } else {
+                1.0
+            };
+            while self.required.back().is_some_and(|&(_, g)| g >= needed) {
+                self.required.pop_back();
+            }
+            self.required.push_back((self.frame, needed));
+            while self
+                .required
+                .front()
+                .is_some_and(|&(f, _)| f + (self.lookahead as u64) < self.frame)
+            {
+                self.required.pop_front();
+            }
+            self.frame += 1;
+
+            let target = self.required.front().map_or(1.0, |&(_, g)| g);
+            let coeff = if target < self.gain {
+                self.attack
+            } else {
+                self.release
+            };
+            self.gain += (target - self.gain) * coeff;
+
+            for sample in frame.iter_mut() {
+                self.delay.push_back(*sample);
+                let delayed = self.delay.pop_front().unwrap_or(0.0);
+                *sample = (delayed * self.gain).clamp(-self.ceiling, self.ceiling);
+            }
+        }
+    }
+}
+
+/// Estimates the peak between the previous sample and the one before it,
+/// given the newest `sample`, and shifts it into `history`.
+fn true_peak(history: &mut [f32; 3], sample: f32) -> f32 {
+    let [p0, p1, p2] = *history;
+    let p3 = sample;
+    *history = [p1, p2, p3];
+
+    let mut peak = p2.abs();
+    for t in [0.25f32, 0.5, 0.75] {
+        // Catmull-Rom between p1 and p2
+        let value = 0.5
+            * (2.0 * p1
+                + (-p0 + p2) * t
+                + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t * t
+                + (-p0 + 3.0 * p1 - 3.0 * p2 + p3) * t * t * t);
+        peak = peak.max(value.abs());
+    }
+    peak
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    /// Interleaved sine with the same signal on every channel.
+    fn sine(channels: usize, freq: f64, level_db: f64, secs: f64) -> Vec<f32> {
+        let amplitude = 10f64.powf(level_db / 20.0);
+        let frames = (RATE as f64 * secs) as usize;
+        (0..frames)
+            .flat_map(|i| {
+                let value = amplitude * (2.0 * PI * freq * i as f64 / RATE as f64).sin();
+                std::iter::repeat_n(value as f32, channels)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn measures_reference_sine() {
+        // EBU Tech 3341: a 1 kHz stereo sine at -23 dBFS reads -23 LUFS
+        let mut meter = LoudnessMeter::new(2, RATE);
+        meter.process(&sine(2, 997.0, -23.0, 20.0));
+
+        let loudness = meter.integrated().unwrap();
+        assert!((loudness - -23.0).abs() < 0.1, "measured {}", loudness);
+        assert!((meter.measured_secs() - 19.7).abs() < 0.01);
+    }
+
+    #[test]
+    fn gates_out_silence() {
+        let mut meter = LoudnessMeter::new(2, RATE);
+        meter.process(&vec![0.0; RATE as usize * 2 * 5]);
+        assert_eq!(meter.integrated(), None);
+
+        meter.process(&sine(2, 997.0, -23.0, 10.0));
+        let loudness = meter.integrated().unwrap();
+        assert!((loudness - -23.0).abs() < 0.1, "measured {}", loudness);
+    }
+
+    #[test]
+    fn limiter_keeps_peaks_under_ceiling() {
+        let mut limiter = Limiter::new(2, RATE);
+        let mut samples = sine(2, 997.0, 6.0, 1.0);
+        limiter.process(&mut samples);
+
+        let ceiling = 10f32.powf(LIMITER_CEILING_DB / 20.0);
+        assert!(samples.iter().all(|s| s.abs() <= ceiling));
+    }
+}
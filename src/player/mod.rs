@@ -1,2 +1,7 @@
 pub mod commands;
+pub mod dsp;
+pub mod loudness;
+pub mod resample;
+pub mod silence;
+pub mod stretch;
 pub mod thread;
@@ -0,0 +1,185 @@
+use std::f64::consts::PI;
+
+/// Input frames on each side of an output sample.
+const HALF_TAPS: usize = 16;
+/// Kernel table entries per input frame.
+const TABLE_RES: usize = 256;
+
+/// Converts decoded audio to the output device's channel count and sample
+/// rate.
+///
+/// Works on interleaved samples. Channels are mixed first, then a windowed
+/// sinc filter resamples each channel. When downsampling the cutoff is
+/// lowered to the output's Nyquist frequency to avoid aliasing.
+pub struct Converter {
+    in_channels: usize,
+    out_channels: usize,
+    // Input frames per output frame
+    step: f64,
+    kernel: Vec<f32>,
+    // Kernel table entries per input frame, scaled by the cutoff
+    kernel_scale: f64,
+
+    mixed: Vec<f32>,
+    // Frame offset of the next output sample into `mixed`
+    pos: f64,
+}
+
+impl Converter {
+    pub fn new(in_channels: usize, in_rate: u32, out_channels: usize, out_rate: u32) -> Self {
+        let step = in_rate as f64 / out_rate as f64;
+        let cutoff = (1.0 / step).min(1.0);
+
+        let kernel = (0..=HALF_TAPS * TABLE_RES)
+            .map(|i| {
+                let x = i as f64 / TABLE_RES as f64;
+                let sinc = if x == 0.0 {
+                    1.0
+                } else {
+                    (PI * x).sin() / (PI * x)
+                };
+                let blackman = 0.42
+                    + 0.5 * (PI * x / HALF_TAPS as f64).cos()
+                    + 0.08 * (2.0 * PI * x / HALF_TAPS as f64).cos();
+                (cutoff * sinc * blackman) as f32
+            })
+            .collect();
+
+        let mut converter = Self {
+            in_channels: in_channels.max(1),
+            out_channels: out_channels.max(1),
+            step,
+            kernel,
+            kernel_scale: cutoff * TABLE_RES as f64,
+            mixed: Vec::new(),
+            pos: 0.0,
+        };
+        converter.reset();
+        converter
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        self.in_channels == self.out_channels && self.step == 1.0
+    }
+
+    pub fn reset(&mut self) {
+        self.mixed.clear();
+        // Pad with silence so the first output sample has full context
+        self.mixed.resize(self.reach() * self.out_channels, 0.0);
+        self.pos = self.reach() as f64;
+    }
+
+    /// Converts `input` and appends the result to `output`.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        if self.is_bypassed() {
+            output.extend_from_slice(input);
+            return;
+        }
+
+        let mut frame = vec![0.0; self.out_channels];
+        for chunk in input.chunks_exact(self.in_channels) {
+            mix_frame(chunk, &mut frame);
+            self.mixed.extend_from_slice(&frame);
+        }
+
+        if self.step == 1.0 {
+            output.append(&mut self.mixed);
+            return;
+        }
+
+        let reach = self.reach();
+        let frames = self.mixed.len() / self.out_channels;
+        while self.pos + (reach as f64) < frames as f64 {
+            let center = self.pos.floor() as usize;
+            let frac = self.pos - center as f64;
+
+            for ch in 0..self.out_channels {
+                let mut sum = 0.0;
+                for k in center + 1 - reach..=center + reach {
+                    let distance = (k as f64 - center as f64 - frac).abs();
+                    sum += self.mixed[k * self.out_channels + ch] * self.tap(distance);
+                }
+                output.push(sum);
+            }
+
+            self.pos += self.step;
+        }
+
+        // Drop frames that no future output sample reaches
+        let consumed = (self.pos.floor() as usize).saturating_sub(reach);
+        if consumed > 0 {
+            self.mixed.drain(..consumed * self.out_channels);
+            self.pos -= consumed as f64;
+        }
+    }
+
+    /// Input frames on each side of an output sample the kernel spans.
+    fn reach(&self) -> usize {
+        if self.step == 1.0 {
+            0
+        } else {
+            (HALF_TAPS as f64 * self.step.max(1.0)).ceil() as usize
+        }
+    }
+
+    fn tap(&self, distance: f64) -> f32 {
+        let index = distance * self.kernel_scale;
+        let i = index as usize;
+        if i + 1 >= self.kernel.len() {
+            return 0.0;
+        }
+        let t = (index - i as f64) as f32;
+        self.kernel[i] * (1.0 - t) + self.kernel[i + 1] * t
+    }
+}
+
+/// Mixes one interleaved frame into `output`'s channel count. Channel order
+/// follows symphonia: front left, front right, centre, LFE, then surrounds.
+fn mix_frame(input: &[f32], output: &mut [f32]) {
+    const CENTER: f32 = std::f32::consts::FRAC_1_SQRT_2;
+
+    match (input.len(), output.len()) {
+        (i, o) if i == o => output.copy_from_slice(input),
+        (1, _) => {
+            output.fill(0.0);
+            let n = output.len().min(2);
+            output[..n].fill(input[0]);
+        }
+        (2, 1) => output[0] = (input[0] + input[1]) * 0.5,
+        (i, o) if i > 2 && o <= 2 => {
+            // Downmix to stereo: centre and surrounds at -3 dB, LFE dropped
+            let mut left = input[0];
+            let mut right = input[1];
+            let mut weight = 1.0;
+            if let Some(&center) = input.get(2) {
+                left += center * CENTER;
+                right += center * CENTER;
+                weight += CENTER;
+            }
+            for (n, &sample) in input.iter().enumerate().skip(4) {
+                if n % 2 == 0 {
+                    left += sample * CENTER;
+                } else {
+                    right += sample * CENTER;
+                }
+            }
+            if input.len() > 4 {
+                weight += CENTER;
+            }
+            left /= weight;
+            right /= weight;
+
+            if o == 1 {
+                output[0] = (left + right) * 0.5;
+            } else {
+                output[0] = left;
+                output[1] = right;
+            }
+        }
+        _ => {
+            output.fill(0.0);
+            let n = input.len().min(output.len());
+            output[..n].copy_from_slice(&input[..n]);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+/// Length of the windows audio is classified in.
+const WINDOW_SECS: f64 = 0.01;
+/// Windows quieter than this count as silence.
+const THRESHOLD_DB: f32 = -50.0;
+/// How much of each pause is kept so speech still breathes.
+const KEEP_SECS: f64 = 0.3;
+
+/// Shortens pauses in speech.
+///
+/// Works on interleaved samples in short windows. Once a run of silent
+/// windows is longer than `KEEP_SECS`, further silent windows are dropped
+/// until sound returns. Cuts only happen between windows that are already
+/// near silent, so they aren't audible.
+pub struct SilenceTrimmer {
+    channels: usize,
+    rate: u32,
+    window: usize,
+    keep: usize,
+    threshold: f32,
+
+    pending: Vec<f32>,
+    silent_frames: usize,
+    trimmed_frames: u64,
+}
+
+impl SilenceTrimmer {
+    pub fn new(channels: usize, rate: u32) -> Self {
+        let channels = channels.max(1);
+        Self {
+            channels,
+            rate,
+            window: ((rate as f64 * WINDOW_SECS) as usize).max(1),
+            keep: (rate as f64 * KEEP_SECS) as usize,
+            threshold: 10f32.powf(THRESHOLD_DB / 10.0),
+            pending: Vec::new(),
+            silent_frames: 0,
+            trimmed_frames: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.silent_frames = 0;
+    }
+
+    /// Trims `input` and appends the result to `output`. Audio shorter than
+    /// a window is held back until more arrives.
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        self.pending.extend_from_slice(input);
+
+        let window_len = self.window * self.channels;
+        let windows = self.pending.len() / window_len;
+        for window in self.pending.chunks_exact(window_len) {
+            let power = window.iter().map(|s| s * s).sum::<f32>() / window.len() as f32;
+            if power < self.threshold {
+                self.silent_frames += self.window;
+            } else {
+                self.silent_frames = 0;
+            }
+
+            if self.silent_frames > self.keep {
+                self.trimmed_frames += self.window as u64;
+            } else {
+                output.extend_from_slice(window);
+            }
+        }
+        self.pending.drain(..windows * window_len);
+    }
+
+    /// Seconds of audio dropped since the last call.
+    pub fn take_trimmed_secs(&mut self) -> f64 {
+        let secs = self.trimmed_frames as f64 / self.rate as f64;
+        self.trimmed_frames = 0;
+        secs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    fn tone(secs: f64) -> Vec<f32> {
+        let frames = (RATE as f64 * secs) as usize;
+        (0..frames)
+            .map(|i| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / RATE as f32).sin())
+            .collect()
+    }
+
+    fn silence(secs: f64) -> Vec<f32> {
+        vec![0.0; (RATE as f64 * secs) as usize]
+    }
+
+    /// Runs `input` through the trimmer in uneven chunks.
+    fn trim(trimmer: &mut SilenceTrimmer, input: &[f32]) -> Vec<f32> {
+        let mut output = Vec::new();
+        for chunk in input.chunks(1234) {
+            trimmer.process(chunk, &mut output);
+        }
+        output
+    }
+
+    #[test]
+    fn shortens_long_pauses() {
+        let mut trimmer = SilenceTrimmer::new(1, RATE);
+        let input = [tone(1.0), silence(2.0), tone(1.0)].concat();
+        let output = trim(&mut trimmer, &input);
+
+        // Each pause keeps its first KEEP_SECS
+        let expected = RATE as usize * 2 + (RATE as f64 * KEEP_SECS) as usize;
+        assert_eq!(output.len(), expected);
+        assert!((trimmer.take_trimmed_secs() - (2.0 - KEEP_SECS)).abs() < 1e-9);
+        assert_eq!(trimmer.take_trimmed_secs(), 0.0);
+    }
+
+    #[test]
+    fn leaves_short_pauses_and_sound_alone() {
+        let mut trimmer = SilenceTrimmer::new(2, RATE);
+        let input = [tone(0.5), silence(0.2), tone(0.5)].concat();
+        let stereo: Vec<f32> = input.iter().flat_map(|&s| [s, s]).collect();
+
+        assert_eq!(trim(&mut trimmer, &stereo), stereo);
+        assert_eq!(trimmer.take_trimmed_secs(), 0.0);
+    }
+
+    #[test]
+    fn all_silent_buffer_keeps_only_one_pause() {
+        let mut trimmer = SilenceTrimmer::new(1, RATE);
+        let output = trim(&mut trimmer, &silence(5.0));
+
+        assert_eq!(output.len(), (RATE as f64 * KEEP_SECS) as usize);
+        assert!(output.iter().all(|&s| s == 0.0));
+        assert!((trimmer.take_trimmed_secs() - (5.0 - KEEP_SECS)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn holds_back_partial_window() {
+        let mut trimmer = SilenceTrimmer::new(1, RATE);
+        let mut output = Vec::new();
+        trimmer.process(&tone(WINDOW_SECS / 2.0), &mut output);
+        assert!(output.is_empty());
+
+        trimmer.process(&tone(WINDOW_SECS / 2.0), &mut output);
+        assert_eq!(output.len(), (RATE as f64 * WINDOW_SECS) as usize);
+    }
+}
@@ -0,0 +1,202 @@
+use std::f32::consts::PI;
+
+pub const MIN_SPEED: f32 = 0.5;
+pub const MAX_SPEED: f32 = 3.0;
+
+const WINDOW_MS: usize = 30;
+const SEEK_MS: usize = 10;
+
+/// WSOLA time-stretcher: changes tempo while keeping pitch.
+///
+/// Works on interleaved samples. Each output hop takes a Hann-windowed
+/// segment from the input, shifted within a small seek range so that it
+/// lines up with the natural continuation of the previous segment.
+pub struct TimeStretch {
+    channels: usize,
+    speed: f32,
+
+    window_frames: usize,
+    hop_frames: usize,
+    seek_frames: usize,
+    window: Vec<f32>,
+
+    input: Vec<f32>,
+    overlap: Vec<f32>,
+    // Frame offsets into `input`
+    nominal_pos: f64,
+    natural_pos: Option<usize>,
+}
+
+impl TimeStretch {
+    pub fn new(channels: usize, sample_rate: u32, speed: f32) -> Self {
+        let window_frames = (sample_rate as usize * WINDOW_MS / 1000).max(64) & !1;
+        let hop_frames = window_frames / 2;
+        let seek_frames = sample_rate as usize * SEEK_MS / 1000;
+
+        let window = (0..window_frames)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / window_frames as f32).cos())
+            .collect();
+
+        Self {
+            channels: channels.max(1),
+            speed: speed.clamp(MIN_SPEED, MAX_SPEED),
+            window_frames,
+            hop_frames,
+            seek_frames,
+            window,
+            input: Vec::new(),
+            overlap: vec![0.0; window_frames * channels.max(1)],
+            nominal_pos: 0.0,
+            natural_pos: None,
+        }
+    }
+
+    /// Takes effect from the next hop. Audio already buffered is kept, so
+    /// the change is seamless.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed.clamp(MIN_SPEED, MAX_SPEED);
+    }
+
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    pub fn is_bypassed(&self) -> bool {
+        (self.speed - 1.0).abs() < 0.01
+    }
+
+    /// Drops any buffered audio, e.g. after a seek.
+    pub fn reset(&mut self) {
+        self.input.clear();
+        self.overlap.iter_mut().for_each(|s| *s = 0.0);
+        self.nominal_pos = 0.0;
+        self.natural_pos = None;
+    }
+
+    /// Appends the audio still held back for the overlap window to `out`
+    /// and empties the buffers, e.g. at the end of a track.
+    pub fn flush(&mut self, out: &mut Vec<f32>) {
+        let ch = self.channels;
+        let rest_from = match self.natural_pos {
+            Some(natural) => {
+                // The pending overlap is the fading tail of the last segment;
+                // the matching fade-in of the input completes it
+                for frame in 0..self.hop_frames {
+                    let w = self.window[frame];
+                    for c in 0..ch {
+                        let input = self.input.get((natural + frame) * ch + c);
+                        out.push(self.overlap[frame * ch + c] + input.map_or(0.0, |s| s * w));
+                    }
+                }
+                natural + self.hop_frames
+            }
+            None => self.nominal_pos as usize,
+        };
+
+        if let Some(rest) = self.input.get(rest_from * ch..) {
+            out.extend_from_slice(rest);
+        }
+        self.reset();
+    }
+
+    /// Feeds decoded samples in and appends any stretched samples to `out`.
+    pub fn process(&mut self, samples: &[f32], out: &mut Vec<f32>) {
+        if self.is_bypassed() {
+            // Back to normal speed, play out what was held for stretching
+            if !self.input.is_empty() {
+                self.flush(out);
+            }
+            out.extend_from_slice(samples);
+            return;
+        }
+
+        self.input.extend_from_slice(samples);
+
+        let ch = self.channels;
+        let hop_in = self.hop_frames as f64 * self.speed as f64;
+
+        loop {
+            let nominal = self.nominal_pos as usize;
+            let search_end = nominal + self.seek_frames + self.window_frames;
+            if search_end * ch > self.input.len() {
+                break;
+            }
+
+            let pos = match self.natural_pos {
+                Some(natural) => self.best_offset(nominal, natural),
+                None => nominal,
+            };
+
+            for frame in 0..self.window_frames {
+                let w = self.window[frame];
+                for c in 0..ch {
+                    self.overlap[frame * ch + c] += self.input[(pos + frame) * ch + c] * w;
+                }
+            }
+
+            let hop_samples = self.hop_frames * ch;
+            out.extend_from_slice(&self.overlap[..hop_samples]);
+            self.overlap.copy_within(hop_samples.., 0);
+            let len = self.overlap.len();
+            self.overlap[len - hop_samples..]
+                .iter_mut()
+                .for_each(|s| *s = 0.0);
+
+            self.natural_pos = Some(pos + self.hop_frames);
+            self.nominal_pos += hop_in;
+
+            self.discard_consumed();
+        }
+    }
+
+    /// Finds the segment start near `nominal` that best matches the
+    /// continuation of the previous segment at `natural`.
+    fn best_offset(&self, nominal: usize, natural: usize) -> usize {
+        let ch = self.channels;
+        let start = nominal.saturating_sub(self.seek_frames);
+        let end = nominal + self.seek_frames;
+        let compare_frames = self.hop_frames;
+
+        let mono = |frame: usize| -> f32 {
+            let base = frame * ch;
+            self.input[base..base + ch].iter().sum::<f32>()
+        };
+
+        let mut best_pos = nominal;
+        let mut best_score = f32::MIN;
+
+        for candidate in start..=end {
+            let mut corr = 0.0;
+            let mut energy = 0.0;
+            for i in (0..compare_frames).step_by(2) {
+                let a = mono(natural + i);
+                let b = mono(candidate + i);
+                corr += a * b;
+                energy += b * b;
+            }
+            let score = corr / (energy.sqrt() + 1e-6);
+            if score > best_score {
+                best_score = score;
+                best_pos = candidate;
+            }
+        }
+
+        best_pos
+    }
+
+    fn discard_consumed(&mut self) {
+        let nominal = self.nominal_pos as usize;
+        let mut keep_from = nominal.saturating_sub(self.seek_frames);
+        if let Some(natural) = self.natural_pos {
+            keep_from = keep_from.min(natural);
+        }
+
+        if keep_from == 0 {
+            return;
+        }
+
+        self.input.drain(..keep_from * self.channels);
+        self.nominal_pos -= keep_from as f64;
+        self.natural_pos = self.natural_pos.map(|n| n - keep_from);
+    }
+}
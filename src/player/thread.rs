@@ -1,75 +1,138 @@
-use std::collections::VecDeque;
+use std::collections::{HashSet, VecDeque};
 use std::fs::File;
-use std::path::PathBuf;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::mpsc::{self, TryRecvError};
 use std::sync::{Arc, Mutex};
 use std::thread::JoinHandle;
 use std::time::Duration;
 
-use cpal::Sample;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample};
 use symphonia::core::audio::{SampleBuffer, SignalSpec};
 use symphonia::core::codecs::{CODEC_TYPE_NULL, DecoderOptions};
 use symphonia::core::errors::Error as SymphoniaError;
 use symphonia::core::formats::{FormatOptions, FormatReader, SeekMode, SeekTo};
-use symphonia::core::io::MediaSourceStream;
+use symphonia::core::io::{MediaSource, MediaSourceStream};
 use symphonia::core::meta::MetadataOptions;
 use symphonia::core::probe::Hint;
 use symphonia::core::units::Time;
 
-use crate::events::types::AppEvent;
-use crate::player::commands::{PlayerCommand, PlayerState};
+use crate::api::stream::{DownloadingMediaSource, HttpMediaSource};
+use crate::cache::loudness::LoudnessStore;
+use crate::events::types::{AppEvent, TrackInfo};
+use crate::player::commands::{AudioSource, PlayerCommand, PlayerState};
+use crate::player::dsp::EffectChain;
+use crate::player::loudness::{Limiter, LoudnessMeter, Normalizer};
+use crate::player::resample::Converter;
+use crate::player::silence::SilenceTrimmer;
+use crate::player::stretch::TimeStretch;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+pub const MAX_VOLUME: f32 = 1.5;
+
+/// How quickly the output follows a gain change, in seconds.
+const GAIN_RAMP: f32 = 0.02;
+
+/// Output gain shared with the device callback, which ramps towards it to
+/// avoid clicks.
+#[derive(Clone)]
+struct Gain(Arc<AtomicU32>);
+
+impl Gain {
+    fn new(gain: f32) -> Self {
+        Self(Arc::new(AtomicU32::new(gain.to_bits())))
+    }
+
+    fn set(&self, gain: f32) {
+        self.0.store(gain.to_bits(), Ordering::Relaxed);
+    }
+
+    fn get(&self) -> f32 {
+        f32::from_bits(self.0.load(Ordering::Relaxed))
+    }
+}
+
+/// Per-sample smoothing factor for following a gain change at `rate`.
+fn gain_ramp(rate: u32) -> f32 {
+    1.0 - (-1.0 / (GAIN_RAMP * rate as f32)).exp()
+}
+
+/// Maps a volume setting to gain. Squaring makes steps sound even.
+///
+/// Gain up to 1.0 is applied by the device callback so changes are heard
+/// straight away. Anything above that is applied before the limiter, which
+/// keeps the boost from clipping.
+fn volume_gain(volume: f32, muted: bool) -> f32 {
+    if muted {
+        0.0
+    } else {
+        let volume = volume.clamp(0.0, MAX_VOLUME);
+        volume * volume
+    }
+}
 
 struct AudioOutput {
     ring_buffer: Arc<Mutex<VecDeque<f32>>>,
-    spec: SignalSpec,
+    rate: u32,
+    channels: usize,
+    device_name: String,
     _stream: cpal::Stream,
     paused: Arc<AtomicBool>,
+    lost: Arc<AtomicBool>,
+    /// The first error the stream reported, other than losing the device
+    error: Arc<Mutex<Option<String>>>,
+    gain: Gain,
 }
 
 impl AudioOutput {
-    fn new(spec: SignalSpec) -> Result<Self, Box<dyn std::error::Error + Send + Sync>> {
-        let host = cpal::default_host();
-        let device = host
-            .default_output_device()
-            .ok_or("No output device available")?;
-
-        let config = cpal::StreamConfig {
-            channels: spec.channels.count() as u16,
-            sample_rate: cpal::SampleRate(spec.rate),
-            buffer_size: cpal::BufferSize::Default,
-        };
+    /// Opens `device_name`, or the default device, with the configuration
+    /// closest to `spec`.
+    fn new(spec: SignalSpec, device_name: Option<&str>, gain: &Gain) -> Result<Self, BoxError> {
+        let device = find_device(device_name)?;
+        let device_name = device.name().unwrap_or_else(|_| "Unknown".to_string());
+
+        let supported = choose_config(&device, &spec)?;
+        let sample_format = supported.sample_format();
+        let config = supported.config();
 
-        let buffer_capacity = spec.rate as usize * spec.channels.count() * 5;
+        let rate = config.sample_rate.0;
+        let channels = config.channels as usize;
+        let buffer_capacity = rate as usize * channels * 5;
         let ring_buffer = Arc::new(Mutex::new(VecDeque::with_capacity(buffer_capacity)));
-        let ring_buffer_clone = ring_buffer.clone();
         let paused = Arc::new(AtomicBool::new(false));
-        let paused_clone = paused.clone();
-
-        let stream = device.build_output_stream(
-            &config,
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                let mut buffer = ring_buffer_clone.lock().unwrap();
-                for sample in data.iter_mut() {
-                    if paused_clone.load(Ordering::Relaxed) {
-                        *sample = Sample::EQUILIBRIUM;
-                    } else {
-                        *sample = buffer.pop_front().unwrap_or(Sample::EQUILIBRIUM);
-                    }
-                }
-            },
-            |err| eprintln!("Audio stream error: {}", err),
-            None,
-        )?;
+        let lost = Arc::new(AtomicBool::new(false));
+        let error = Arc::new(Mutex::new(None));
+
+        let stream = match sample_format {
+            SampleFormat::F32 => {
+                build_stream::<f32>(&device, &config, &ring_buffer, &paused, &lost, &error, gain)
+            }
+            SampleFormat::I16 => {
+                build_stream::<i16>(&device, &config, &ring_buffer, &paused, &lost, &error, gain)
+            }
+            SampleFormat::U16 => {
+                build_stream::<u16>(&device, &config, &ring_buffer, &paused, &lost, &error, gain)
+            }
+            SampleFormat::I32 => {
+                build_stream::<i32>(&device, &config, &ring_buffer, &paused, &lost, &error, gain)
+            }
+            format => return Err(format!("Unsupported sample format {}", format).into()),
+        }?;
 
         stream.play()?;
 
         Ok(AudioOutput {
             ring_buffer,
-            spec,
+            rate,
+            channels,
+            device_name,
             _stream: stream,
             paused,
+            lost,
+            error,
+            gain: gain.clone(),
         })
     }
 
@@ -94,40 +157,506 @@ impl AudioOutput {
     fn clear_buffer(&self) {
         self.ring_buffer.lock().unwrap().clear();
     }
+
+    /// Seconds of audio queued for the device.
+    fn buffered_secs(&self) -> f64 {
+        self.buffer_len() as f64 / (self.rate as f64 * self.channels as f64)
+    }
+
+    /// True once the device has gone away, e.g. a headset was unplugged.
+    fn is_lost(&self) -> bool {
+        self.lost.load(Ordering::Relaxed)
+    }
+
+    /// The stream failed in some other way, e.g. an ALSA or PipeWire error.
+    fn error(&self) -> Option<String> {
+        self.error.lock().unwrap().clone()
+    }
+
+    /// Lost or failed outputs are replaced before playback resumes.
+    fn needs_reopen(&self) -> bool {
+        self.is_lost() || self.error().is_some()
+    }
+
+    fn wait_until_drained(&self) {
+        while self.buffer_len() > 0 {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+}
+
+/// A track whose reader and decoder are open but not yet playing.
+struct OpenedTrack {
+    source: AudioSource,
+    format: Box<dyn FormatReader>,
+    decoder: Box<dyn symphonia::core::codecs::Decoder>,
+    track_id: u32,
+    total_duration: Option<Duration>,
+}
+
+/// Picks the device configuration closest to the decoded audio. Whatever
+/// doesn't match is converted before it reaches the device.
+fn choose_config(
+    device: &cpal::Device,
+    spec: &SignalSpec,
+) -> Result<cpal::SupportedStreamConfig, BoxError> {
+    let channels = spec.channels.count() as u16;
+    let best = device
+        .supported_output_configs()
+        .map(|configs| configs.collect::<Vec<_>>())
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|c| {
+            matches!(
+                c.sample_format(),
+                SampleFormat::F32 | SampleFormat::I16 | SampleFormat::U16 | SampleFormat::I32
+            )
+        })
+        .max_by_key(|c| {
+            let rate_supported =
+                c.min_sample_rate().0 <= spec.rate && spec.rate <= c.max_sample_rate().0;
+            (
+                c.channels() == channels,
+                c.channels() == 2,
+                rate_supported,
+                c.sample_format() == SampleFormat::F32,
+            )
+        });
+
+    match best {
+        Some(range) => {
+            let rate = spec
+                .rate
+                .clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+            Ok(range.with_sample_rate(cpal::SampleRate(rate)))
+        }
+        None => Ok(device.default_output_config()?),
+    }
+}
+
+fn build_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    ring_buffer: &Arc<Mutex<VecDeque<f32>>>,
+    paused: &Arc<AtomicBool>,
+    lost: &Arc<AtomicBool>,
+    error: &Arc<Mutex<Option<String>>>,
+    gain: &Gain,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let ring_buffer = ring_buffer.clone();
+    let paused = paused.clone();
+    let lost = lost.clone();
+    let error = error.clone();
+    let gain = gain.clone();
+
+    let channels = config.channels.max(1) as usize;
+    let ramp = gain_ramp(config.sample_rate.0);
+    let mut current_gain = gain.get().min(1.0);
+
+    device.build_output_stream(
+        config,
+        move |data: &mut [T], _: &cpal::OutputCallbackInfo| {
+            if paused.load(Ordering::Relaxed) {
+                data.fill(T::EQUILIBRIUM);
+                return;
+            }
+
+            let target_gain = gain.get().min(1.0);
+            let mut buffer = ring_buffer.lock().unwrap();
+            for frame in data.chunks_mut(channels) {
+                current_gain += (target_gain - current_gain) * ramp;
+                for sample in frame {
+                    let value = buffer.pop_front().unwrap_or(0.0) * current_gain;
+                    *sample = T::from_sample(value.clamp(-1.0, 1.0));
+                }
+            }
+        },
+        move |err| match err {
+            cpal::StreamError::DeviceNotAvailable => lost.store(true, Ordering::Relaxed),
+            err => {
+                error.lock().unwrap().get_or_insert(err.to_string());
+            }
+        },
+        None,
+    )
+}
+
+fn output_device_names() -> Vec<String> {
+    cpal::default_host()
+        .output_devices()
+        .map(|devices| devices.filter_map(|d| d.name().ok()).collect())
+        .unwrap_or_default()
+}
+
+/// Looks up an output device by name. Falls back to the default device if
+/// it's missing, so a stale config entry doesn't prevent playback.
+fn find_device(name: Option<&str>) -> Result<cpal::Device, BoxError> {
+    let host = cpal::default_host();
+
+    if let Some(name) = name {
+        let found = host
+            .output_devices()?
+            .find(|d| d.name().is_ok_and(|n| n == name));
+        match found {
+            Some(device) => return Ok(device),
+            None => eprintln!("Output device '{}' not found, using default", name),
+        }
+    }
+
+    Ok(host
+        .default_output_device()
+        .ok_or("No output device available")?)
 }
 
 struct PlaybackContext {
+    source: AudioSource,
     format: Box<dyn FormatReader>,
     decoder: Box<dyn symphonia::core::codecs::Decoder>,
     track_id: u32,
+    track: TrackInfo,
+    next: Option<(OpenedTrack, TrackInfo)>,
+    spec: SignalSpec,
     audio_output: AudioOutput,
     sample_buf: SampleBuffer<f32>,
+    normalizer: Option<Normalizer>,
+    trimmer: Option<SilenceTrimmer>,
+    trim_buf: Vec<f32>,
+    effects: EffectChain,
+    /// Always in place, so that normalization, effects or volume boost
+    /// being turned on later doesn't add its lookahead delay mid-stream
+    limiter: Limiter,
+    stretch: TimeStretch,
+    stretch_buf: Vec<f32>,
+    converter: Converter,
+    convert_buf: Vec<f32>,
+    /// Gain above 1.0, following the output gain
+    boost: f32,
     total_frames_decoded: u64,
     total_duration: Option<Duration>,
 }
 
+impl PlaybackContext {
+    fn apply_boost(&mut self) {
+        let target = self.audio_output.gain.get().max(1.0);
+        if target == 1.0 && self.boost == 1.0 {
+            return;
+        }
+        let ramp = gain_ramp(self.audio_output.rate);
+        for frame in self.convert_buf.chunks_mut(self.audio_output.channels) {
+            self.boost += (target - self.boost) * ramp;
+            for sample in frame {
+                *sample *= self.boost;
+            }
+        }
+        if (target - self.boost).abs() < 1e-4 {
+            self.boost = target;
+        }
+    }
+
+    fn write_decoded(&mut self) {
+        if let Some(normalizer) = &mut self.normalizer {
+            normalizer.process(self.sample_buf.samples_mut());
+        }
+        let samples = match &mut self.trimmer {
+            Some(trimmer) => {
+                self.trim_buf.clear();
+                trimmer.process(self.sample_buf.samples(), &mut self.trim_buf);
+                &mut self.trim_buf[..]
+            }
+            None => self.sample_buf.samples_mut(),
+        };
+        self.effects.process(samples);
+        self.stretch_buf.clear();
+        self.stretch.process(samples, &mut self.stretch_buf);
+        self.write_stretched();
+    }
+
+    /// Plays out the end of the audio held by the time-stretcher, which
+    /// would otherwise be lost when the track ends.
+    fn flush_stretch(&mut self) {
+        self.stretch_buf.clear();
+        self.stretch.flush(&mut self.stretch_buf);
+        self.write_stretched();
+    }
+
+    fn write_stretched(&mut self) {
+        self.convert_buf.clear();
+        self.converter
+            .process(&self.stretch_buf, &mut self.convert_buf);
+        self.apply_boost();
+        self.limiter.process(&mut self.convert_buf);
+        self.audio_output.write_samples(&self.convert_buf);
+    }
+
+    fn reset_pipeline(&mut self) {
+        self.stretch.reset();
+        self.converter.reset();
+        self.effects.reset();
+        if let Some(trimmer) = &mut self.trimmer {
+            trimmer.reset();
+        }
+        self.limiter.reset();
+    }
+
+    fn local_position(&self) -> f64 {
+        self.total_frames_decoded as f64 / self.spec.rate as f64
+    }
+
+    /// Decode position in book time.
+    fn position(&self) -> Duration {
+        Duration::from_secs_f64(self.track.start_offset + self.local_position())
+    }
+
+    /// Track position of the audio leaving the speakers, behind the decoder
+    /// by whatever is still buffered.
+    fn heard_position(&self) -> Duration {
+        let buffered = self.audio_output.buffered_secs() * self.stretch.speed() as f64;
+        Duration::from_secs_f64((self.local_position() - buffered).max(0.0))
+    }
+
+    /// Book position of the audio leaving the speakers. Unlike the track
+    /// position, this stays right while the previous track's tail plays out.
+    fn heard_book_position(&self) -> Duration {
+        let buffered = self.audio_output.buffered_secs() * self.stretch.speed() as f64;
+        Duration::from_secs_f64(
+            (self.track.start_offset + self.local_position() - buffered).max(0.0),
+        )
+    }
+
+    fn normalizer_gain_db(&self) -> f64 {
+        self.normalizer.as_ref().map_or(0.0, |n| n.gain_db())
+    }
+
+    fn is_near_end(&self) -> bool {
+        self.total_duration
+            .is_some_and(|d| d.as_secs_f64() - self.local_position() < PRELOAD_AHEAD)
+    }
+
+    /// Continues decoding from `next` without touching the output, so the
+    /// previous track's tail plays straight into the new one.
+    fn switch_to(&mut self, next: OpenedTrack, track: TrackInfo) {
+        self.source = next.source;
+        self.format = next.format;
+        self.decoder = next.decoder;
+        self.track_id = next.track_id;
+        self.total_duration = next.total_duration;
+        self.total_frames_decoded = 0;
+        self.track = track;
+    }
+}
+
+/// How long before the end of a track the next one is opened.
+const PRELOAD_AHEAD: f64 = 30.0;
+
+/// Sets up loudness normalization for each track. Local files are measured
+/// once in the background and the result is stored; until it's known, and
+/// for streams, the normalizer works from a running estimate.
+struct LoudnessControl {
+    target: Option<f64>,
+    store: Option<LoudnessStore>,
+    measuring: HashSet<PathBuf>,
+    results_tx: mpsc::Sender<(PathBuf, Option<f64>)>,
+    results_rx: mpsc::Receiver<(PathBuf, Option<f64>)>,
+}
+
+impl LoudnessControl {
+    fn new(target: Option<f64>) -> Self {
+        let store = target.and_then(|_| {
+            LoudnessStore::open()
+                .map_err(|e| eprintln!("Failed to open loudness store: {:?}", e))
+                .ok()
+        });
+        let (results_tx, results_rx) = mpsc::channel();
+        Self {
+            target,
+            store,
+            measuring: HashSet::new(),
+            results_tx,
+            results_rx,
+        }
+    }
+
+    /// Attaches a normalizer for the context's current track, starting
+    /// from `gain_db` until the track's own loudness is known.
+    fn attach(&mut self, c: &mut PlaybackContext, gain_db: f64) {
+        c.normalizer = self.normalizer(&c.source, c.spec, gain_db);
+    }
+
+    fn normalizer(
+        &mut self,
+        source: &AudioSource,
+        spec: SignalSpec,
+        gain_db: f64,
+    ) -> Option<Normalizer> {
+        let mut normalizer =
+            Normalizer::new(spec.channels.count(), spec.rate, self.target?, gain_db);
+        if let AudioSource::File(path) = source {
+            match self.store.as_ref().and_then(|s| s.get(path)) {
+                Some(lufs) => normalizer.set_measured(lufs),
+                None => self.measure(path),
+            }
+        }
+        Some(normalizer)
+    }
+
+    fn measure(&mut self, path: &Path) {
+        if !self.measuring.insert(path.to_path_buf()) {
+            return;
+        }
+
+        let path = path.to_path_buf();
+        let results_tx = self.results_tx.clone();
+        std::thread::spawn(move || {
+            let lufs = measure_loudness(&path).unwrap_or_else(|e| {
+                eprintln!("Failed to measure loudness of {}: {}", path.display(), e);
+                None
+            });
+            let _ = results_tx.send((path, lufs));
+        });
+    }
+
+    /// Stores finished measurements and applies them to the playing track.
+    fn poll(&mut self, mut ctx: Option<&mut PlaybackContext>) {
+        while let Ok((path, lufs)) = self.results_rx.try_recv() {
+            // Files that can't be measured stay marked so they aren't retried
+            let Some(lufs) = lufs else {
+                continue;
+            };
+            self.measuring.remove(&path);
+            if let Some(store) = &mut self.store
+                && let Err(e) = store.insert(&path, lufs)
+            {
+                eprintln!("Failed to store loudness: {:?}", e);
+            }
+
+            if let Some(c) = ctx.as_deref_mut()
+                && matches!(&c.source, AudioSource::File(p) if *p == path)
+                && let Some(normalizer) = &mut c.normalizer
+            {
+                normalizer.set_measured(lufs);
+            }
+        }
+    }
+}
+
+/// Decodes a whole file to measure its integrated loudness.
+fn measure_loudness(path: &Path) -> Result<Option<f64>, BoxError> {
+    let source = AudioSource::File(path.to_path_buf());
+    let mut opened = open_track(&reqwest::blocking::Client::new(), &source)?;
+    let mut meter: Option<(SignalSpec, LoudnessMeter)> = None;
+    let mut sample_buf: Option<SampleBuffer<f32>> = None;
+
+    loop {
+        let packet = match opened.format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => {
+                break;
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != opened.track_id {
+            continue;
+        }
+
+        let decoded = match opened.decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            Err(SymphoniaError::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let (meter_spec, meter) = meter
+            .get_or_insert_with(|| (spec, LoudnessMeter::new(spec.channels.count(), spec.rate)));
+        if spec != *meter_spec {
+            continue;
+        }
+
+        let needed = decoded.capacity() * spec.channels.count();
+        if sample_buf.as_ref().is_none_or(|b| b.capacity() < needed) {
+            sample_buf = Some(SampleBuffer::new(decoded.capacity() as u64, spec));
+        }
+        let Some(buf) = &mut sample_buf else {
+            continue;
+        };
+        buf.copy_interleaved_ref(decoded);
+        meter.process(buf.samples());
+    }
+
+    Ok(meter.and_then(|(_, meter)| meter.integrated()))
+}
+
 pub fn spawn(
     cmd_rx: mpsc::Receiver<PlayerCommand>,
     event_tx: mpsc::Sender<AppEvent>,
+    output_device: Option<String>,
+    loudness_target: Option<f64>,
 ) -> JoinHandle<()> {
     std::thread::spawn(move || {
+        let mut device_name = output_device;
         let mut ctx: Option<PlaybackContext> = None;
+        let mut queue: VecDeque<(AudioSource, TrackInfo)> = VecDeque::new();
         let mut is_paused = false;
+        let mut speed = 1.0;
+        let mut volume = 1.0;
+        let mut muted = false;
+        let mut voice_boost = false;
+        let mut trim_silence = false;
+        let mut time_saved = 0.0;
+        let mut reported_time_saved = 0.0;
+        let mut fade: Option<(std::time::Instant, Duration)> = None;
+        let mut stop_at: Option<Duration> = None;
+        let gain = Gain::new(volume_gain(volume, muted));
+        let mut loudness = LoudnessControl::new(loudness_target);
+        let http_client = reqwest::blocking::Client::new();
         let mut last_position_update = std::time::Instant::now();
 
         loop {
+            loudness.poll(ctx.as_mut());
+
             // Check for commands
             match cmd_rx.try_recv() {
                 Ok(cmd) => match cmd {
-                    PlayerCommand::Play { path, position } => {
-                        // Stop current playback
-                        ctx = None;
+                    PlayerCommand::Play {
+                        source,
+                        track,
+                        position,
+                    } => {
+                        if fade.take().is_some() {
+                            gain.set(volume_gain(volume, muted));
+                        }
+
+                        // Stop current playback, keeping its gain as a starting point
+                        let gain_db = ctx.take().map_or(0.0, |c| c.normalizer_gain_db());
+                        queue.clear();
+                        stop_at = None;
                         is_paused = false;
+                        // Counted per Play; the app adds it up for the book
+                        time_saved = 0.0;
+                        reported_time_saved = 0.0;
 
                         let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Loading));
 
-                        match load_audio(&path, position) {
-                            Ok(new_ctx) => {
+                        let result = open_track(&http_client, &source).and_then(|opened| {
+                            start_playback(
+                                opened,
+                                track,
+                                position,
+                                speed,
+                                device_name.as_deref(),
+                                &gain,
+                            )
+                        });
+
+                        match result {
+                            Ok(mut new_ctx) => {
+                                loudness.attach(&mut new_ctx, gain_db);
+                                new_ctx.effects = effect_chain(voice_boost, &new_ctx.spec);
+                                new_ctx.trimmer = silence_trimmer(trim_silence, &new_ctx.spec);
+                                new_ctx.write_decoded();
                                 if let Some(dur) = new_ctx.total_duration {
                                     let _ = event_tx.send(AppEvent::DurationChanged(dur));
                                 }
@@ -143,7 +672,14 @@ pub fn spawn(
                         }
                     }
 
+                    PlayerCommand::Enqueue { source, track } => {
+                        queue.push_back((source, track));
+                    }
+
                     PlayerCommand::Pause => {
+                        if fade.take().is_some() {
+                            gain.set(volume_gain(volume, muted));
+                        }
                         if let Some(ref c) = ctx {
                             c.audio_output.set_paused(true);
                             is_paused = true;
@@ -153,6 +689,31 @@ pub fn spawn(
                     }
 
                     PlayerCommand::Resume => {
+                        // The device went away while paused; move to whatever is available now
+                        if let Some(c) = ctx.take_if(|c| c.audio_output.needs_reopen()) {
+                            match reopen(
+                                c,
+                                &http_client,
+                                &mut queue,
+                                speed,
+                                device_name.as_deref(),
+                                &gain,
+                                &mut loudness,
+                            ) {
+                                Ok(new_ctx) => {
+                                    let _ = event_tx.send(AppEvent::OutputDeviceChanged(
+                                        new_ctx.audio_output.device_name.clone(),
+                                    ));
+                                    ctx = Some(new_ctx);
+                                }
+                                Err(e) => {
+                                    let _ = event_tx.send(AppEvent::PlayerError(e.to_string()));
+                                    let _ = event_tx
+                                        .send(AppEvent::PlayerStateChanged(PlayerState::Stopped));
+                                }
+                            }
+                        }
+
                         if let Some(ref c) = ctx {
                             c.audio_output.set_paused(false);
                             is_paused = false;
@@ -162,10 +723,15 @@ pub fn spawn(
                     }
 
                     PlayerCommand::Stop => {
+                        if fade.take().is_some() {
+                            gain.set(volume_gain(volume, muted));
+                        }
                         if let Some(ref c) = ctx {
                             c.audio_output.clear_buffer();
                         }
                         ctx = None;
+                        queue.clear();
+                        stop_at = None;
                         is_paused = false;
                         let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Stopped));
                     }
@@ -182,10 +748,10 @@ pub fn spawn(
                             match c.format.seek(SeekMode::Accurate, seek_to) {
                                 Ok(_seeked_to) => {
                                     c.decoder.reset();
-                                    c.total_frames_decoded = (position.as_secs_f64()
-                                        * c.audio_output.spec.rate as f64)
-                                        as u64;
-                                    let _ = event_tx.send(AppEvent::PositionUpdate(position));
+                                    c.reset_pipeline();
+                                    c.total_frames_decoded =
+                                        (position.as_secs_f64() * c.spec.rate as f64) as u64;
+                                    let _ = event_tx.send(AppEvent::PositionUpdate(c.position()));
                                 }
                                 Err(e) => {
                                     let _ = event_tx
@@ -195,8 +761,101 @@ pub fn spawn(
                         }
                     }
 
-                    PlayerCommand::SetSpeed(_speed) => {
-                        todo!()
+                    PlayerCommand::StopAt(position) => {
+                        stop_at = position;
+                    }
+
+                    PlayerCommand::SetSpeed(new_speed) => {
+                        speed = new_speed;
+                        if let Some(ref mut c) = ctx {
+                            c.stretch.set_speed(speed);
+                        }
+                    }
+
+                    PlayerCommand::SetVolume(new_volume) => {
+                        volume = new_volume;
+                        gain.set(volume_gain(volume, muted));
+                    }
+
+                    PlayerCommand::Mute(mute) => {
+                        muted = mute;
+                        gain.set(volume_gain(volume, muted));
+                    }
+
+                    PlayerCommand::SetVoiceBoost(enabled) => {
+                        voice_boost = enabled;
+                        if let Some(ref mut c) = ctx {
+                            c.effects = effect_chain(voice_boost, &c.spec);
+                        }
+                    }
+
+                    PlayerCommand::FadeOut(length) => {
+                        if ctx.is_some() {
+                            fade = Some((std::time::Instant::now(), length));
+                        }
+                    }
+
+                    PlayerCommand::CancelFade => {
+                        if fade.take().is_some() {
+                            gain.set(volume_gain(volume, muted));
+                        }
+                    }
+
+                    PlayerCommand::SetTrimSilence(enabled) => {
+                        trim_silence = enabled;
+                        if let Some(ref mut c) = ctx {
+                            c.trimmer = silence_trimmer(trim_silence, &c.spec);
+                        }
+                    }
+
+                    PlayerCommand::ListOutputDevices => {
+                        let _ = event_tx.send(AppEvent::OutputDevices(
+                            output_device_names(),
+                            device_name.clone(),
+                        ));
+                    }
+
+                    PlayerCommand::SetOutputDevice(name) => {
+                        if let Some(ref name) = name
+                            && !output_device_names().contains(name)
+                        {
+                            let _ = event_tx.send(AppEvent::PlayerError(format!(
+                                "Output device '{}' not found",
+                                name
+                            )));
+                            continue;
+                        }
+                        device_name = name;
+
+                        let Some(c) = ctx.take() else {
+                            let label = device_name.clone().unwrap_or("System default".to_string());
+                            let _ = event_tx.send(AppEvent::OutputDeviceChanged(label));
+                            continue;
+                        };
+
+                        match reopen(
+                            c,
+                            &http_client,
+                            &mut queue,
+                            speed,
+                            device_name.as_deref(),
+                            &gain,
+                            &mut loudness,
+                        ) {
+                            Ok(new_ctx) => {
+                                new_ctx.audio_output.set_paused(is_paused);
+                                let _ = event_tx.send(AppEvent::OutputDeviceChanged(
+                                    new_ctx.audio_output.device_name.clone(),
+                                ));
+                                ctx = Some(new_ctx);
+                            }
+                            Err(e) => {
+                                is_paused = false;
+                                let _ = event_tx.send(AppEvent::PlayerError(e.to_string()));
+                                let _ = event_tx
+                                    .send(AppEvent::PlayerStateChanged(PlayerState::Stopped));
+                            }
+                        }
                     }
                 },
 
@@ -210,16 +869,74 @@ pub fn spawn(
                 }
             }
 
+            if let Some((started, length)) = fade {
+                let progress = started.elapsed().as_secs_f32() / length.as_secs_f32();
+                if progress < 1.0 {
+                    gain.set(volume_gain(volume * (1.0 - progress), muted));
+                } else {
+                    fade = None;
+                    if let Some(ref c) = ctx {
+                        c.audio_output.set_paused(true);
+                        is_paused = true;
+                        let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Paused));
+                    }
+                    // Back to full volume for whenever playback resumes
+                    gain.set(volume_gain(volume, muted));
+                }
+            }
+
             // Process audio if we have a context and not paused
             if let Some(ref mut c) = ctx {
+                if c.audio_output.is_lost() && !is_paused {
+                    is_paused = true;
+                    let _ = event_tx.send(AppEvent::PlayerError(format!(
+                        "Output device '{}' disconnected",
+                        c.audio_output.device_name
+                    )));
+                    let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Paused));
+                }
+
+                if !is_paused && let Some(error) = c.audio_output.error() {
+                    c.audio_output.set_paused(true);
+                    is_paused = true;
+                    let _ = event_tx.send(AppEvent::PlayerError(format!(
+                        "Audio stream error: {}",
+                        error
+                    )));
+                    let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Paused));
+                }
+
+                if !is_paused
+                    && let Some(end) = stop_at
+                    && c.heard_book_position() >= end
+                {
+                    stop_at = None;
+                    c.audio_output.set_paused(true);
+                    is_paused = true;
+                    let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Paused));
+                    let _ = event_tx.send(AppEvent::StopReached);
+                }
+
                 if is_paused {
                     std::thread::sleep(Duration::from_millis(50));
                     continue;
                 }
 
+                // Open the next track ahead of time so the switch is seamless
+                if c.next.is_none()
+                    && c.is_near_end()
+                    && let Some((source, track)) = queue.pop_front()
+                {
+                    match open_track(&http_client, &source) {
+                        Ok(opened) => c.next = Some((opened, track)),
+                        Err(e) => {
+                            let _ = event_tx.send(AppEvent::PlayerError(e.to_string()));
+                        }
+                    }
+                }
+
                 // Throttle if buffer is full
-                let max_buffer =
-                    c.audio_output.spec.rate as usize * c.audio_output.spec.channels.count() * 3;
+                let max_buffer = c.audio_output.rate as usize * c.audio_output.channels * 3;
                 if c.audio_output.buffer_len() > max_buffer {
                     std::thread::sleep(Duration::from_millis(10));
                     continue;
@@ -234,19 +951,48 @@ pub fn spawn(
 
                         match c.decoder.decode(&packet) {
                             Ok(decoded) => {
+                                let spec = *decoded.spec();
+                                if spec != c.spec {
+                                    // A following track may use another format; the output stays
+                                    c.stretch =
+                                        TimeStretch::new(spec.channels.count(), spec.rate, speed);
+                                    c.converter = Converter::new(
+                                        spec.channels.count(),
+                                        spec.rate,
+                                        c.audio_output.channels,
+                                        c.audio_output.rate,
+                                    );
+                                    c.spec = spec;
+                                    let gain_db =
+                                        c.normalizer.as_ref().map_or(0.0, |n| n.gain_db());
+                                    c.normalizer = loudness.normalizer(&c.source, spec, gain_db);
+                                    c.effects = effect_chain(voice_boost, &spec);
+                                    c.trimmer = silence_trimmer(trim_silence, &spec);
+                                }
+                                // The next track may decode larger packets
+                                let needed = decoded.capacity() * c.spec.channels.count();
+                                if c.sample_buf.capacity() < needed {
+                                    c.sample_buf =
+                                        SampleBuffer::new(decoded.capacity() as u64, c.spec);
+                                }
                                 c.sample_buf.copy_interleaved_ref(decoded);
-                                c.total_frames_decoded += c.sample_buf.len() as u64
-                                    / c.audio_output.spec.channels.count() as u64;
+                                c.total_frames_decoded +=
+                                    c.sample_buf.len() as u64 / c.spec.channels.count() as u64;
 
-                                c.audio_output.write_samples(c.sample_buf.samples());
+                                c.write_decoded();
+                                if let Some(trimmer) = &mut c.trimmer {
+                                    time_saved += trimmer.take_trimmed_secs();
+                                }
 
                                 // Send position update every 100ms
                                 if last_position_update.elapsed() >= Duration::from_millis(100) {
-                                    let current_secs = c.total_frames_decoded as f64
-                                        / c.audio_output.spec.rate as f64;
-                                    let _ = event_tx.send(AppEvent::PositionUpdate(
-                                        Duration::from_secs_f64(current_secs),
-                                    ));
+                                    let _ = event_tx.send(AppEvent::PositionUpdate(c.position()));
+                                    if time_saved != reported_time_saved {
+                                        let _ = event_tx.send(AppEvent::TimeSaved(
+                                            Duration::from_secs_f64(time_saved),
+                                        ));
+                                        reported_time_saved = time_saved;
+                                    }
                                     last_position_update = std::time::Instant::now();
                                 }
                             }
@@ -264,15 +1010,42 @@ pub fn spawn(
                     Err(SymphoniaError::IoError(e))
                         if e.kind() == std::io::ErrorKind::UnexpectedEof =>
                     {
-                        // Wait for buffer to drain
-                        while c.audio_output.buffer_len() > 0 {
-                            std::thread::sleep(Duration::from_millis(50));
-                        }
-                        std::thread::sleep(Duration::from_millis(200));
+                        let next = c.next.take().or_else(|| {
+                            let (source, track) = queue.pop_front()?;
+                            open_track(&http_client, &source)
+                                .map_err(|e| {
+                                    let _ = event_tx.send(AppEvent::PlayerError(e.to_string()));
+                                })
+                                .ok()
+                                .map(|opened| (opened, track))
+                        });
 
-                        ctx = None;
-                        let _ = event_tx.send(AppEvent::TrackEnded);
-                        let _ = event_tx.send(AppEvent::PlayerStateChanged(PlayerState::Stopped));
+                        match next {
+                            Some((opened, track)) => {
+                                // Another format gets a new stretcher, so the old one is emptied
+                                let params = opened.decoder.codec_params();
+                                if params.sample_rate != Some(c.spec.rate)
+                                    || params.channels != Some(c.spec.channels)
+                                {
+                                    c.flush_stretch();
+                                }
+                                c.switch_to(opened, track.clone());
+                                let gain_db = c.normalizer_gain_db();
+                                loudness.attach(c, gain_db);
+                                let _ = event_tx.send(AppEvent::TrackChanged(track));
+                                if let Some(dur) = c.total_duration {
+                                    let _ = event_tx.send(AppEvent::DurationChanged(dur));
+                                }
+                            }
+                            None => {
+                                c.flush_stretch();
+                                c.audio_output.wait_until_drained();
+                                ctx = None;
+                                let _ = event_tx.send(AppEvent::TrackEnded);
+                                let _ = event_tx
+                                    .send(AppEvent::PlayerStateChanged(PlayerState::Stopped));
+                            }
+                        }
                     }
 
                     Err(e) => {
@@ -289,20 +1062,110 @@ pub fn spawn(
     })
 }
 
-fn load_audio(
-    path: &PathBuf,
-    start_position: Duration,
-) -> Result<PlaybackContext, Box<dyn std::error::Error + Send + Sync>> {
+fn effect_chain(voice_boost: bool, spec: &SignalSpec) -> EffectChain {
+    if voice_boost {
+        EffectChain::voice_boost(spec.channels.count(), spec.rate)
+    } else {
+        EffectChain::default()
+    }
+}
+
+fn silence_trimmer(trim_silence: bool, spec: &SignalSpec) -> Option<SilenceTrimmer> {
+    trim_silence.then(|| SilenceTrimmer::new(spec.channels.count(), spec.rate))
+}
+
+/// Rebuilds playback on a fresh output, picking up at the position being
+/// heard. The old output is closed first so the device is free again.
+fn reopen(
+    c: PlaybackContext,
+    http_client: &reqwest::blocking::Client,
+    queue: &mut VecDeque<(AudioSource, TrackInfo)>,
+    speed: f32,
+    device_name: Option<&str>,
+    gain: &Gain,
+    loudness: &mut LoudnessControl,
+) -> Result<PlaybackContext, BoxError> {
+    let position = c.heard_position();
+    let gain_db = c.normalizer_gain_db();
+    let PlaybackContext {
+        source,
+        track,
+        next,
+        audio_output,
+        mut effects,
+        mut trimmer,
+        ..
+    } = c;
+    drop(audio_output);
+
+    if let Some((opened, next_track)) = next {
+        queue.push_front((opened.source, next_track));
+    }
+
+    let opened = open_track(http_client, &source)?;
+    let mut c = start_playback(opened, track, position, speed, device_name, gain)?;
+    loudness.attach(&mut c, gain_db);
+    effects.reset();
+    c.effects = effects;
+    if let Some(trimmer) = &mut trimmer {
+        trimmer.reset();
+    }
+    c.trimmer = trimmer;
+    c.write_decoded();
+    Ok(c)
+}
+
+fn open_track(
+    http_client: &reqwest::blocking::Client,
+    source: &AudioSource,
+) -> Result<OpenedTrack, BoxError> {
     let codecs = symphonia::default::get_codecs();
     let probe = symphonia::default::get_probe();
 
-    let file = File::open(path)?;
-    let mss = MediaSourceStream::new(Box::new(file), Default::default());
-
     let mut hint = Hint::new();
-    if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-        hint.with_extension(ext);
-    }
+    let media_source: Box<dyn MediaSource> = match source {
+        AudioSource::File(path) => {
+            if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
+                hint.with_extension(ext);
+            }
+            Box::new(File::open(path)?)
+        }
+        AudioSource::Stream {
+            url,
+            api_key,
+            extension,
+        } => {
+            if let Some(ext) = extension {
+                hint.with_extension(ext);
+            }
+            Box::new(HttpMediaSource::open(
+                http_client.clone(),
+                url.clone(),
+                api_key.clone(),
+            )?)
+        }
+        AudioSource::Downloading {
+            path,
+            len,
+            url,
+            api_key,
+            extension,
+        } => {
+            if let Some(ext) = extension {
+                hint.with_extension(ext);
+            }
+            // Gone once the download has been moved into the cache, or
+            // abandoned; streaming still works
+            Box::new(DownloadingMediaSource::new(
+                File::open(path).ok(),
+                *len,
+                http_client.clone(),
+                url.clone(),
+                api_key.clone(),
+            ))
+        }
+    };
+    let mss = MediaSourceStream::new(media_source, Default::default());
 
     let probed = probe.format(
         &hint,
@@ -310,7 +1173,7 @@ fn load_audio(
         &FormatOptions::default(),
         &MetadataOptions::default(),
     )?;
-    let mut format = probed.format;
+    let format = probed.format;
 
     let track = format
         .tracks()
@@ -328,7 +1191,34 @@ fn load_audio(
         })
     });
 
-    let mut decoder = codecs.make(&codec_params, &DecoderOptions::default())?;
+    let decoder = codecs.make(&codec_params, &DecoderOptions::default())?;
+
+    Ok(OpenedTrack {
+        source: source.clone(),
+        format,
+        decoder,
+        track_id,
+        total_duration,
+    })
+}
+
+/// Opens the output and sets up the pipeline. The first decoded packet is
+/// left in `sample_buf` for the caller to write once playback is set up.
+fn start_playback(
+    opened: OpenedTrack,
+    track: TrackInfo,
+    start_position: Duration,
+    speed: f32,
+    device_name: Option<&str>,
+    gain: &Gain,
+) -> Result<PlaybackContext, BoxError> {
+    let OpenedTrack {
+        source,
+        mut format,
+        mut decoder,
+        track_id,
+        total_duration,
+    } = opened;
 
     // Seek to start position if needed
     if start_position > Duration::ZERO {
@@ -363,9 +1253,16 @@ fn load_audio(
         }
     };
 
-    let audio_output = AudioOutput::new(spec)?;
+    let audio_output = AudioOutput::new(spec, device_name, gain)?;
 
-    audio_output.write_samples(first_samples.samples());
+    let limiter = Limiter::new(audio_output.channels, audio_output.rate);
+    let stretch = TimeStretch::new(spec.channels.count(), spec.rate, speed);
+    let converter = Converter::new(
+        spec.channels.count(),
+        spec.rate,
+        audio_output.channels,
+        audio_output.rate,
+    );
 
     let initial_frames = if start_position > Duration::ZERO {
         (start_position.as_secs_f64() * spec.rate as f64) as u64
@@ -373,14 +1270,26 @@ fn load_audio(
         first_samples.len() as u64 / spec.channels.count() as u64
     };
 
-    let sample_buf = SampleBuffer::new(first_samples.len() as u64, spec);
-
     Ok(PlaybackContext {
+        source,
         format,
         decoder,
         track_id,
+        track,
+        next: None,
+        spec,
         audio_output,
-        sample_buf,
+        sample_buf: first_samples,
+        normalizer: None,
+        trimmer: None,
+        trim_buf: Vec::new(),
+        effects: EffectChain::default(),
+        limiter,
+        stretch,
+        stretch_buf: Vec::new(),
+        converter,
+        convert_buf: Vec::new(),
+        boost: 1.0,
         total_frames_decoded: initial_frames,
         total_duration,
     })
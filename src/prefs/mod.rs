@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::api::query::LibraryQuery;
+use crate::app::queue::QueueEntry;
+
+const PREFS_FILE: &str = "preferences.json";
+
+/// Settings changed from inside the app, as opposed to the config file.
+/// Saved to the data directory whenever they change.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Preferences {
+    /// Volume chosen for each book, by item id
+    #[serde(default)]
+    pub item_volumes: HashMap<String, f32>,
+    /// When each book was last paused, in ms since the epoch, by item id
+    #[serde(default)]
+    pub item_paused_at: HashMap<String, i64>,
+    /// Sort and filter chosen for each library, by library id
+    #[serde(default)]
+    pub library_queries: HashMap<String, LibraryQuery>,
+    /// Books and chapter ranges to play next, in order
+    #[serde(default)]
+    pub queue: Vec<QueueEntry>,
+
+    #[serde(skip)]
+    path: Option<PathBuf>,
+}
+
+impl Preferences {
+    /// Loads saved preferences. Missing or unreadable files give defaults.
+    pub fn load() -> Self {
+        let path = match prefs_path() {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!("Preferences won't be saved: {:?}", e);
+                return Self::default();
+            }
+        };
+
+        let mut prefs: Self = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        prefs.path = Some(path);
+        prefs
+    }
+
+    pub fn save(&self) {
+        let Some(ref path) = self.path else {
+            return;
+        };
+
+        let result = serde_json::to_string(self)
+            .context("Failed to serialize preferences")
+            .and_then(|json| {
+                let tmp = path.with_extension("tmp");
+                fs::write(&tmp, json).context("Failed to write preferences")?;
+                fs::rename(&tmp, path).context("Failed to write preferences")
+            });
+        if let Err(e) = result {
+            eprintln!("Failed to save preferences: {:?}", e);
+        }
+    }
+}
+
+fn prefs_path() -> Result<PathBuf> {
+    let dir = dirs::data_dir()
+        .context("Could not find data directory")?
+        .join("decibelle");
+    fs::create_dir_all(&dir).context("Failed to create data directory")?;
+    Ok(dir.join(PREFS_FILE))
+}
@@ -1,5 +1,7 @@
 use crate::config::{Config, ImageProtocol};
+use crate::offline::OfflineStore;
 use ratatui_image::{picker::Picker, protocol::StatefulProtocol};
+use std::collections::HashMap;
 use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
 use std::thread;
 
@@ -13,6 +15,7 @@ pub struct CoverFetcher {
     tx: Sender<CoverMessage>,
     config: Config,
     client: reqwest::blocking::Client,
+    store: Option<OfflineStore>,
 }
 
 impl CoverFetcher {
@@ -24,6 +27,7 @@ impl CoverFetcher {
             tx,
             config,
             client,
+            store: OfflineStore::open().ok(),
         }
     }
 
@@ -33,6 +37,15 @@ impl CoverFetcher {
         let config = self.config.clone();
         let client = self.client.clone();
 
+        let local_cover = self
+            .store
+            .as_ref()
+            .and_then(|s| std::fs::read(s.cover_file(&item_id)).ok());
+        if let Some(data) = local_cover {
+            let _ = self.tx.send(CoverMessage::Loaded { item_id, data });
+            return;
+        }
+
         thread::spawn(move || {
             let cover_url = format!("{}/api/items/{}/cover", config.server_url, item_id);
             match client
@@ -84,6 +97,8 @@ pub struct ImageCache {
     pub picker: Picker,
     pub current_image: Option<StatefulProtocol>,
     pub current_item_id: Option<String>,
+    /// Covers shown on the home view's shelves, by item id
+    pub thumbnails: HashMap<String, StatefulProtocol>,
 }
 
 impl ImageCache {
@@ -119,6 +134,7 @@ impl ImageCache {
             picker,
             current_image: None,
             current_item_id: None,
+            thumbnails: HashMap::new(),
         }
     }
 
@@ -137,6 +153,20 @@ impl ImageCache {
         Ok(())
     }
 
+    pub fn load_thumbnail(&mut self, item_id: &str, image_data: &[u8]) -> Result<(), String> {
+        if self.thumbnails.contains_key(item_id) {
+            return Ok(());
+        }
+
+        let img = image::load_from_memory(image_data)
+            .map_err(|e| format!("Failed to decode image: {}", e))?;
+
+        let protocol = self.picker.new_resize_protocol(img);
+        self.thumbnails.insert(item_id.to_string(), protocol);
+
+        Ok(())
+    }
+
     pub fn clear(&mut self) {
         self.current_image = None;
         self.current_item_id = None;
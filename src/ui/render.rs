@@ -5,15 +5,25 @@ use ratatui::{
     symbols::border,
     text::{Line, Span},
     widgets::{
-        Block, Borders, Clear, List, ListItem, Paragraph, Scrollbar, ScrollbarOrientation,
-        ScrollbarState,
+        Block, Borders, Clear, LineGauge, List, ListItem, ListState, Paragraph, Scrollbar,
+        ScrollbarOrientation, ScrollbarState,
     },
 };
 use ratatui_image::StatefulImage;
 
 use crate::{
-    api::models::{Chapter, LibraryItem},
-    app::state::{App, Focus},
+    api::{
+        models::{Chapter, LibraryItem},
+        query::LibraryQuery,
+    },
+    app::{
+        browse::{self, BrowseMode},
+        menu::Menu,
+        prompt::Prompt,
+        search::SearchField,
+        state::{App, Focus},
+    },
+    events::types::DownloadProgress,
     player::commands::PlayerState,
     ui::{
         cover::ImageCache, format_duration, format_duration_long, format_size,
@@ -22,6 +32,10 @@ use crate::{
 };
 
 const ROUNDED_BORDER: border::Set = border::ROUNDED;
+/// Rows taken by one home shelf: its label, the covers, titles and a gap.
+const SHELF_HEIGHT: u16 = 11;
+/// Columns taken by one book on a home shelf, including the gap.
+const CARD_WIDTH: u16 = 18;
 #[allow(dead_code)]
 const NOTIFICATION_WIDTH: u16 = 40;
 #[allow(dead_code)]
@@ -53,16 +67,100 @@ pub fn render(f: &mut Frame, app: &mut App, image_cache: &mut ImageCache) {
         ])
         .split(area);
 
-    draw_header(f, chunks[0]);
+    draw_header(f, chunks[0], app);
     draw_main_content(f, chunks[1], app, image_cache);
     draw_playback_controls(f, chunks[2], app);
     draw_footer(f, chunks[3], app);
 
     app.layout_regions.controls = Some(chunks[2]);
 
+    if let Some(ref menu) = app.menu {
+        draw_menu(f, area, menu);
+    }
+    if let Some(ref prompt) = app.prompt {
+        draw_prompt(f, area, prompt);
+    }
+
     draw_notifications(f, area, app.notifications.active_notifications());
 }
 
+fn draw_menu(f: &mut Frame, area: Rect, menu: &Menu) {
+    let theme = get_theme();
+
+    let longest = menu
+        .items
+        .iter()
+        .map(|item| item.chars().count())
+        .chain([menu.title.chars().count()])
+        .max()
+        .unwrap_or(0);
+    let width = (longest as u16 + 8).clamp(30, area.width.saturating_sub(4));
+    let height = (menu.items.len() as u16 + 2).min(area.height.saturating_sub(2));
+
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let items: Vec<ListItem> = menu
+        .items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let is_selected = i == menu.selected;
+            let prefix = if is_selected { "> " } else { "  " };
+            let style = if is_selected {
+                theme.selection_style()
+            } else {
+                theme.value_style()
+            };
+            ListItem::new(Span::styled(format!("{}{}", prefix, item), style))
+        })
+        .collect();
+
+    let mut state = ListState::default().with_selected(Some(menu.selected));
+    let list = List::new(items).block(
+        block_with_title(&menu.title)
+            .border_style(theme.border_style(true))
+            .style(Style::default().bg(theme.bg)),
+    );
+
+    f.render_widget(Clear, popup);
+    f.render_stateful_widget(list, popup, &mut state);
+}
+
+fn draw_prompt(f: &mut Frame, area: Rect, prompt: &Prompt) {
+    let theme = get_theme();
+
+    let longest = prompt
+        .title
+        .chars()
+        .count()
+        .max(prompt.input.chars().count() + 2);
+    let width = (longest as u16 + 4).clamp(30, area.width.saturating_sub(4));
+    let height = 3;
+
+    let popup = Rect {
+        x: area.x + area.width.saturating_sub(width) / 2,
+        y: area.y + area.height.saturating_sub(height) / 2,
+        width,
+        height,
+    };
+
+    let input = Paragraph::new(format!("{}▏", prompt.input))
+        .style(theme.value_style())
+        .block(
+            block_with_title(&prompt.title)
+                .border_style(theme.border_style(true))
+                .style(Style::default().bg(theme.bg)),
+        );
+
+    f.render_widget(Clear, popup);
+    f.render_widget(input, popup);
+}
+
 fn draw_notifications(f: &mut Frame, area: Rect, notifications: &[Notification]) {
     let theme = get_theme();
 
@@ -123,9 +221,14 @@ fn draw_notifications(f: &mut Frame, area: Rect, notifications: &[Notification])
     }
 }
 
-fn draw_header(f: &mut Frame, area: Rect) {
+fn draw_header(f: &mut Frame, area: Rect, app: &App) {
     let theme = get_theme();
-    let header = Paragraph::new("Decibelle")
+    let text = if app.offline {
+        "Decibelle (offline)"
+    } else {
+        "Decibelle"
+    };
+    let header = Paragraph::new(text)
         .style(theme.header_style())
         .block(block_with_title(" 🎧 ").border_style(theme.border_style(false)))
         .centered();
@@ -133,6 +236,12 @@ fn draw_header(f: &mut Frame, area: Rect) {
 }
 
 fn draw_main_content(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut ImageCache) {
+    if app.home.is_some() {
+        draw_home(f, area, app, image_cache);
+        return;
+    }
+    app.layout_regions.home = None;
+
     let main_chunks = Layout::default()
         .direction(Direction::Vertical)
         .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
@@ -144,27 +253,253 @@ fn draw_main_content(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut
         .split(main_chunks[0]);
 
     app.layout_regions.library_list = Some(top_chunks[0]);
-    app.layout_regions.chapters = Some(top_chunks[1]);
+
+    let side_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+        .split(top_chunks[1]);
+    let lower_chunks = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(side_chunks[1]);
+    app.layout_regions.chapters = Some(side_chunks[0]);
+    app.layout_regions.bookmarks = Some(lower_chunks[0]);
+    app.layout_regions.queue = Some(lower_chunks[1]);
 
     draw_library_list(f, top_chunks[0], app);
-    draw_chapters(f, top_chunks[1], app);
+    draw_chapters(f, side_chunks[0], app);
+    draw_bookmarks(f, lower_chunks[0], app);
+    draw_queue(f, lower_chunks[1], app);
     draw_now_playing(f, main_chunks[1], app, image_cache);
 }
 
-fn draw_library_list(f: &mut Frame, area: Rect, app: &App) {
+fn draw_home(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut ImageCache) {
+    let theme = get_theme();
+    app.layout_regions.home = Some(area);
+    app.layout_regions.home_cards.clear();
+
+    let Some(home) = &app.home else {
+        return;
+    };
+    let block = block_with_title(" ● Home ").border_style(theme.border_style(true));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    if home.shelves.is_empty() {
+        let message = if home.loading {
+            "Loading..."
+        } else {
+            "Nothing on the shelves yet"
+        };
+        let text_area = Rect {
+            y: inner.y + inner.height / 2,
+            height: 1,
+            ..inner
+        };
+        f.render_widget(
+            Paragraph::new(message)
+                .alignment(Alignment::Center)
+                .style(theme.label_style()),
+            text_area,
+        );
+        return;
+    }
+
+    let visible_shelves = (inner.height / SHELF_HEIGHT).max(1) as usize;
+    let first_shelf = scroll_offset(
+        app.layout_regions.home_offset,
+        home.selected_shelf,
+        visible_shelves,
+    );
+    app.layout_regions.home_offset = first_shelf;
+    let cards = (inner.width / CARD_WIDTH).max(1) as usize;
+
+    for (row, (shelf_index, shelf)) in home
+        .shelves
+        .iter()
+        .enumerate()
+        .skip(first_shelf)
+        .take(visible_shelves)
+        .enumerate()
+    {
+        let y = inner.y + row as u16 * SHELF_HEIGHT;
+        if y + SHELF_HEIGHT > inner.y + inner.height {
+            break;
+        }
+        let is_selected_shelf = shelf_index == home.selected_shelf;
+
+        let label_style = if is_selected_shelf {
+            theme.title_style()
+        } else {
+            theme.label_style()
+        };
+        f.render_widget(
+            Paragraph::new(Line::from(vec![
+                Span::styled(format!(" {}", shelf.label), label_style),
+                Span::styled(format!("  {}", shelf.entities.len()), theme.label_style()),
+            ])),
+            Rect {
+                x: inner.x,
+                y,
+                width: inner.width,
+                height: 1,
+            },
+        );
+
+        // Only the selected shelf scrolls sideways
+        let first_entry = if is_selected_shelf {
+            home.selected_entry.saturating_sub(cards - 1)
+        } else {
+            0
+        };
+
+        for (column, (entry_index, item)) in shelf
+            .entities
+            .iter()
+            .enumerate()
+            .skip(first_entry)
+            .take(cards)
+            .enumerate()
+        {
+            let is_selected = is_selected_shelf && entry_index == home.selected_entry;
+            let x = inner.x + column as u16 * CARD_WIDTH;
+            let width = CARD_WIDTH - 2;
+
+            let cover_area = Rect {
+                x,
+                y: y + 1,
+                width,
+                height: SHELF_HEIGHT - 3,
+            };
+            let cover_block = Block::default()
+                .borders(Borders::ALL)
+                .border_set(ROUNDED_BORDER)
+                .border_style(theme.border_style(is_selected));
+            let image_area = cover_block.inner(cover_area);
+            f.render_widget(cover_block, cover_area);
+            match image_cache.thumbnails.get_mut(&item.id) {
+                Some(protocol) => {
+                    f.render_stateful_widget(StatefulImage::default(), image_area, protocol)
+                }
+                None => f.render_widget(
+                    Paragraph::new("…")
+                        .alignment(Alignment::Center)
+                        .style(theme.label_style()),
+                    Rect {
+                        y: image_area.y + image_area.height / 2,
+                        height: 1,
+                        ..image_area
+                    },
+                ),
+            }
+
+            let title = item
+                .media
+                .as_ref()
+                .and_then(|m| m.metadata.title.as_deref())
+                .unwrap_or("N/A");
+            let progress = match app.item_progress.get(&item.id) {
+                Some(progress) if progress.is_finished => " ✓".to_string(),
+                Some(progress) if progress.progress > 0.0 => {
+                    format!(" {:.0}%", progress.progress * 100.0)
+                }
+                _ => String::new(),
+            };
+            let title_width = (width as usize).saturating_sub(progress.chars().count());
+            let title: String = if title.chars().count() > title_width {
+                title
+                    .chars()
+                    .take(title_width.saturating_sub(1))
+                    .chain(['…'])
+                    .collect()
+            } else {
+                title.to_string()
+            };
+            let title_style = if is_selected {
+                theme.selection_style()
+            } else {
+                theme.value_style()
+            };
+            let title_area = Rect {
+                x,
+                y: y + SHELF_HEIGHT - 2,
+                width,
+                height: 1,
+            };
+            f.render_widget(
+                Paragraph::new(Line::from(vec![
+                    Span::styled(title, title_style),
+                    Span::styled(progress, theme.label_style()),
+                ])),
+                title_area,
+            );
+
+            app.layout_regions.home_cards.push((
+                Rect {
+                    height: SHELF_HEIGHT - 2,
+                    ..cover_area
+                },
+                shelf_index,
+                entry_index,
+            ));
+        }
+    }
+}
+
+fn draw_library_list(f: &mut Frame, area: Rect, app: &mut App) {
     let theme = get_theme();
     let is_focused = app.focus == Focus::Libraries;
     let border_style = theme.border_style(is_focused);
 
-    if app.libraries.len() > 0 {
+    if !app.libraries.is_empty() && app.browse.mode != BrowseMode::Books {
+        draw_browse_list(f, area, app);
+    } else if !app.libraries.is_empty() {
         let selected_library = app.libraries[app.selected_library_index].clone();
-        let title = format!(" ● {} ", selected_library.name);
+        let query = app
+            .prefs
+            .library_queries
+            .get(&selected_library.id)
+            .filter(|query| **query != LibraryQuery::default());
+        let title = match (
+            app.search.as_ref().filter(|s| s.stashed_items.is_some()),
+            query,
+        ) {
+            (Some(search), _) => format!(
+                " ● {}: results for \"{}\" ",
+                selected_library.name, search.query
+            ),
+            (None, Some(query)) => {
+                let arrow = if query.descending { "↓" } else { "↑" };
+                let mut title = format!(
+                    " ● {} · {} {}",
+                    selected_library.name,
+                    query.sort.label(),
+                    arrow
+                );
+                if let Some(filter) = &query.filter {
+                    title.push_str(&format!(" · {}", filter.label()));
+                }
+                title.push(' ');
+                title
+            }
+            (None, None) => format!(" ● {} ", selected_library.name),
+        };
+
+        // Only the visible rows are built, libraries can be large
+        let visible = area.height.saturating_sub(2) as usize;
+        let offset = scroll_offset(
+            app.layout_regions.library_offset,
+            app.selected_library_item_index,
+            visible,
+        );
+        app.layout_regions.library_offset = offset;
 
         let items: Vec<ListItem> = app
             .library_items
-            .clone()
-            .into_iter()
+            .iter()
             .enumerate()
+            .skip(offset)
+            .take(visible)
             .map(|(i, item)| {
                 let is_selected = i == app.selected_library_item_index;
                 let prefix = if is_selected { "> " } else { "  " };
@@ -174,18 +509,65 @@ fn draw_library_list(f: &mut Frame, area: Rect, app: &App) {
                     .and_then(|m| m.metadata.title.as_ref())
                     .map(|s| s.as_str())
                     .unwrap_or("N/A");
-                let text = format!("{}{}", prefix, title);
                 let style = if is_focused && is_selected {
                     theme.selection_style()
                 } else {
                     theme.value_style()
                 };
-                ListItem::new(text).style(style)
+                let match_style = style.fg(theme.accent_alt).add_modifier(Modifier::BOLD);
+
+                let mut spans = vec![Span::styled(prefix, style)];
+                match app
+                    .search
+                    .as_ref()
+                    .and_then(|s| s.match_for(i))
+                    .and_then(|m| m.field.as_ref())
+                {
+                    Some(m) if m.field == SearchField::Title => {
+                        spans.extend(highlight_spans(&m.text, &m.positions, style, match_style));
+                    }
+                    Some(m) => {
+                        spans.push(Span::styled(title, style));
+                        spans.push(Span::styled(" · ", theme.label_style()));
+                        spans.extend(highlight_spans(
+                            &m.text,
+                            &m.positions,
+                            theme.label_style(),
+                            match_style,
+                        ));
+                    }
+                    None => spans.push(Span::styled(title, style)),
+                }
+                if app.offline_items.contains(&item.id) && !app.offline {
+                    spans.push(Span::styled(" 󰇚", style.fg(theme.accent)));
+                }
+                ListItem::new(Line::from(spans))
             })
             .collect();
 
+        let mut block = block_with_title(&title).border_style(border_style);
+        if let Some(search) = &app.search {
+            let cursor = if search.editing { "▏" } else { "" };
+            let count = match search.matches.len() {
+                0 => " no matches ".to_string(),
+                n => format!(" {}/{} ", search.current + 1, n),
+            };
+            block = block
+                .title_bottom(Line::from(format!(" /{}{} ", search.query, cursor)))
+                .title_bottom(Line::from(count).right_aligned());
+        } else if app.library_items.len() < app.items_total {
+            block = block.title_bottom(
+                Line::from(format!(
+                    " {} of {} ",
+                    app.library_items.len(),
+                    app.items_total
+                ))
+                .right_aligned(),
+            );
+        }
+
         let list = List::new(items)
-            .block(block_with_title(&title).border_style(border_style))
+            .block(block)
             .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
 
         f.render_widget(list, area);
@@ -206,6 +588,154 @@ fn draw_library_list(f: &mut Frame, area: Rect, app: &App) {
     }
 }
 
+/// The library panel while it lists groups of books, or one group's books.
+fn draw_browse_list(f: &mut Frame, area: Rect, app: &mut App) {
+    let theme = get_theme();
+    let is_focused = app.focus == Focus::Libraries;
+    let border_style = theme.border_style(is_focused);
+
+    let visible = area.height.saturating_sub(2) as usize;
+    let selected = app.browse.selected();
+    let offset = scroll_offset(app.layout_regions.library_offset, selected, visible);
+    app.layout_regions.library_offset = offset;
+
+    let progress_of = |item: &LibraryItem| match app.item_progress.get(&item.id) {
+        Some(progress) if progress.is_finished => "✓".to_string(),
+        Some(progress) if progress.progress > 0.0 => format!("{:.0}%", progress.progress * 100.0),
+        _ => String::new(),
+    };
+
+    let (title, rows): (String, Vec<(String, String)>) = match app.browse.open() {
+        Some(group) => (
+            format!(" ● {} · {} ", app.browse.mode.label(), group.name),
+            group
+                .books
+                .iter()
+                .skip(offset)
+                .take(visible)
+                .map(|book| {
+                    let title = book
+                        .media
+                        .as_ref()
+                        .and_then(|m| m.metadata.title.clone())
+                        .unwrap_or_else(|| "N/A".to_string());
+                    let label = match browse::series_sequence(book, &group.id) {
+                        Some(sequence) => format!("{}. {}", sequence, title),
+                        None => title,
+                    };
+                    (label, progress_of(book))
+                })
+                .collect(),
+        ),
+        None => (
+            format!(" ● {} ", app.browse.mode.label()),
+            app.browse
+                .groups
+                .iter()
+                .skip(offset)
+                .take(visible)
+                .map(|group| {
+                    let finished = group
+                        .books
+                        .iter()
+                        .filter(|book| {
+                            app.item_progress
+                                .get(&book.id)
+                                .is_some_and(|progress| progress.is_finished)
+                        })
+                        .count();
+                    let detail = match finished {
+                        0 => format!("{} books", group.count),
+                        _ => format!("{}/{} finished", finished, group.count),
+                    };
+                    (group.name.clone(), detail)
+                })
+                .collect(),
+        ),
+    };
+
+    let items: Vec<ListItem> = rows
+        .into_iter()
+        .enumerate()
+        .map(|(row, (label, detail))| {
+            let is_selected = offset + row == selected;
+            let prefix = if is_selected { "> " } else { "  " };
+            let style = if is_focused && is_selected {
+                theme.selection_style()
+            } else {
+                theme.value_style()
+            };
+
+            let label = format!("{}{}", prefix, label);
+            let padding = (area.width as usize)
+                .saturating_sub(label.chars().count() + detail.chars().count() + 4);
+            ListItem::new(Line::from(vec![
+                Span::styled(label, style),
+                Span::styled(" ".repeat(padding), style),
+                Span::styled(detail, theme.label_style()),
+            ]))
+        })
+        .collect();
+
+    let block = block_with_title(&title).border_style(border_style);
+    if items.is_empty() {
+        let message = if app.browse.loading {
+            "Loading..."
+        } else {
+            "Nothing to show"
+        };
+        f.render_widget(
+            Paragraph::new(message)
+                .alignment(Alignment::Center)
+                .style(theme.label_style())
+                .block(block),
+            area,
+        );
+        return;
+    }
+
+    f.render_widget(List::new(items).block(block), area);
+}
+
+/// First row to show so that `selected` is visible, scrolling as little as
+/// possible from `offset`.
+fn scroll_offset(offset: usize, selected: usize, visible: usize) -> usize {
+    if selected < offset {
+        selected
+    } else if visible > 0 && selected >= offset + visible {
+        selected + 1 - visible
+    } else {
+        offset
+    }
+}
+
+/// Splits `text` into spans with the chars at `positions` highlighted.
+fn highlight_spans(
+    text: &str,
+    positions: &[usize],
+    style: Style,
+    match_style: Style,
+) -> Vec<Span<'static>> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut run = String::new();
+    let mut run_matched = false;
+
+    for (i, c) in text.chars().enumerate() {
+        let matched = positions.binary_search(&i).is_ok();
+        if matched != run_matched && !run.is_empty() {
+            let style = if run_matched { match_style } else { style };
+            spans.push(Span::styled(std::mem::take(&mut run), style));
+        }
+        run_matched = matched;
+        run.push(c);
+    }
+    if !run.is_empty() {
+        let style = if run_matched { match_style } else { style };
+        spans.push(Span::styled(run, style));
+    }
+    spans
+}
+
 fn draw_now_playing(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut ImageCache) {
     let theme = get_theme();
     let is_focused = app.focus == Focus::InfoPanel;
@@ -214,6 +744,7 @@ fn draw_now_playing(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut
     f.render_widget(block, area);
 
     app.layout_regions.info_panel = Some(area);
+    app.layout_regions.info_links.clear();
 
     match (&app.current_library_item, &app.current_chapter) {
         (Some(item), Some(chapter)) => {
@@ -221,7 +752,7 @@ fn draw_now_playing(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut
                 .direction(Direction::Horizontal)
                 .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
                 .split(inner);
-            draw_info_panel(
+            app.layout_regions.info_links = draw_info_panel(
                 f,
                 panels[0],
                 item,
@@ -237,7 +768,7 @@ fn draw_now_playing(f: &mut Frame, area: Rect, app: &mut App, image_cache: &mut
                 .direction(Direction::Horizontal)
                 .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
                 .split(inner);
-            draw_info_panel(
+            app.layout_regions.info_links = draw_info_panel(
                 f,
                 panels[0],
                 item,
@@ -273,7 +804,7 @@ fn draw_info_panel(
     _current_pos: f64,
     scroll: &mut u16,
     is_focused: bool,
-) {
+) -> Vec<(u16, BrowseMode)> {
     let theme = get_theme();
     let media = match &item.media {
         Some(m) => m,
@@ -282,13 +813,19 @@ fn draw_info_panel(
                 Paragraph::new("No media information").style(theme.label_style()),
                 area,
             );
-            return;
+            return Vec::new();
         }
     };
 
     let metadata = &media.metadata;
     let label = theme.label_style();
     let value = theme.value_style();
+    // Author and narrator lines open their books when selected
+    let link = if is_focused {
+        value.add_modifier(Modifier::UNDERLINED)
+    } else {
+        value
+    };
 
     // Build all content lines
     let mut lines: Vec<Line> = Vec::new();
@@ -308,17 +845,19 @@ fn draw_info_panel(
     ]));
 
     // Author
+    let author_line = lines.len() as u16;
     let author = metadata.author_name.as_deref().unwrap_or("Unknown");
     lines.push(Line::from(vec![
         Span::styled("Author:    ", label),
-        Span::styled(author, value),
+        Span::styled(author, link),
     ]));
 
     // Narrator
+    let narrator_line = lines.len() as u16;
     let narrator = metadata.narrator_name.as_deref().unwrap_or("-");
     lines.push(Line::from(vec![
         Span::styled("Narrator:  ", label),
-        Span::styled(narrator, value),
+        Span::styled(narrator, link),
     ]));
 
     // Series
@@ -373,48 +912,48 @@ fn draw_info_panel(
     // Spacer
     lines.push(Line::from(""));
 
-    if let Some(description) = &metadata.description {
-        if !description.is_empty() {
-            let plain_desc = description
-                .replace("<br>", " ")
-                .replace("<br/>", " ")
-                .replace("<br />", " ")
-                .replace("</p>", " ")
-                .replace("<p>", "");
-            let re_cleaned: String = plain_desc
-                .chars()
-                .fold((String::new(), false), |(mut acc, in_tag), c| {
-                    if c == '<' {
-                        (acc, true)
-                    } else if c == '>' {
-                        (acc, false)
-                    } else if !in_tag {
-                        acc.push(c);
-                        (acc, false)
-                    } else {
-                        (acc, true)
-                    }
-                })
-                .0;
-
-            let desc_width = area.width.saturating_sub(4) as usize;
-            let words: Vec<&str> = re_cleaned.trim().split_whitespace().collect();
-            let mut current_line = String::new();
-
-            for word in words {
-                if current_line.is_empty() {
-                    current_line = word.to_string();
-                } else if current_line.len() + 1 + word.len() <= desc_width {
-                    current_line.push(' ');
-                    current_line.push_str(word);
+    if let Some(description) = &metadata.description
+        && !description.is_empty()
+    {
+        let plain_desc = description
+            .replace("<br>", " ")
+            .replace("<br/>", " ")
+            .replace("<br />", " ")
+            .replace("</p>", " ")
+            .replace("<p>", "");
+        let re_cleaned: String = plain_desc
+            .chars()
+            .fold((String::new(), false), |(mut acc, in_tag), c| {
+                if c == '<' {
+                    (acc, true)
+                } else if c == '>' {
+                    (acc, false)
+                } else if !in_tag {
+                    acc.push(c);
+                    (acc, false)
                 } else {
-                    lines.push(Line::from(Span::styled(current_line.clone(), value)));
-                    current_line = word.to_string();
+                    (acc, true)
                 }
+            })
+            .0;
+
+        let desc_width = area.width.saturating_sub(4) as usize;
+        let words: Vec<&str> = re_cleaned.split_whitespace().collect();
+        let mut current_line = String::new();
+
+        for word in words {
+            if current_line.is_empty() {
+                current_line = word.to_string();
+            } else if current_line.len() + 1 + word.len() <= desc_width {
+                current_line.push(' ');
+                current_line.push_str(word);
+            } else {
+                lines.push(Line::from(Span::styled(current_line.clone(), value)));
+                current_line = word.to_string();
             }
-            if !current_line.is_empty() {
-                lines.push(Line::from(Span::styled(current_line, value)));
-            }
+        }
+        if !current_line.is_empty() {
+            lines.push(Line::from(Span::styled(current_line, value)));
         }
     }
 
@@ -435,6 +974,15 @@ fn draw_info_panel(
     let para = Paragraph::new(lines).scroll((*scroll, 0));
     f.render_widget(para, inner_area);
 
+    let links: Vec<(u16, BrowseMode)> = [
+        (author_line, BrowseMode::Authors),
+        (narrator_line, BrowseMode::Narrators),
+    ]
+    .into_iter()
+    .filter(|(line, _)| *line >= *scroll && *line - *scroll < visible_height)
+    .map(|(line, mode)| (inner_area.y + line - *scroll, mode))
+    .collect();
+
     if total_lines > visible_height {
         let scrollbar_area = Rect {
             x: area.x + area.width - 2,
@@ -464,6 +1012,8 @@ fn draw_info_panel(
             );
         }
     }
+
+    links
 }
 
 fn draw_thumbnail(f: &mut Frame, area: Rect, item: &LibraryItem, image_cache: &mut ImageCache) {
@@ -481,24 +1031,24 @@ fn draw_thumbnail(f: &mut Frame, area: Rect, item: &LibraryItem, image_cache: &m
     let bg_fill = Block::default().style(Style::default().bg(theme.bg));
     f.render_widget(bg_fill, image_area);
 
-    if image_cache.current_item_id.as_deref() == Some(&item.id) {
-        if let Some(ref mut protocol) = image_cache.current_image {
-            let max_height = image_area.height;
-            let max_width = image_area.width;
+    if image_cache.current_item_id.as_deref() == Some(&item.id)
+        && let Some(ref mut protocol) = image_cache.current_image
+    {
+        let max_height = image_area.height;
+        let max_width = image_area.width;
 
-            let thumb_height = max_height.min(max_width / 2).max(1);
-            let thumb_width = thumb_height * 2;
+        let thumb_height = max_height.min(max_width / 2).max(1);
+        let thumb_width = thumb_height * 2;
 
-            let centered_area = Rect {
-                x: image_area.x + (image_area.width.saturating_sub(thumb_width)) / 2,
-                y: image_area.y + (image_area.height.saturating_sub(thumb_height)) / 2,
-                width: thumb_width,
-                height: thumb_height,
-            };
+        let centered_area = Rect {
+            x: image_area.x + (image_area.width.saturating_sub(thumb_width)) / 2,
+            y: image_area.y + (image_area.height.saturating_sub(thumb_height)) / 2,
+            width: thumb_width,
+            height: thumb_height,
+        };
 
-            f.render_stateful_widget(StatefulImage::default(), centered_area, protocol);
-            return;
-        }
+        f.render_stateful_widget(StatefulImage::default(), centered_area, protocol);
+        return;
     }
 
     let text_area = Rect {
@@ -515,7 +1065,7 @@ fn draw_thumbnail(f: &mut Frame, area: Rect, item: &LibraryItem, image_cache: &m
     );
 }
 
-fn draw_chapters(f: &mut Frame, area: Rect, app: &App) {
+fn draw_chapters(f: &mut Frame, area: Rect, app: &mut App) {
     let theme = get_theme();
     let is_focused = app.focus == Focus::Chapters;
     let border_style = theme.border_style(is_focused);
@@ -572,9 +1122,92 @@ fn draw_chapters(f: &mut Frame, area: Rect, app: &App) {
         .block(block_with_title(" ● Chapters ").border_style(border_style))
         .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
 
-    let mut list_state =
-        ratatui::widgets::ListState::default().with_selected(Some(app.selected_chapter_index));
+    let mut list_state = ratatui::widgets::ListState::default()
+        .with_offset(app.layout_regions.chapters_offset)
+        .with_selected(Some(app.selected_chapter_index));
+    f.render_stateful_widget(list, area, &mut list_state);
+    app.layout_regions.chapters_offset = list_state.offset();
+}
+
+fn draw_bookmarks(f: &mut Frame, area: Rect, app: &mut App) {
+    let theme = get_theme();
+    let is_focused = app.focus == Focus::Bookmarks;
+    let border_style = theme.border_style(is_focused);
+
+    let items: Vec<ListItem> = app
+        .bookmarks
+        .iter()
+        .enumerate()
+        .map(|(i, bookmark)| {
+            let is_selected = i == app.selected_bookmark_index;
+            let prefix = if is_selected { "> " } else { "  " };
+
+            let style = if is_focused && is_selected {
+                theme.selection_style()
+            } else {
+                theme.value_style()
+            };
+
+            ListItem::new(Line::from(vec![
+                Span::styled(prefix, style),
+                Span::styled(format_duration(bookmark.time), theme.label_style()),
+                Span::styled(format!("  {}", bookmark.title), style),
+            ]))
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(block_with_title(" ● Bookmarks ").border_style(border_style))
+        .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
+
+    let mut list_state = ratatui::widgets::ListState::default()
+        .with_offset(app.layout_regions.bookmarks_offset)
+        .with_selected(Some(app.selected_bookmark_index));
+    f.render_stateful_widget(list, area, &mut list_state);
+    app.layout_regions.bookmarks_offset = list_state.offset();
+}
+
+fn draw_queue(f: &mut Frame, area: Rect, app: &mut App) {
+    let theme = get_theme();
+    let is_focused = app.focus == Focus::Queue;
+    let border_style = theme.border_style(is_focused);
+
+    let items: Vec<ListItem> = app
+        .prefs
+        .queue
+        .iter()
+        .enumerate()
+        .map(|(i, entry)| {
+            let is_selected = i == app.selected_queue_index;
+            let prefix = if is_selected { "> " } else { "  " };
+
+            let style = if is_focused && is_selected {
+                theme.selection_style()
+            } else {
+                theme.value_style()
+            };
+
+            ListItem::new(Line::from(vec![
+                Span::styled(prefix, style),
+                Span::styled(format!("{}. ", i + 1), theme.label_style()),
+                Span::styled(entry.label(), style),
+            ]))
+        })
+        .collect();
+
+    let title = match app.prefs.queue.len() {
+        0 => " ● Up Next ".to_string(),
+        n => format!(" ● Up Next ({}) ", n),
+    };
+    let list = List::new(items)
+        .block(block_with_title(&title).border_style(border_style))
+        .highlight_spacing(ratatui::widgets::HighlightSpacing::Always);
+
+    let mut list_state = ratatui::widgets::ListState::default()
+        .with_offset(app.layout_regions.queue_offset)
+        .with_selected(Some(app.selected_queue_index));
     f.render_stateful_widget(list, area, &mut list_state);
+    app.layout_regions.queue_offset = list_state.offset();
 }
 
 fn draw_playback_controls(f: &mut Frame, area: Rect, app: &App) {
@@ -624,7 +1257,35 @@ fn draw_playback_controls(f: &mut Frame, area: Rect, app: &App) {
     .style(theme.value_style());
     f.render_widget(controls, controls_row[1]);
 
-    if app.is_downloading {
+    let volume = if app.muted {
+        "󰖁 Muted".to_string()
+    } else {
+        format!("󰕾 {:.0}%", app.volume * 100.0)
+    };
+    let boost = if app.voice_boost { "  󰗅 Boost" } else { "" };
+    let saved = if app.trim_silence || !app.time_saved.is_zero() {
+        format!(
+            "  󰅒 -{}",
+            format_duration_long(app.time_saved.as_secs_f64())
+        )
+    } else {
+        String::new()
+    };
+    let sleep = app
+        .sleep_remaining()
+        .map(|remaining| format!("  󰒲 {}", format_duration(remaining.as_secs_f64())))
+        .unwrap_or_default();
+    let status = Paragraph::new(format!(
+        "󰓅 {:.1}x  {}{}{}{}",
+        app.playback_speed, volume, boost, saved, sleep
+    ))
+    .alignment(Alignment::Left)
+    .style(Style::new().fg(theme.info));
+    f.render_widget(status, controls_row[0]);
+
+    if let Some(ref progress) = app.download_progress {
+        draw_download_gauge(f, controls_row[2], progress);
+    } else if app.is_downloading {
         let loading_text = format!("{} Loading...", &app.loading_animation.current_frame());
         let loading = Paragraph::new(loading_text)
             .alignment(Alignment::Right)
@@ -677,6 +1338,24 @@ fn draw_playback_controls(f: &mut Frame, area: Rect, app: &App) {
     );
 }
 
+fn draw_download_gauge(f: &mut Frame, area: Rect, progress: &DownloadProgress) {
+    let theme = get_theme();
+
+    let label = format!(
+        "󰇚 {}/{} {}/s",
+        format_size(progress.bytes_done as i64),
+        format_size(progress.bytes_total as i64),
+        format_size(progress.bytes_per_sec as i64),
+    );
+
+    let gauge = LineGauge::default()
+        .ratio(progress.ratio())
+        .label(Span::styled(label, Style::new().fg(theme.accent)))
+        .filled_style(Style::new().fg(theme.accent))
+        .unfilled_style(Style::new().fg(theme.fg_dim));
+    f.render_widget(gauge, area);
+}
+
 fn draw_progress_bar(
     f: &mut Frame,
     area: Rect,
@@ -718,11 +1397,11 @@ fn draw_progress_bar(
     let mut slider = String::new();
     for i in 0..slider_width {
         if i < filled.saturating_sub(1) {
-            slider.push_str("━");
+            slider.push('━');
         } else if i == filled.saturating_sub(1) || (filled == 0 && i == 0) {
-            slider.push_str("●");
+            slider.push('●');
         } else {
-            slider.push_str("─");
+            slider.push('─');
         }
     }
 
@@ -756,16 +1435,42 @@ fn draw_progress_bar(
 fn draw_footer(f: &mut Frame, area: Rect, app: &App) {
     let theme = get_theme();
     let keybinds = match app.focus {
+        _ if app.home.is_some() => {
+            "↑↓/jk: Shelves | ←→/hl: Books | Enter: Play | g/Esc: Close Home | n: Toggle Time | Space: Pause | q: Quit"
+        }
+        Focus::Libraries if app.search.as_ref().is_some_and(|s| s.editing) => {
+            "Type to search title, author, narrator, series | Enter: Done | Esc: Cancel"
+        }
+        Focus::Libraries if app.search.is_some() => {
+            "↑↓/jk: Navigate | n/N: Next/Prev Match | /: New Search | Esc: Clear Search | →/l/Enter: Select | Tab: Focus | q: Quit"
+        }
+        Focus::Libraries if app.browse.mode == BrowseMode::Playlists => {
+            "↑↓/jk: Navigate | →/l/Enter: Open | ←/h/Esc: Back | P: Play Playlist | p: Playlist | L/H: Switch Library | Tab: Focus | q: Quit"
+        }
+        Focus::Libraries if app.browse.mode.is_pseudo_library() => {
+            "↑↓/jk: Navigate | →/l/Enter: Open | ←/h/Esc: Back | p: Playlist | L/H: Switch Library | Tab: Focus | q: Quit"
+        }
+        Focus::Libraries if app.browse.mode != BrowseMode::Books => {
+            "↑↓/jk: Navigate | →/l/Enter: Open | ←/h/Esc: Back | v: Next View | p: Playlist | L/H: Switch Library | Tab: Focus | q: Quit"
+        }
         Focus::Libraries => {
-            "↑↓/jk: Navigate | →/l/Enter: Select | L/H: Switch Library | Tab: Focus | n: Toggle Time | Space: Pause | q: Quit"
+            "↑↓/jk: Navigate | /: Search | S/F: Sort/Filter | v: Series/Authors/Narrators | p: Playlist | u: Queue | g: Home | →/l/Enter: Select | L/H: Switch Library | d/D: Offline Copy | Tab: Focus | n: Toggle Time | Space: Pause | q: Quit"
         }
         Focus::Chapters => {
-            "↑↓/jk: Navigate | ←/h: Back | Enter: Play Chapter | Tab: Focus | n: Toggle Time | Space: Pause | q: Quit"
+            "↑↓/jk: Navigate | ←/h: Back | Enter: Play Chapter | u: Queue Chapter | Tab: Focus | n: Toggle Time | Space: Pause | q: Quit"
+        }
+        Focus::Bookmarks => {
+            "↑↓/jk: Navigate | Enter: Jump | a: Add | e: Edit | x: Delete | Tab: Focus | Space: Pause | q: Quit"
+        }
+        Focus::Queue => {
+            "↑↓/jk: Navigate | Enter: Play Now | J/K: Move Down/Up | x: Remove | Tab: Focus | Space: Pause | q: Quit"
         }
         Focus::Controls => {
-            "←→/hl: ±5s | ←→(global): ±30s | Space: Play/Pause | n: Toggle Time | Tab: Focus | q: Quit"
+            "←→/hl: ±5s | ←→(global): ±30s | Space: Play/Pause | [/]: Speed | -/+: Volume | m: Mute | b: Voice Boost | s: Trim Silence | t: Sleep Timer | o: Output | n: Toggle Time | Tab: Focus | q: Quit"
+        }
+        Focus::InfoPanel => {
+            "↑↓/jk: Scroll | Enter: Books by Author/Narrator | Tab: Focus | n: Toggle Time | Space: Pause | q: Quit"
         }
-        Focus::InfoPanel => "↑↓/jk: Scroll | Tab: Focus | n: Toggle Time | Space: Pause | q: Quit",
     };
 
     f.render_widget(